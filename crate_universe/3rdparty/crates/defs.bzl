@@ -321,6 +321,7 @@ _NORMAL_DEPENDENCIES = {
             "tera": Label("@cui//:tera-1.20.0"),
             "textwrap": Label("@cui//:textwrap-0.16.2"),
             "toml": Label("@cui//:toml-0.8.21"),
+            "toml_edit": Label("@cui//:toml_edit-0.22.25"),
             "tracing": Label("@cui//:tracing-0.1.41"),
             "tracing-subscriber": Label("@cui//:tracing-subscriber-0.3.19"),
             "url": Label("@cui//:url-2.5.4"),
@@ -3294,6 +3295,7 @@ def crate_repositories():
         struct(repo = "cui__tera-1.20.0", is_dev_dep = False),
         struct(repo = "cui__textwrap-0.16.2", is_dev_dep = False),
         struct(repo = "cui__toml-0.8.21", is_dev_dep = False),
+        struct(repo = "cui__toml_edit-0.22.25", is_dev_dep = False),
         struct(repo = "cui__tracing-0.1.41", is_dev_dep = False),
         struct(repo = "cui__tracing-subscriber-0.3.19", is_dev_dep = False),
         struct(repo = "cui__url-2.5.4", is_dev_dep = False),
@@ -72,6 +72,16 @@ If you are using from_cargo and are pulling dependencies from a private crate re
 make sure you set the `CARGO_BAZEL_ISOLATED=false bazel build //...` environmental.  If not `crates_universe`
 will not be able to pull from your private registry.
 
+Registries which require authentication can be configured with a `credential-provider` in the Cargo config
+passed to `cargo_config`. Cargo reads the index of these registries with the provider's token when repinning.
+The generated `http_archive`s for crates from these registries set `auth_patterns` so Bazel sends the password
+of a matching `~/.netrc` entry as the `Authorization` header:
+
+```text
+machine my-registry.example.com
+password <token>
+```
+
 The generated crates_repository contains helper macros which make collecting dependencies for Bazel targets simpler.
 Notably, the all_crate_deps and aliases macros (
 see [Dependencies API](https://bazelbuild.github.io/rules_rust/crate_universe.html#dependencies-api)) commonly allow the
@@ -501,7 +511,7 @@ def _collect_render_config(module, repository):
         # bzlmod doesn't allow passing `None` as a default parameter to indicate a value was
         # not provided. So for backward compatibility, certain empty values are assumed to be
         # not provided and thus are converted explicitly to `None`.
-        for null_defaults in ["vendor_mode", "regen_command", "default_package_name"]:
+        for null_defaults in ["vendor_mode", "regen_command", "default_package_name", "crates_module_sharding"]:
             if config_kwargs[null_defaults] == "":
                 config_kwargs[null_defaults] = None
 
@@ -595,6 +605,11 @@ def _generate_hub_and_spokes(
             generate_binaries = cfg.generate_binaries,
             render_config = render_config,
             repository_ctx = module_ctx,
+            version_resolution = cfg.version_resolution,
+            enforce_rust_version = cfg.enforce_rust_version,
+            deny_yanked = cfg.deny_yanked,
+            default_annotations = cfg.default_annotations,
+            disabled_default_annotations = cfg.disabled_default_annotations,
         ),
     )
 
@@ -877,6 +892,7 @@ def _crate_impl(module_ctx):
             if annotation_dict.pop("gen_all_binaries"):
                 annotation_dict["gen_binaries"] = True
             annotation_dict["gen_build_script"] = _OPT_BOOL_VALUES[annotation_dict["gen_build_script"]]
+            annotation_dict["gen_rust_doc"] = _OPT_BOOL_VALUES[annotation_dict["gen_rust_doc"]]
 
             # Process the override targets for the annotation.
             # In the non-bzlmod approach, this is given as a dict
@@ -1028,6 +1044,10 @@ def _crate_impl(module_ctx):
 _FROM_COMMON_ATTRS = {
     "cargo_config": CRATES_VENDOR_ATTRS["cargo_config"],
     "cargo_lockfile": CRATES_VENDOR_ATTRS["cargo_lockfile"],
+    "default_annotations": CRATES_VENDOR_ATTRS["default_annotations"],
+    "deny_yanked": CRATES_VENDOR_ATTRS["deny_yanked"],
+    "disabled_default_annotations": CRATES_VENDOR_ATTRS["disabled_default_annotations"],
+    "enforce_rust_version": CRATES_VENDOR_ATTRS["enforce_rust_version"],
     "generate_binaries": CRATES_VENDOR_ATTRS["generate_binaries"],
     "generate_build_scripts": CRATES_VENDOR_ATTRS["generate_build_scripts"],
     "host_tools": attr.label(
@@ -1054,6 +1074,7 @@ _FROM_COMMON_ATTRS = {
         doc = "A set of all platform triples to consider when generating dependencies.",
         default = SUPPORTED_PLATFORM_TRIPLES,
     ),
+    "version_resolution": CRATES_VENDOR_ATTRS["version_resolution"],
 }
 
 _from_cargo = tag_class(
@@ -1128,6 +1149,9 @@ _annotation = tag_class(
         "crate_features": attr.string_list(
             doc = "A list of strings to add to a crate's `rust_library::crate_features` attribute.",
         ),
+        "crate_features_remove": attr.string_list(
+            doc = "A list of features to remove from the features Cargo resolved for a crate.",
+        ),
         "data": _relative_label_list(
             doc = "A list of labels to add to a crate's `rust_library::data` attribute.",
         ),
@@ -1137,6 +1161,9 @@ _annotation = tag_class(
         "deps": _relative_label_list(
             doc = "A list of labels to add to a crate's `rust_library::deps` attribute.",
         ),
+        "deps_remove": attr.string_list(
+            doc = "A list of crate names to remove from the dependencies Cargo resolved for a crate.",
+        ),
         "disable_pipelining": attr.bool(
             doc = "If True, disables pipelining for library targets for this crate.",
         ),
@@ -1154,6 +1181,11 @@ _annotation = tag_class(
             values = _OPT_BOOL_VALUES.keys(),
             default = "auto",
         ),
+        "gen_rust_doc": attr.string(
+            doc = "An authoritative flag to determine whether or not to produce a `rust_doc` target for the current crate, overriding `render_config.generate_rust_docs`. Supported values are 'on', 'off', and 'auto'.",
+            values = _OPT_BOOL_VALUES.keys(),
+            default = "auto",
+        ),
         "override_target_bin": attr.label(
             doc = "An optional alternate target to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
@@ -1178,6 +1210,9 @@ _annotation = tag_class(
         "proc_macro_deps": _relative_label_list(
             doc = "A list of labels to add to a crate's `rust_library::proc_macro_deps` attribute.",
         ),
+        "proc_macro_deps_remove": attr.string_list(
+            doc = "A list of crate names to remove from the procedural macro dependencies Cargo resolved for a crate.",
+        ),
         "repositories": attr.string_list(
             doc = "A list of repository names specified from `crate.from_cargo(name=...)` that this annotation is applied to. Defaults to all repositories.",
             default = [],
@@ -1307,6 +1342,11 @@ can be found below where the supported keys for each template can be found in th
             doc = "The base template to use for Crate label repository names. The available format keys are [`{repository}`, `{name}`, `{version}`].",
             default = "{repository}__{name}-{version}",
         ),
+        "crates_module_sharding": attr.string(
+            doc = "An optional way to split the dependency data of the `defs.bzl` crates module into `shards/<name>.bzl` files, one for each workspace member `package` or top level `directory`. BUILD files then load `aliases`, `all_crate_deps` and `crate_deps` from the shard of their package, e.g. `load(\"@crates//:shards/app.bzl\", \"all_crate_deps\")`, so only its data is loaded. To migrate, update the loads of these macros in BUILD files; calling them from `defs.bzl` fails with the shard to load instead.",
+            values = ["", "directory", "package"],
+            default = "",
+        ),
         "crates_module_template": attr.string(
             doc = "The pattern to use for the `defs.bzl` and `BUILD.bazel` file names used for the crates module. The available format keys are [`{file}`].",
             default = "//:{file}",
@@ -1330,6 +1370,10 @@ can be found below where the supported keys for each template can be found in th
             doc = "Whether to generate rules license metedata.",
             default = False,
         ),
+        "generate_rust_docs": attr.bool(
+            doc = "Whether to generate a `rust_doc` target for the library of every crate, and a `docs` target combining the documentation of every version of them behind a single index. With a nightly toolchain, crates link to their dependencies and share a search index. Can be overridden for each crate with the `gen_rust_doc` annotation.",
+            default = False,
+        ),
         "generate_target_compatible_with": attr.bool(
             doc = "Whether to generate `target_compatible_with` annotations on the generated BUILD files.  This catches a `target_triple` being targeted that isn't declared in `supported_platform_triples`.",
             default = True,
@@ -1342,6 +1386,10 @@ can be found below where the supported keys for each template can be found in th
             doc = "An optional command to demonstrate how generated files should be regenerated.",
             default = "",
         ),
+        "strict_visibility": attr.bool(
+            doc = "Whether to restrict the aliases of each direct dependency to the packages of the workspace members whose `Cargo.toml` declares it. Dependencies declared with `crate.spec` remain public.",
+            default = False,
+        ),
         "vendor_mode": attr.string(
             doc = "An optional configuration for rendering content to be rendered into repositories.",
             default = "",
@@ -1360,6 +1408,7 @@ Environment Variables:
 | `CARGO_BAZEL_GENERATOR_SHA256` | The sha256 checksum of the file located at `CARGO_BAZEL_GENERATOR_URL` |
 | `CARGO_BAZEL_GENERATOR_URL` | The URL of a cargo-bazel binary. This variable takes precedence over attributes and can use `file://` for local paths |
 | `CARGO_BAZEL_ISOLATED` | An authoritative flag as to whether or not the `CARGO_HOME` environment variable should be isolated from the host configuration |
+| `CARGO_BAZEL_LOCAL_OVERRIDES` | A comma-delimited list of `name=/absolute/path` pairs of local crate checkouts to use in place of crates from crates.io. The dependencies are always re-spliced while this is set but neither the `lockfile` nor the `Cargo.lock` file are updated. |
 | `CARGO_BAZEL_REPIN` | An indicator that the dependencies represented by the rule should be regenerated. `REPIN` may also be used. See [Repinning / Updating Dependencies](#repinning--updating-dependencies) for more details. |
 | `CARGO_BAZEL_REPIN_ONLY` | A comma-delimited allowlist for rules to execute repinning. Can be useful if multiple instances of the repository rule are used in a Bazel workspace, but repinning should be limited to one of them. |
 
@@ -9,6 +9,7 @@ get_host_triple = _get_host_triple
 CARGO_BAZEL_ISOLATED = "CARGO_BAZEL_ISOLATED"
 CARGO_BAZEL_REPIN = "CARGO_BAZEL_REPIN"
 CARGO_BAZEL_DEBUG = "CARGO_BAZEL_DEBUG"
+CARGO_BAZEL_LOCAL_OVERRIDES = "CARGO_BAZEL_LOCAL_OVERRIDES"
 REPIN = "REPIN"
 
 CARGO_BAZEL_REPIN_ONLY = "CARGO_BAZEL_REPIN_ONLY"
@@ -103,22 +103,28 @@ def _annotation(
         compile_data_glob = None,
         compile_data_glob_excludes = None,
         crate_features = None,
+        crate_features_remove = None,
         data = None,
         data_glob = None,
         deps = None,
+        deps_remove = None,
         extra_aliased_targets = None,
         gen_binaries = None,
         disable_pipelining = False,
         gen_build_script = None,
+        gen_rust_doc = None,
         patch_args = None,
         patch_tool = None,
         patches = None,
         proc_macro_deps = None,
+        proc_macro_deps_remove = None,
         rustc_env = None,
         rustc_env_files = None,
         rustc_flags = None,
         shallow_since = None,
-        override_targets = None):
+        override_targets = None,
+        vendor_keep_glob = None,
+        vendor_exclude_glob = None):
     """A collection of extra attributes and settings for a particular crate
 
     Args:
@@ -151,9 +157,13 @@ def _annotation(
             attribute.
         crate_features (optional): A list of strings to add to a crate's `rust_library::crate_features`
             attribute.
+        crate_features_remove (optional): A list of features to remove from the features Cargo resolved for a
+            crate. Use `crate.select` to only remove them on some platforms.
         data (list, optional): A list of labels to add to a crate's `rust_library::data` attribute.
         data_glob (list, optional): A list of glob patterns to add to a crate's `rust_library::data` attribute.
         deps (list, optional): A list of labels to add to a crate's `rust_library::deps` attribute.
+        deps_remove (optional): A list of crate names to remove from the dependencies Cargo resolved for a
+            crate. Use `crate.select` to only remove them on some platforms.
         extra_aliased_targets (dict, optional): A list of targets to add to the generated aliases in the root
             crate_universe repository.
         gen_binaries (list or bool, optional): As a list, the subset of the crate's bins that should get `rust_binary`
@@ -161,6 +171,8 @@ def _annotation(
         disable_pipelining (bool, optional): If True, disables pipelining for library targets for this crate.
         gen_build_script (bool, optional): An authorative flag to determine whether or not to produce
             `cargo_build_script` targets for the current crate.
+        gen_rust_doc (bool, optional): An authorative flag to determine whether or not to produce a `rust_doc`
+            target for the current crate. Overrides [render_config](#render_config)'s `generate_rust_docs`.
         patch_args (list, optional): The `patch_args` attribute of a Bazel repository rule. See
             [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)
         patch_tool (string, optional): The `patch_tool` attribute of a Bazel repository rule. See
@@ -169,6 +181,8 @@ def _annotation(
             [http_archive.patches](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patches)
         proc_macro_deps (list, optional): A list of labels to add to a crate's `rust_library::proc_macro_deps`
             attribute.
+        proc_macro_deps_remove (optional): A list of crate names to remove from the procedural macro dependencies
+            Cargo resolved for a crate. Use `crate.select` to only remove them on some platforms.
         rustc_env (dict, optional): Additional variables to set on a crate's `rust_library::rustc_env` attribute.
         rustc_env_files (list, optional): A list of labels to set on a crate's `rust_library::rustc_env_files`
             attribute.
@@ -177,6 +191,10 @@ def _annotation(
             instead of a crate registry. This flag optimizes fetching the source code.
         override_targets (dict, optional): A dictionary of alternate targets to use when something depends on this crate to allow
             the parent repo to provide its own version of this dependency. Keys can be `proc-marco`, `custom-build`, `lib`, `bin`.
+        vendor_keep_glob (list, optional): Glob patterns of files to keep when `crates_vendor::prune_sources` removes
+            the files of locally vendored crates which no generated target uses.
+        vendor_exclude_glob (list, optional): Glob patterns of files to remove when `crates_vendor::prune_sources` is
+            set, even if a generated target uses them. `vendor_keep_glob` takes precedence.
 
     Returns:
         string: A json encoded string containing the specified version and separately all other inputs.
@@ -209,22 +227,28 @@ def _annotation(
             compile_data_glob = compile_data_glob,
             compile_data_glob_excludes = compile_data_glob_excludes,
             crate_features = crate_features,
+            crate_features_remove = crate_features_remove,
             data = _stringify_list(data),
             data_glob = data_glob,
             deps = _stringify_list(deps),
+            deps_remove = deps_remove,
             extra_aliased_targets = extra_aliased_targets,
             gen_binaries = gen_binaries,
             disable_pipelining = disable_pipelining,
             gen_build_script = gen_build_script,
+            gen_rust_doc = gen_rust_doc,
             patch_args = patch_args,
             patch_tool = patch_tool,
             patches = _stringify_list(patches),
             proc_macro_deps = _stringify_list(proc_macro_deps),
+            proc_macro_deps_remove = proc_macro_deps_remove,
             rustc_env = rustc_env,
             rustc_env_files = _stringify_list(rustc_env_files),
             rustc_flags = rustc_flags,
             shallow_since = shallow_since,
             override_targets = override_targets,
+            vendor_keep_glob = vendor_keep_glob,
+            vendor_exclude_glob = vendor_exclude_glob,
         ),
     ))
 
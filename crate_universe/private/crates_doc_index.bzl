@@ -0,0 +1,278 @@
+"""A rule combining the documentation of third-party crates into one browsable tree"""
+
+load("//rust/private:common.bzl", "rust_common")
+load("//rust/private:providers.bzl", "LintsInfo")
+load("//rust/private:rustdoc.bzl", "rustdoc_compile_action")
+load("//rust/private:utils.bzl", "find_toolchain")
+load("//rust/toolchain/channel:channel.bzl", "RustToolchainChannelInfo")
+
+_RustDocCrateInfo = provider(
+    doc = "The crate a `rust_doc` target documents and how.",
+    fields = {
+        "crate": "Target: The crate to document.",
+        "rustdoc_flags": "list[str]: Flags passed to `rustdoc` for the crate.",
+    },
+)
+
+def _rust_doc_crate_aspect_impl(target, ctx):
+    if ctx.rule.kind != "rust_doc":
+        fail("{} is not a `rust_doc` target".format(target.label))
+
+    return [_RustDocCrateInfo(
+        crate = ctx.rule.attr.crate,
+        rustdoc_flags = ctx.rule.attr.rustdoc_flags,
+    )]
+
+_rust_doc_crate_aspect = aspect(
+    doc = "Collects the crate and flags of a `rust_doc` target.",
+    implementation = _rust_doc_crate_aspect_impl,
+)
+
+_INDEX_TEMPLATE = """\
+<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Crates</title>
+</head>
+<body>
+<h1>Crates</h1>
+<ul>
+{crates}
+</ul>
+</body>
+</html>
+"""
+
+# Copies the documentation of every crate of a tree into it before running the `rustdoc`
+# command which documents its last crate and merges the shared files of all of them.
+_MERGE_COMMAND = """\
+set -e
+tree="$1"
+count="$2"
+shift 2
+while [ "$count" -gt 0 ]; do
+    cp -RL "$1"/. "$tree"/
+    count=$((count - 1))
+    shift
+done
+exec "$@"
+"""
+
+def _version_key(version):
+    """Orders semver versions, with pre-releases before their release.
+
+    Args:
+        version (str): A semver version.
+
+    Returns:
+        tuple: A key for sorting versions.
+    """
+    release, _, pre = version.partition("+")[0].partition("-")
+    numbers = [int(number) if number.isdigit() else 0 for number in release.split(".")]
+    return (numbers, pre == "", pre)
+
+def _crates_doc_index_impl(ctx):
+    toolchain = find_toolchain(ctx)
+
+    crates = {}
+    versions = {}
+    for doc, version in ctx.attr.docs.items():
+        info = doc[_RustDocCrateInfo]
+        crate_info = info.crate[rust_common.crate_info]
+        key = "{}-{}".format(crate_info.name, version)
+        if key in crates:
+            fail("Documentation for multiple crates named `{}` at version {} was requested: {} and {}".format(
+                crate_info.name,
+                version,
+                crates[key].doc.label,
+                doc.label,
+            ))
+        crates[key] = struct(
+            doc = doc,
+            info = info,
+            crate_info = crate_info,
+            name = crate_info.name,
+            version = version,
+        )
+        versions.setdefault(crate_info.name, []).append(version)
+
+    # Merging the documentation of several crates and linking between them relies on unstable
+    # `rustdoc` options and a shell, so it's only done on nightly toolchains outside of Windows.
+    # Otherwise every crate is documented into a tree of its own.
+    merge = ctx.attr._channel[RustToolchainChannelInfo].value == "nightly" and toolchain.exec_triple.system != "windows"
+
+    # `rustdoc` names the documentation of a crate after the crate, so every version of a
+    # crate is documented into a different tree. The first tree holds the newest version of
+    # every crate, the second the next newest, and so on.
+    tree_index = {}
+    for name, crate_versions in versions.items():
+        for index, version in enumerate(sorted(crate_versions, key = _version_key, reverse = True)):
+            key = "{}-{}".format(name, version)
+            tree_index[key] = str(index) if merge else key
+    trees = {}
+    for key in sorted(crates):
+        trees.setdefault(tree_index[key], []).append(key)
+
+    root = "{}.rustdoc".format(ctx.label.name)
+
+    # The documentation of each crate is generated on its own along with the parts of the
+    # shared files, such as the search index, which describe it. The last crate of each tree
+    # is documented into the tree itself, merging the parts of the other crates.
+    outputs = []
+    for tree_name, keys in sorted(trees.items()):
+        tree = ctx.actions.declare_directory("{}/{}".format(root, tree_name))
+        crate_dirs = []
+        parts_dirs = []
+        for key in keys:
+            crate = crates[key]
+            is_last = key == keys[-1]
+            parts_dir = None
+            merge_flags = []
+            if is_last:
+                output_dir = tree
+                if merge:
+                    merge_flags = ["-Zunstable-options", "--merge=finalize"] + [
+                        "--include-parts-dir={}".format(parts_dir.path)
+                        for parts_dir in parts_dirs
+                    ]
+            else:
+                output_dir = ctx.actions.declare_directory("{}.rustdoc_crates/{}".format(ctx.label.name, key))
+                parts_dir = ctx.actions.declare_directory("{}.rustdoc_parts/{}".format(ctx.label.name, key))
+                merge_flags = ["-Zunstable-options", "--merge=none", "--parts-out-dir={}".format(parts_dir.path)]
+
+            # Links to the documentation of dependencies are relative to the directory of the
+            # crate's documentation, one level below the root of its tree.
+            link_flags = []
+            for dependency in ctx.attr.links.get(key, []) if merge else []:
+                if dependency not in crates:
+                    continue
+                dependency_tree = tree_index[dependency]
+                link_flags.append("--extern-html-root-url={}={}".format(
+                    crates[dependency].name,
+                    "../" if dependency_tree == tree_name else "../../{}/".format(dependency_tree),
+                ))
+
+            crate_target = crate.info.crate
+            lints_info = crate_target[LintsInfo] if LintsInfo in crate_target else None
+            action = rustdoc_compile_action(
+                ctx = ctx,
+                toolchain = toolchain,
+                crate_info = crate.crate_info,
+                lints_info = lints_info,
+                output = output_dir,
+                rustdoc_flags = [
+                    "--extern",
+                    "{}={}".format(crate.name, crate.crate_info.output.path),
+                ] + merge_flags + link_flags + crate.info.rustdoc_flags,
+            )
+
+            # The action is created for this target rather than the crate's, so `CARGO_MANIFEST_DIR`
+            # has to be pointed back at the crate's package.
+            env = dict(action.env)
+            components = "${{pwd}}/{}/{}".format(crate_target.label.workspace_root, crate_target.label.package).split("/")
+            env["CARGO_MANIFEST_DIR"] = "/".join([c for c in components if c])
+
+            progress_message = "Generating Rustdoc for {} into {}".format(crate_target.label, ctx.label)
+            if not is_last or not crate_dirs:
+                ctx.actions.run(
+                    mnemonic = "RustdocIndex",
+                    progress_message = progress_message,
+                    outputs = [output_dir, parts_dir] if parts_dir else [output_dir],
+                    executable = action.executable,
+                    inputs = action.inputs,
+                    env = env,
+                    arguments = action.arguments,
+                    tools = action.tools,
+                )
+                if not is_last:
+                    crate_dirs.append(output_dir)
+                    parts_dirs.append(parts_dir)
+                continue
+
+            merge_args = ctx.actions.args()
+            merge_args.add(tree.path)
+            merge_args.add(str(len(crate_dirs)))
+            merge_args.add_all(crate_dirs, expand_directories = False)
+            merge_args.add(action.executable)
+            ctx.actions.run_shell(
+                mnemonic = "RustdocIndex",
+                progress_message = progress_message,
+                outputs = [tree],
+                command = _MERGE_COMMAND,
+                inputs = depset(crate_dirs + parts_dirs, transitive = [action.inputs]),
+                env = env,
+                arguments = [merge_args] + action.arguments,
+                tools = action.tools + [action.executable],
+            )
+        outputs.append(tree)
+
+    index = ctx.actions.declare_file("{}/index.html".format(root))
+    ctx.actions.write(
+        output = index,
+        content = _INDEX_TEMPLATE.format(crates = "\n".join([
+            "<li><a href=\"{tree}/{name}/index.html\">{name}</a> {version}</li>".format(
+                tree = tree_index[key],
+                name = crates[key].name,
+                version = crates[key].version,
+            )
+            for key in sorted(crates, key = lambda key: (crates[key].name, _version_key(crates[key].version)))
+        ])),
+    )
+    outputs.append(index)
+
+    return [
+        DefaultInfo(
+            files = depset(outputs),
+        ),
+        OutputGroupInfo(
+            rustdoc_dir = depset(outputs),
+        ),
+    ]
+
+crates_doc_index = rule(
+    doc = """\
+Generates the documentation of a set of crates, indexed by `<name>.rustdoc/index.html`, in which each
+crate's documentation links to that of its dependencies.
+
+This rule is rendered into the crates module by `crates_repository`, `crates_vendor` and the `crate`
+module extension when `render_config.generate_rust_docs` or the `gen_rust_doc` annotation is used.
+With a nightly toolchain (`@rules_rust//rust/toolchain/channel=nightly`), the shared files of the crates,
+such as the search index, are merged with `rustdoc`'s unstable `--merge` options. `rustdoc` names the
+documentation of a crate after the crate, so the newest version of every crate is documented into
+`<name>.rustdoc/0`, the next newest into `<name>.rustdoc/1` and so on. Search covers every crate of a tree.
+Merging requires a shell, so it isn't done on Windows.
+
+Otherwise each crate is documented into a tree of its own, `<name>.rustdoc/<crate name>-<version>`, and
+doesn't link to the documentation of its dependencies.
+""",
+    implementation = _crates_doc_index_impl,
+    attrs = {
+        "docs": attr.label_keyed_string_dict(
+            doc = "The `rust_doc` targets of the crates to document, mapped to the version of the crate.",
+            aspects = [_rust_doc_crate_aspect],
+            mandatory = True,
+        ),
+        "links": attr.string_list_dict(
+            doc = "The crates whose documentation each crate links to, as `<crate name>-<version>`.",
+        ),
+        "_channel": attr.label(
+            default = Label("//rust/toolchain/channel"),
+        ),
+        "_error_format": attr.label(
+            default = Label("//rust/settings:error_format"),
+        ),
+        "_process_wrapper": attr.label(
+            doc = "A process wrapper for running rustdoc on all platforms",
+            default = Label("//util/process_wrapper"),
+            executable = True,
+            allow_single_file = True,
+            cfg = "exec",
+        ),
+    },
+    fragments = ["cpp"],
+    toolchains = [
+        str(Label("//rust:toolchain_type")),
+        "@bazel_tools//tools/cpp:toolchain_type",
+    ],
+)
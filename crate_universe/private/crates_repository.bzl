@@ -157,6 +157,7 @@ Environment Variables:
 | `CARGO_BAZEL_GENERATOR_SHA256` | The sha256 checksum of the file located at `CARGO_BAZEL_GENERATOR_URL` |
 | `CARGO_BAZEL_GENERATOR_URL` | The URL of a cargo-bazel binary. This variable takes precedence over attributes and can use `file://` for local paths |
 | `CARGO_BAZEL_ISOLATED` | An authoritative flag as to whether or not the `CARGO_HOME` environment variable should be isolated from the host configuration |
+| `CARGO_BAZEL_LOCAL_OVERRIDES` | A comma-delimited list of `name=/absolute/path` pairs of local crate checkouts to use in place of crates from crates.io. The dependencies are always re-spliced while this is set but neither the `lockfile` nor the `Cargo.lock` file are updated. |
 | `CARGO_BAZEL_REPIN` | An indicator that the dependencies represented by the rule should be regenerated. `REPIN` may also be used. See [Repinning / Updating Dependencies](#repinning--updating-dependencies) for more details. |
 | `CARGO_BAZEL_REPIN_ONLY` | A comma-delimited allowlist for rules to execute repinning. Can be useful if multiple instances of the repository rule are used in a Bazel workspace, but repinning should be limited to one of them. |
 
@@ -226,6 +227,9 @@ that is called behind the scenes to update dependencies.
 | `package_name` | `cargo upgrade --package package_name` |
 | `package_name@1.2.3` | `cargo upgrade --package package_name@1.2.3` |
 | `package_name@1.2.3=4.5.6` | `cargo upgrade --package package_name@1.2.3 --precise=4.5.6` |
+| `upgrade` | Raise version requirements in `Cargo.toml` to the newest semver-compatible releases, then `cargo update --workspace` |
+| `upgrade=package_a,package_b` | Raise version requirements of only the listed packages, then `cargo update --package package_a --package package_b` |
+| `upgrade-incompatible[=package_a,...]` | As `upgrade`, but also crosses semver-incompatible versions |
 
 If the `crates_repository` is used multiple times in the same Bazel workspace (e.g. for multiple independent
 Rust workspaces), it may additionally be useful to use the `CARGO_BAZEL_REPIN_ONLY` environment variable, which
@@ -259,6 +263,32 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
             doc = "Wether or not the toolchain names of windows toolchains are expected to be in a `compressed` format.",
             default = True,
         ),
+        "default_annotations": attr.string(
+            doc = (
+                "The version of the annotations built into `cargo-bazel` for crates which commonly need them " +
+                "(e.g. `libz-sys`, `openssl-sys`). These are applied beneath any user provided `annotations`. " +
+                "Built-in annotations are not applied unless a version is set."
+            ),
+            values = ["", "v1"],
+            default = "",
+        ),
+        "deny_yanked": attr.bool(
+            doc = (
+                "Whether or not to fail if a resolved crate has been yanked from its registry. " +
+                "Such crates are reported as warnings along with the packages which depend on them otherwise."
+            ),
+            default = False,
+        ),
+        "disabled_default_annotations": attr.string_list(
+            doc = "The names of crates whose built-in annotations (see `default_annotations`) should not be applied.",
+        ),
+        "enforce_rust_version": attr.bool(
+            doc = (
+                "Whether or not to fail if a resolved crate declares a `rust-version` newer than the rustc in use. " +
+                "Such crates are reported as warnings along with the packages which depend on them otherwise."
+            ),
+            default = False,
+        ),
         "generate_binaries": attr.bool(
             doc = (
                 "Whether to generate `rust_binary` targets for all the binary crates in every package. " +
@@ -361,6 +391,17 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
             doc = "A set of all platform triples to consider when generating dependencies.",
             default = SUPPORTED_PLATFORM_TRIPLES,
         ),
+        "version_resolution": attr.string(
+            doc = (
+                "How Cargo picks dependency versions when generating a new lockfile. `maximal` is Cargo's default " +
+                "behavior. `minimal` and `direct_minimal` select the oldest versions allowed by the requirements " +
+                "of all dependencies or only those of workspace members respectively. This is useful with a " +
+                "separate lockfile to test crates against the oldest versions of dependencies they claim to support. " +
+                "The minimal modes rely on unstable Cargo flags and require a nightly `cargo`."
+            ),
+            values = ["maximal", "minimal", "direct_minimal"],
+            default = "maximal",
+        ),
     },
     environ = CRATES_REPOSITORY_ENVIRON,
 )
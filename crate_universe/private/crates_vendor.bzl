@@ -274,6 +274,11 @@ def _write_config_file(ctx):
             output_pkg = _get_output_package(ctx),
             workspace_name = workspace_name,
             render_config = dict(json.decode(ctx.attr.render_config)) if ctx.attr.render_config else None,
+            version_resolution = ctx.attr.version_resolution,
+            enforce_rust_version = ctx.attr.enforce_rust_version,
+            deny_yanked = ctx.attr.deny_yanked,
+            default_annotations = ctx.attr.default_annotations,
+            disabled_default_annotations = ctx.attr.disabled_default_annotations,
         ),
     )
 
@@ -295,7 +300,12 @@ def generate_config_file(
         output_pkg,
         workspace_name,
         render_config,
-        repository_ctx = None):
+        repository_ctx = None,
+        version_resolution = "maximal",
+        enforce_rust_version = False,
+        deny_yanked = False,
+        default_annotations = "",
+        disabled_default_annotations = []):
     """Writes the rendering config to cargo-bazel-config.json.
 
     Args:
@@ -313,6 +323,11 @@ def generate_config_file(
         render_config: The render config to use.
         repository_ctx (repository_ctx, optional): A repository context object
             used for enabling certain functionality.
+        version_resolution (str, optional): How Cargo picks dependency versions when resolving.
+        enforce_rust_version (bool, optional): Whether to fail on crates requiring a newer rustc.
+        deny_yanked (bool, optional): Whether to fail on crates yanked from their registry.
+        default_annotations (str, optional): The version of the built-in annotations to apply, if any.
+        disabled_default_annotations (list, optional): Crates whose built-in annotations are not applied.
 
     Returns:
         file: The cargo-bazel-config.json written.
@@ -387,6 +402,11 @@ def generate_config_file(
         supported_platform_triples = supported_platform_triples,
         repository_name = repository_name or ctx.label.name,
         repository_ctx = repository_ctx,
+        version_resolution = version_resolution,
+        enforce_rust_version = enforce_rust_version,
+        deny_yanked = deny_yanked,
+        default_annotations = default_annotations,
+        disabled_default_annotations = disabled_default_annotations,
     )
 
     return json.encode_indent(
@@ -437,6 +457,9 @@ def _crates_vendor_impl(ctx):
         args.extend(["--cargo-lockfile", _expand_env("CARGO_LOCK", is_windows)])
         cargo_bazel_runfiles.extend([ctx.file.cargo_lockfile])
 
+    if ctx.attr.prune_sources:
+        args.append("--prune-sources")
+
     # Optionally include buildifier
     if ctx.attr.buildifier:
         environ.append(_sys_runfile_env(ctx, "BUILDIFIER", ctx.executable.buildifier, is_windows))
@@ -514,6 +537,32 @@ CRATES_VENDOR_ATTRS = {
         doc = "The path to an existing `Cargo.lock` file",
         allow_single_file = True,
     ),
+    "default_annotations": attr.string(
+        doc = (
+            "The version of the annotations built into `cargo-bazel` for crates which commonly need them " +
+            "(e.g. `libz-sys`, `openssl-sys`). These are applied beneath any user provided `annotations`. " +
+            "Built-in annotations are not applied unless a version is set."
+        ),
+        values = ["", "v1"],
+        default = "",
+    ),
+    "deny_yanked": attr.bool(
+        doc = (
+            "Whether or not to fail if a resolved crate has been yanked from its registry. " +
+            "Such crates are reported as warnings along with the packages which depend on them otherwise."
+        ),
+        default = False,
+    ),
+    "disabled_default_annotations": attr.string_list(
+        doc = "The names of crates whose built-in annotations (see `default_annotations`) should not be applied.",
+    ),
+    "enforce_rust_version": attr.bool(
+        doc = (
+            "Whether or not to fail if a resolved crate declares a `rust-version` newer than the rustc in use. " +
+            "Such crates are reported as warnings along with the packages which depend on them otherwise."
+        ),
+        default = False,
+    ),
     "generate_binaries": attr.bool(
         doc = (
             "Whether to generate `rust_binary` targets for all the binary crates in every package. " +
@@ -555,6 +604,17 @@ CRATES_VENDOR_ATTRS = {
     "packages": attr.string_dict(
         doc = "A set of crates (packages) specifications to depend on. See [crate.spec](#crate.spec).",
     ),
+    "prune_sources": attr.bool(
+        doc = (
+            "Whether or not to remove files of crates vendored in `local` mode which are not used by any generated " +
+            "target (e.g. tests, benchmarks and documentation). Files matched by a target's sources or by its " +
+            "`compile_data_glob` and `data_glob` annotations are kept, so crates using the default `**` globs keep " +
+            "all of their files. Narrow those globs, or use the `vendor_keep_glob` and " +
+            "`vendor_exclude_glob` parameters of `crate.annotation` to adjust which files are kept. The " +
+            "`.cargo-checksum.json` file of each crate is updated to match."
+        ),
+        default = False,
+    ),
     "render_config": attr.string(
         doc = (
             "The configuration flags to use for rendering. Use `//crate_universe:defs.bzl\\%render_config` to " +
@@ -578,6 +638,17 @@ CRATES_VENDOR_ATTRS = {
         doc = "The path to a directory to write files into. Absolute paths will be treated as relative to the workspace root",
         default = "crates",
     ),
+    "version_resolution": attr.string(
+        doc = (
+            "How Cargo picks dependency versions when generating a new lockfile. `maximal` is Cargo's default " +
+            "behavior. `minimal` and `direct_minimal` select the oldest versions allowed by the requirements " +
+            "of all dependencies or only those of workspace members respectively. This is useful with a " +
+            "separate lockfile to test crates against the oldest versions of dependencies they claim to support. " +
+            "The minimal modes rely on unstable Cargo flags and require a nightly `cargo`."
+        ),
+        values = ["maximal", "minimal", "direct_minimal"],
+        default = "maximal",
+    ),
     "_bash_runfiles": attr.label(
         doc = "The runfiles library for bash.",
         cfg = "target",
@@ -657,6 +728,35 @@ call against the generated workspace. The following table describes how to contr
 | Any of [`full`, `eager`, `all`] | `cargo update` |
 | `package_name` | `cargo upgrade --package package_name` |
 | `package_name@1.2.3` | `cargo upgrade --package package_name --precise 1.2.3` |
+| `upgrade` | Raise version requirements in `Cargo.toml` to the newest semver-compatible releases, then `cargo update --workspace` |
+| `upgrade=package_a,package_b` | Raise version requirements of only the listed packages, then `cargo update --package package_a --package package_b` |
+| `upgrade-incompatible[=package_a,...]` | As `upgrade`, but also crosses semver-incompatible versions |
+
+### Verifying vendored outputs
+
+To check in CI that vendored outputs are up to date and have not been edited by hand, pass `--verify`:
+
+```shell
+bazel run //3rdparty:crates_vendor -- --verify
+```
+
+This renders everything into a temporary directory and compares it against the committed `BUILD` files,
+`defs.bzl` and `Cargo.lock`. In `local` vendoring mode the sources of each crate are additionally checked
+against the checksums recorded by `cargo vendor` and `Cargo.lock`. Any difference is printed as a unified diff
+and the command exits with a non-zero status. Nothing in the workspace is modified.
+
+### Watching for changes
+
+While iterating on `Cargo.toml` files, pass `--watch` to vendor again whenever the config, a manifest or the
+Cargo config changes:
+
+```shell
+bazel run //3rdparty:crates_vendor -- --watch
+```
+
+Changes are debounced, only outputs which changed are rewritten and the crates which were added, removed,
+updated or changed are printed after each run. Errors are reported without ending the session, which runs
+until interrupted.
 
 """,
     attrs = CRATES_VENDOR_ATTRS,
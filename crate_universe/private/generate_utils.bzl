@@ -4,6 +4,7 @@ load(
     ":common_utils.bzl",
     "CARGO_BAZEL_DEBUG",
     "CARGO_BAZEL_ISOLATED",
+    "CARGO_BAZEL_LOCAL_OVERRIDES",
     "REPIN_ALLOWLIST_ENV_VAR",
     "REPIN_ENV_VARS",
     "parse_alias_rule",
@@ -21,6 +22,7 @@ CRATES_REPOSITORY_ENVIRON = GENERATOR_ENV_VARS + REPIN_ENV_VARS + [
     REPIN_ALLOWLIST_ENV_VAR,
     CARGO_BAZEL_ISOLATED,
     CARGO_BAZEL_DEBUG,
+    CARGO_BAZEL_LOCAL_OVERRIDES,
 ]
 
 def get_generator(repository_ctx, host_triple):
@@ -93,12 +95,15 @@ def render_config(
         crate_alias_template = "//:{name}-{version}",
         crate_repository_template = "{repository}__{name}-{version}",
         crates_module_template = "//:{file}",
+        crates_module_sharding = None,
         default_alias_rule = "alias",
         default_package_name = None,
         generate_cargo_toml_env_vars = True,
+        generate_rust_docs = False,
         generate_target_compatible_with = True,
         platforms_template = "@rules_rust//rust/platform:{triple}",
         regen_command = None,
+        strict_visibility = False,
         vendor_mode = None,
         generate_rules_license_metadata = False):
     """Various settings used to configure rendered outputs
@@ -126,6 +131,12 @@ def render_config(
             repository. The available format keys are [`{repository}`, `{name}`, `{version}`].
         crates_module_template (str, optional): The pattern to use for the `defs.bzl` and `BUILD.bazel`
             file names used for the crates module. The available format keys are [`{file}`].
+        crates_module_sharding (str, optional): An optional way to split the dependency data of the `defs.bzl`
+            crates module into `shards/<name>.bzl` files, one for each workspace member `package` or top level
+            `directory`. BUILD files then load `aliases`, `all_crate_deps` and `crate_deps` from the shard of
+            their package, e.g. `load("@crates//:shards/app.bzl", "all_crate_deps")`, so only its data is
+            loaded. To migrate, update the loads of these macros in BUILD files; calling them from `defs.bzl`
+            fails with the shard to load instead.
         default_alias_rule (str, option): Alias rule to use when generating aliases for all crates.  Acceptable values
             are 'alias', 'dbg'/'fastbuild'/'opt' (transitions each crate's `compilation_mode`)  or a string
             representing a rule in the form '<label to .bzl>:<rule>' that takes a single label parameter 'actual'.
@@ -134,6 +145,10 @@ def render_config(
             auto package detection of things like `all_crate_deps`.
         generate_cargo_toml_env_vars (bool, optional): Whether to generate cargo_toml_env_vars targets. This is expected
             to be true except when bootstrapping.
+        generate_rust_docs (bool, optional): Whether to generate a `rust_doc` target for the library of every crate,
+            and a `docs` target in the crates module combining the documentation of every version of them behind
+            a single index. With a nightly toolchain, crates link to their dependencies and share a search index.
+            Can be overridden for each crate with [crate.annotation](#crateannotation).
         generate_target_compatible_with (bool, optional):  Whether to generate `target_compatible_with` annotations on
             the generated BUILD files.  This catches a `target_triple`being targeted that isn't declared in
             `supported_platform_triples`.
@@ -141,6 +156,8 @@ def render_config(
             See [platforms documentation](https://docs.bazel.build/versions/main/platforms.html). The available format
             keys are [`{triple}`].
         regen_command (str, optional): An optional command to demonstrate how generated files should be regenerated.
+        strict_visibility (bool, optional): Whether to restrict the aliases of each direct dependency to the packages
+            of the workspace members whose `Cargo.toml` declares it. Dependencies declared in Bazel remain public.
         vendor_mode (str, optional): An optional configuration for rendirng content to be rendered into repositories.
         generate_rules_license_metadata (bool, optional): Whether to generate rules license metedata
 
@@ -152,14 +169,17 @@ def render_config(
         crate_alias_template = crate_alias_template,
         crate_label_template = crate_label_template,
         crate_repository_template = crate_repository_template,
+        crates_module_sharding = crates_module_sharding,
         crates_module_template = crates_module_template,
         default_alias_rule = parse_alias_rule(default_alias_rule),
         default_package_name = default_package_name,
         generate_cargo_toml_env_vars = generate_cargo_toml_env_vars,
         generate_rules_license_metadata = generate_rules_license_metadata,
+        generate_rust_docs = generate_rust_docs,
         generate_target_compatible_with = generate_target_compatible_with,
         platforms_template = platforms_template,
         regen_command = regen_command,
+        strict_visibility = strict_visibility,
         vendor_mode = vendor_mode,
     ))
 
@@ -247,7 +267,12 @@ def compile_config(
         render_config,
         supported_platform_triples,
         repository_name,
-        repository_ctx = None):
+        repository_ctx = None,
+        version_resolution = "maximal",
+        enforce_rust_version = False,
+        deny_yanked = False,
+        default_annotations = "",
+        disabled_default_annotations = []):
     """Create a config file for generating crate targets
 
     [cargo_config]: https://doc.rust-lang.org/cargo/reference/config.html
@@ -264,6 +289,11 @@ def compile_config(
         repository_name (str): The name of the repository being generated
         repository_ctx (repository_ctx, optional): A repository context object used for enabling
             certain functionality.
+        version_resolution (str, optional): How Cargo picks dependency versions when resolving.
+        enforce_rust_version (bool, optional): Whether to fail on crates requiring a newer rustc.
+        deny_yanked (bool, optional): Whether to fail on crates yanked from their registry.
+        default_annotations (str, optional): The version of the built-in annotations to apply, if any.
+        disabled_default_annotations (list, optional): Crates whose built-in annotations are not applied.
 
     Returns:
         struct: A struct matching a `cargo_bazel::config::Config`.
@@ -303,6 +333,13 @@ def compile_config(
             repository_name = repository_name,
         ),
         supported_platform_triples = supported_platform_triples,
+        version_resolution = version_resolution,
+        enforce_rust_version = enforce_rust_version,
+        deny_yanked = deny_yanked,
+        default_annotations = struct(
+            version = default_annotations,
+            disabled = disabled_default_annotations,
+        ) if default_annotations else None,
     )
 
     return config
@@ -327,6 +364,11 @@ def generate_config(repository_ctx):
         supported_platform_triples = repository_ctx.attr.supported_platform_triples,
         repository_name = repository_ctx.name,
         repository_ctx = repository_ctx,
+        version_resolution = repository_ctx.attr.version_resolution,
+        enforce_rust_version = repository_ctx.attr.enforce_rust_version,
+        deny_yanked = repository_ctx.attr.deny_yanked,
+        default_annotations = repository_ctx.attr.default_annotations,
+        disabled_default_annotations = repository_ctx.attr.disabled_default_annotations,
     )
 
     config_path = repository_ctx.path("cargo-bazel.json")
@@ -386,6 +428,10 @@ def determine_repin(
             else:
                 return True
 
+    # Local overrides are never pinned so the workspace must always be spliced
+    if repository_ctx.os.environ.get(CARGO_BAZEL_LOCAL_OVERRIDES, "").strip():
+        return True
+
     # If a deterministic lockfile was not added then always repin
     if not lockfile_path:
         return True
@@ -1,4 +1,5 @@
 //! Module api provides a publicly consumable API over rules_rust's crate_universe.
-//! While it has no formal compatibility guarantees, it is much less likely to break than other types in this library.
+//! Unlike other types in this library, the API exposed here is versioned (see [lockfile::API_VERSION]) and is not
+//! affected by changes to the serialized formats `cargo-bazel` reads and writes.
 
 pub mod lockfile;
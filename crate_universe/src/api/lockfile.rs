@@ -1,32 +1,42 @@
 //! The lockfile::public module represents a reasonable stable API for inspecting the contents of a lockfile which others can code against.
+//!
+//! The types in this module are versioned by [API_VERSION] and are independent of the serialized
+//! lockfile format. Fields may be added to the lockfile, or renamed within it, without affecting
+//! consumers of this module. Within a given [API_VERSION], accessors will only ever be added, never
+//! removed or changed in a way that breaks callers; any such change is accompanied by a bump of
+//! [API_VERSION] and a semver-incompatible release of `cargo-bazel`.
 
-use std::collections::BTreeSet;
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 
 use anyhow::Result;
-use serde::Deserialize;
 
+use crate::config::Commitish;
 pub use crate::config::CrateId;
-use crate::context::crate_context::{CrateDependency, Rule};
+use crate::context::crate_context::{BuildScriptAttributes, CrateDependency, Rule};
 use crate::context::{CommonAttributes, Context};
-use crate::select::Select;
+use crate::lockfile::read_lockfile;
+use crate::metadata::SourceAnnotation;
+use crate::select::{Select, Selectable};
+
+/// The version of the API exposed by this module.
+///
+/// This is bumped whenever an accessor is removed or changes meaning. Tools built on this module
+/// can assert on it to detect an incompatible `cargo-bazel` upgrade at compile time.
+pub const API_VERSION: u32 = 1;
 
 /// Parse a lockfile at a path on disk.
+///
+/// Lockfiles written with older schema versions are migrated to the current one.
 pub fn parse(path: &Path) -> Result<impl CargoBazelLockfile> {
-    let reader = BufReader::new(File::open(path)?);
-    let lockfile: CargoBazelLockfileImpl = serde_json::from_reader(reader)?;
-    Ok(lockfile)
+    Ok(CargoBazelLockfileImpl(read_lockfile(path)?.context))
 }
 
 /// `CargoBazelLockfile` provides a view over `cargo-bazel`'s lockfile format.
 ///
 /// This trait provides information about the third-party dependencies of a workspace.
 /// While the lockfile's format doesn't provide any kind of compatibility guarantees over time,
-/// this type offers an interface which is likely to be publicly supportable.
-/// No formal compatibility guarantees are offered around this type - it may change at any time,
-/// but the maintainers will attempt to keep it as stable they reasonably can.
+/// this type offers an interface which is versioned by [API_VERSION].
 pub trait CargoBazelLockfile {
     /// Get the members of the local workspace.
     /// These are typically not very interesting on their own, but can be used as roots for navigating what dependencies these crates have.
@@ -34,10 +44,24 @@ pub trait CargoBazelLockfile {
 
     /// Get information about a specific crate (which may be in the local workspace, or an external dependency).
     fn crate_info(&self, crate_id: &CrateId) -> Option<CrateInfo>;
+
+    /// Get the identifiers of all crates (workspace members and external dependencies) in the lockfile.
+    fn crates(&self) -> BTreeSet<CrateId>;
+
+    /// Get the external crates which provide binary targets.
+    fn binary_crates(&self) -> BTreeSet<CrateId>;
+
+    /// Get the crates which are directly depended on by any workspace member.
+    fn direct_deps(&self) -> BTreeSet<CrateId>;
+
+    /// Get the crates which are directly depended on by any workspace member, but only for tests.
+    fn direct_dev_deps(&self) -> BTreeSet<CrateId>;
+
+    /// Get a mapping of configurations (e.g. `cfg(unix)`) used in dependency selects to the
+    /// platform triples (in Bazel form) which satisfy them.
+    fn conditions(&self) -> BTreeMap<String, BTreeSet<String>>;
 }
 
-#[derive(Deserialize)]
-#[serde(transparent)]
 struct CargoBazelLockfileImpl(Context);
 
 impl CargoBazelLockfile for CargoBazelLockfileImpl {
@@ -56,12 +80,54 @@ impl CargoBazelLockfile for CargoBazelLockfileImpl {
                 .iter()
                 .any(|t| matches!(t, Rule::ProcMacro(_))),
             common_attributes: crate_context.common_attrs.clone(),
+            build_script_attributes: crate_context.build_script_attrs.clone(),
+            binaries: crate_context
+                .targets
+                .iter()
+                .filter(|t| matches!(t, Rule::Binary(_)))
+                .map(|t| t.crate_name().to_owned())
+                .collect(),
+            package_url: crate_context.package_url.clone(),
+            repository: crate_context.repository.clone(),
+            license: crate_context.license.clone(),
+            license_ids: crate_context.license_ids.clone(),
+            license_file: crate_context.license_file.clone(),
+            extra_aliased_targets: crate_context.extra_aliased_targets.clone(),
         })
     }
+
+    fn crates(&self) -> BTreeSet<CrateId> {
+        self.0.crates.keys().cloned().collect()
+    }
+
+    fn binary_crates(&self) -> BTreeSet<CrateId> {
+        self.0.binary_crates.clone()
+    }
+
+    fn direct_deps(&self) -> BTreeSet<CrateId> {
+        self.0.direct_deps.clone()
+    }
+
+    fn direct_dev_deps(&self) -> BTreeSet<CrateId> {
+        self.0.direct_dev_deps.clone()
+    }
+
+    fn conditions(&self) -> BTreeMap<String, BTreeSet<String>> {
+        self.0
+            .conditions
+            .iter()
+            .map(|(condition, triples)| {
+                (
+                    condition.clone(),
+                    triples.iter().map(|triple| triple.to_bazel()).collect(),
+                )
+            })
+            .collect()
+    }
 }
 
 /// Information about a crate (which may be in-workspace or a dependency).
-#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug)]
 pub struct CrateInfo {
     name: String,
     version: semver::Version,
@@ -69,6 +135,22 @@ pub struct CrateInfo {
     is_proc_macro: bool,
 
     common_attributes: CommonAttributes,
+
+    build_script_attributes: Option<BuildScriptAttributes>,
+
+    binaries: BTreeSet<String>,
+
+    package_url: Option<String>,
+
+    repository: Option<SourceAnnotation>,
+
+    license: Option<String>,
+
+    license_ids: BTreeSet<String>,
+
+    license_file: Option<String>,
+
+    extra_aliased_targets: BTreeMap<String, String>,
 }
 
 impl CrateInfo {
@@ -93,6 +175,61 @@ impl CrateInfo {
         self.is_proc_macro
     }
 
+    /// The crate names of the binary targets provided by the crate.
+    pub fn binaries(&self) -> &BTreeSet<String> {
+        &self.binaries
+    }
+
+    /// The Rust edition the crate is compiled with.
+    pub fn edition(&self) -> &str {
+        &self.common_attributes.edition
+    }
+
+    /// The URL of the crate's homepage or repository, if one was declared.
+    pub fn package_url(&self) -> Option<&str> {
+        self.package_url.as_deref()
+    }
+
+    /// Where the crate's sources are fetched from. Workspace members have no source.
+    pub fn source(&self) -> Option<CrateSource> {
+        self.repository.as_ref().map(CrateSource::from)
+    }
+
+    /// The license expression declared by the crate.
+    pub fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    /// The SPDX license IDs parsed from the crate's license expression.
+    pub fn license_ids(&self) -> &BTreeSet<String> {
+        &self.license_ids
+    }
+
+    /// The license file declared by the crate, relative to its manifest.
+    pub fn license_file(&self) -> Option<&str> {
+        self.license_file.as_deref()
+    }
+
+    /// Features enabled on the crate.
+    pub fn crate_features(&self) -> Configured<String> {
+        Configured::new(&self.common_attributes.crate_features, |value| value)
+    }
+
+    /// Environment variables set when compiling the crate.
+    pub fn rustc_env(&self) -> Configured<(String, String)> {
+        Configured::new(&self.common_attributes.rustc_env, |value| value)
+    }
+
+    /// Extra flags passed to rustc when compiling the crate.
+    pub fn rustc_flags(&self) -> Configured<String> {
+        Configured::new(&self.common_attributes.rustc_flags, |value| value)
+    }
+
+    /// Additional targets (keyed by alias name) which are exposed under an alias for the crate.
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.extra_aliased_targets
+    }
+
     /// Dependencies required to compile the crate, without procedural macro dependencies.
     pub fn normal_deps(&self) -> Select<BTreeSet<CrateDependency>> {
         self.common_attributes.deps.clone()
@@ -112,11 +249,266 @@ impl CrateInfo {
     pub fn proc_macro_dev_deps(&self) -> Select<BTreeSet<CrateDependency>> {
         self.common_attributes.proc_macro_deps_dev.clone()
     }
+
+    /// Like [CrateInfo::normal_deps], as [Dependency] values.
+    pub fn configured_normal_deps(&self) -> Configured<Dependency> {
+        Configured::new(&self.common_attributes.deps, Dependency::from)
+    }
+
+    /// Like [CrateInfo::dev_deps], as [Dependency] values.
+    pub fn configured_dev_deps(&self) -> Configured<Dependency> {
+        Configured::new(&self.common_attributes.deps_dev, Dependency::from)
+    }
+
+    /// Like [CrateInfo::proc_macro_deps], as [Dependency] values.
+    pub fn configured_proc_macro_deps(&self) -> Configured<Dependency> {
+        Configured::new(&self.common_attributes.proc_macro_deps, Dependency::from)
+    }
+
+    /// Like [CrateInfo::proc_macro_dev_deps], as [Dependency] values.
+    pub fn configured_proc_macro_dev_deps(&self) -> Configured<Dependency> {
+        Configured::new(
+            &self.common_attributes.proc_macro_deps_dev,
+            Dependency::from,
+        )
+    }
+
+    /// Information about the crate's build script, if it has one and it is enabled.
+    pub fn build_script(&self) -> Option<BuildScriptInfo> {
+        self.build_script_attributes
+            .as_ref()
+            .map(|attrs| BuildScriptInfo {
+                attributes: attrs.clone(),
+            })
+    }
+}
+
+/// Information about a crate's build script (`build.rs`).
+#[derive(PartialEq, Eq, Debug)]
+pub struct BuildScriptInfo {
+    attributes: BuildScriptAttributes,
+}
+
+impl BuildScriptInfo {
+    /// The value of the crate's `links` manifest key, if any.
+    pub fn links(&self) -> Option<&str> {
+        self.attributes.links.as_deref()
+    }
+
+    /// Environment variables set when running the build script.
+    pub fn build_script_env(&self) -> Configured<(String, String)> {
+        Configured::new(&self.attributes.build_script_env, |value| value)
+    }
+
+    /// Environment variables set when compiling the build script.
+    pub fn rustc_env(&self) -> Configured<(String, String)> {
+        Configured::new(&self.attributes.rustc_env, |value| value)
+    }
+
+    /// Labels of data made available to the build script when it runs.
+    pub fn data(&self) -> Configured<String> {
+        Configured::new(&self.attributes.data, |label| label.to_string())
+    }
+
+    /// Glob patterns of files made available to the build script when it runs.
+    pub fn data_glob(&self) -> &BTreeSet<String> {
+        &self.attributes.data_glob
+    }
+
+    /// Labels of data made available to the build script when it is compiled.
+    pub fn compile_data(&self) -> Configured<String> {
+        Configured::new(&self.attributes.compile_data, |label| label.to_string())
+    }
+
+    /// Labels of executables made available to the build script when it runs.
+    pub fn tools(&self) -> Configured<String> {
+        Configured::new(&self.attributes.tools, |label| label.to_string())
+    }
+
+    /// Dependencies required to compile the build script, without procedural macro dependencies.
+    pub fn deps(&self) -> Configured<Dependency> {
+        Configured::new(&self.attributes.deps, Dependency::from)
+    }
+
+    /// Procedural macro dependencies required to compile the build script.
+    pub fn proc_macro_deps(&self) -> Configured<Dependency> {
+        Configured::new(&self.attributes.proc_macro_deps, Dependency::from)
+    }
+
+    /// Dependencies of the crate which set `links` and whose metadata is passed to the build script.
+    pub fn link_deps(&self) -> Configured<Dependency> {
+        Configured::new(&self.attributes.link_deps, Dependency::from)
+    }
+}
+
+/// Values of an attribute, some of which may only apply to certain configurations
+/// (e.g. `cfg(unix)`), while others apply unconditionally.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Configured<T> {
+    items: Vec<(Option<String>, T)>,
+}
+
+impl<T> Configured<T> {
+    fn new<S, F>(select: &Select<S>, func: F) -> Self
+    where
+        S: Selectable,
+        F: Fn(S::ItemType) -> T,
+    {
+        Self {
+            items: select
+                .items()
+                .into_iter()
+                .map(|(configuration, value)| (configuration, func(value)))
+                .collect(),
+        }
+    }
+
+    /// Whether there are no values, common or configuration-specific.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The configurations which have some configuration-specific value associated.
+    pub fn configurations(&self) -> BTreeSet<&str> {
+        self.items
+            .iter()
+            .filter_map(|(configuration, _)| configuration.as_deref())
+            .collect()
+    }
+
+    /// The values which apply regardless of configuration.
+    pub fn common(&self) -> Vec<&T> {
+        self.items
+            .iter()
+            .filter(|(configuration, _)| configuration.is_none())
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// All values and their associated configurations, if any.
+    pub fn items(&self) -> &[(Option<String>, T)] {
+        &self.items
+    }
+
+    /// All values, whether common or configured.
+    pub fn values(&self) -> Vec<&T> {
+        self.items.iter().map(|(_, value)| value).collect()
+    }
+}
+
+/// A dependency of a crate.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub struct Dependency {
+    id: CrateId,
+    target: String,
+    alias: Option<String>,
+}
+
+impl Dependency {
+    /// The crate which is depended on.
+    pub fn id(&self) -> &CrateId {
+        &self.id
+    }
+
+    /// The name of the target which is depended on. Note this may differ from the
+    /// dependency's package name in cases such as build scripts.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// The name the dependency is renamed to in the dependent's manifest, if any.
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+}
+
+impl From<CrateDependency> for Dependency {
+    fn from(dependency: CrateDependency) -> Self {
+        Self {
+            id: dependency.id,
+            target: dependency.target,
+            alias: dependency.alias,
+        }
+    }
+}
+
+/// Where a crate's sources are fetched from.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+pub enum CrateSource {
+    /// An archive downloaded over HTTP (e.g. from a registry).
+    Http {
+        /// The URL of the archive.
+        url: String,
+
+        /// The expected sha256 of the archive, if known.
+        sha256: Option<String>,
+    },
+
+    /// A git repository.
+    Git {
+        /// The URL of the repository.
+        remote: String,
+
+        /// The revision of the repository which is checked out.
+        reference: GitReference,
+
+        /// The path within the repository at which the crate lives, if not the root.
+        strip_prefix: Option<String>,
+    },
+
+    /// A local path, relative to the Bazel workspace root.
+    Path {
+        /// The path to the crate's sources.
+        path: String,
+    },
+}
+
+/// A pin of a git repository.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+pub enum GitReference {
+    /// A tag.
+    Tag(String),
+
+    /// The HEAD of a branch.
+    Branch(String),
+
+    /// A specific revision.
+    Rev(String),
+}
+
+impl From<&SourceAnnotation> for CrateSource {
+    fn from(annotation: &SourceAnnotation) -> Self {
+        match annotation {
+            SourceAnnotation::Http { url, sha256, .. } => Self::Http {
+                url: url.clone(),
+                sha256: sha256.clone(),
+            },
+            SourceAnnotation::Git {
+                remote,
+                commitish,
+                strip_prefix,
+                ..
+            } => Self::Git {
+                remote: remote.clone(),
+                reference: match commitish {
+                    Commitish::Tag(tag) => GitReference::Tag(tag.clone()),
+                    Commitish::Branch(branch) => GitReference::Branch(branch.clone()),
+                    Commitish::Rev(rev) => GitReference::Rev(rev.clone()),
+                },
+                strip_prefix: strip_prefix.clone(),
+            },
+            SourceAnnotation::Path { path } => Self::Path {
+                path: path.to_string(),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{parse, CargoBazelLockfile};
+    use super::{parse, CargoBazelLockfile, CrateSource};
     use crate::config::CrateId;
     use crate::context::crate_context::CrateDependency;
     use semver::Version;
@@ -189,6 +581,15 @@ mod test {
                 },
             ],
         );
+        assert_eq!(
+            got_pkg_a
+                .configured_normal_deps()
+                .values()
+                .into_iter()
+                .map(|dep| (dep.id().name.as_str(), dep.target(), dep.alias()))
+                .collect::<Vec<_>>(),
+            vec![("anyhow", "anyhow", None), ("reqwest", "reqwest", None)],
+        );
 
         let async_process = CrateId {
             name: String::from("async-process"),
@@ -201,6 +602,14 @@ mod test {
             .into_iter()
             .map(|(config, dep)| (config, dep.id.name))
             .collect();
+        let got_async_process_configured_deps: BTreeSet<(Option<String>, String)> =
+            got_async_process
+                .configured_normal_deps()
+                .items()
+                .iter()
+                .map(|(config, dep)| (config.clone(), dep.id().name.clone()))
+                .collect();
+        assert_eq!(got_async_process_configured_deps, got_async_process_deps);
         assert_eq!(
             got_async_process_deps,
             vec![
@@ -220,4 +629,75 @@ mod test {
             .collect::<BTreeSet<_>>(),
         );
     }
+
+    #[test]
+    fn exercise_public_lockfile_api_attributes() {
+        let runfiles = runfiles::Runfiles::create().unwrap();
+        let path = runfiles::rlocation!(
+            runfiles, "rules_rust/crate_universe/test_data/cargo_bazel_lockfile/multi_package-cargo-bazel-lock.json").unwrap();
+
+        let parsed = parse(&path).unwrap();
+        assert!(parsed.binary_crates().is_empty());
+        assert!(parsed.crates().is_superset(&parsed.workspace_members()));
+        assert!(parsed.crates().is_superset(&parsed.direct_deps()));
+        assert_eq!(
+            parsed
+                .conditions()
+                .get("cfg(unix)")
+                .map(|triples| triples.contains("x86_64-unknown-linux-gnu")),
+            Some(true),
+        );
+
+        let anyhow = CrateId {
+            name: String::from("anyhow"),
+            version: Version::new(1, 0, 69),
+        };
+        let got_anyhow = parsed.crate_info(&anyhow).unwrap();
+        assert_eq!(
+            got_anyhow.source(),
+            Some(CrateSource::Http {
+                url: String::from("https://crates.io/api/v1/crates/anyhow/1.0.69/download"),
+                sha256: Some(String::from(
+                    "224afbd727c3d6e4b90103ece64b8d1b67fbb1973b1046c2281eed3f3803f800"
+                )),
+            })
+        );
+        assert_eq!(got_anyhow.license(), Some("MIT OR Apache-2.0"));
+        assert_eq!(
+            got_anyhow.license_ids(),
+            &BTreeSet::from([String::from("Apache-2.0"), String::from("MIT")])
+        );
+        assert_eq!(got_anyhow.crate_features().common(), vec!["default", "std"]);
+        assert!(got_anyhow.binaries().is_empty());
+
+        let pkg_a = CrateId {
+            name: String::from("pkg_a"),
+            version: Version::new(0, 1, 0),
+        };
+        assert_eq!(parsed.crate_info(&pkg_a).unwrap().source(), None);
+
+        let libnghttp2_sys = CrateId {
+            name: String::from("libnghttp2-sys"),
+            version: Version::parse("0.1.7+1.45.0").unwrap(),
+        };
+        let build_script = parsed
+            .crate_info(&libnghttp2_sys)
+            .unwrap()
+            .build_script()
+            .unwrap();
+        assert_eq!(build_script.links(), Some("nghttp2"));
+        assert_eq!(
+            build_script.data_glob(),
+            &BTreeSet::from([String::from("**"), String::from("nghttp2/**")])
+        );
+        assert_eq!(
+            build_script
+                .deps()
+                .values()
+                .into_iter()
+                .map(|dep| dep.id().name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["cc"]
+        );
+    }
 }
@@ -1,10 +1,14 @@
 //! Command line interface entry points and utilities
 
 mod generate;
+mod lockfile;
+mod outdated;
 mod query;
 mod render;
 mod splice;
 mod vendor;
+mod vet;
+mod why;
 
 use clap::Parser;
 use tracing::Subscriber;
@@ -17,17 +21,25 @@ use tracing_subscriber::FmtSubscriber;
 pub use tracing::Level as LogLevel;
 
 pub use self::generate::GenerateOptions;
+pub use self::lockfile::{LockfileCommand, LockfileOptions, MigrateOptions};
+pub use self::outdated::OutdatedOptions;
 pub use self::query::QueryOptions;
 pub use self::render::RenderOptions;
 pub use self::splice::SpliceOptions;
 pub use self::vendor::VendorOptions;
+pub use self::vet::VetOptions;
+pub use self::why::WhyOptions;
 
 // Entrypoints
 pub use generate::generate;
+pub use lockfile::lockfile;
+pub use outdated::outdated;
 pub use query::query;
 pub use render::render;
 pub use splice::splice;
 pub use vendor::vendor;
+pub use vet::vet;
+pub use why::why;
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -50,6 +62,18 @@ pub enum Options {
 
     /// Render a BUILD file for a single crate.
     Render(RenderOptions),
+
+    /// Report crates in a lockfile for which newer versions are available.
+    Outdated(OutdatedOptions),
+
+    /// Check the crates in a lockfile against `cargo vet` audits.
+    Vet(VetOptions),
+
+    /// Explain which dependents and features enable a feature of a crate.
+    Why(WhyOptions),
+
+    /// Inspect or upgrade a cargo-bazel lockfile.
+    Lockfile(LockfileOptions),
 }
 
 // Convenience wrappers to avoid dependencies in the binary
@@ -59,7 +83,9 @@ pub fn parse_args() -> Options {
     Options::parse()
 }
 
-const EXPECTED_LOGGER_NAMES: [&str; 5] = ["Generate", "Splice", "Query", "Vendor", "Render"];
+const EXPECTED_LOGGER_NAMES: [&str; 9] = [
+    "Generate", "Splice", "Query", "Vendor", "Render", "Outdated", "Vet", "Why", "Lockfile",
+];
 
 /// A wrapper for the tracing-subscriber default [FormatEvent]
 /// that prepends the name of the active CLI option.
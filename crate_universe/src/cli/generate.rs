@@ -10,12 +10,15 @@ use camino::Utf8PathBuf;
 use cargo_lock::Lockfile;
 use clap::Parser;
 
-use crate::config::Config;
+use crate::config::load_config_and_splicing_manifest;
 use crate::context::Context;
-use crate::lockfile::{lock_context, write_lockfile};
-use crate::metadata::{load_metadata, Annotations, Cargo, SourceAnnotation};
+use crate::lockfile::{lock_context, read_lockfile, write_lockfile};
+use crate::metadata::{
+    check_context_rust_versions, check_rust_versions, load_metadata, Annotations, Cargo,
+    SourceAnnotation,
+};
 use crate::rendering::{write_outputs, Renderer};
-use crate::splicing::SplicingManifest;
+use crate::splicing::{check_yanked_crates, LocalOverrides};
 use crate::utils::normalize_cargo_file_paths;
 use crate::utils::starlark::Label;
 
@@ -31,13 +34,15 @@ pub struct GenerateOptions {
     #[clap(long, env = "RUSTC")]
     pub rustc: Option<PathBuf>,
 
-    /// The config file with information about the Bazel and Cargo workspace
+    /// The config file with information about the Bazel and Cargo workspace. This may
+    /// also be a `cargo-bazel.toml` file, in which case `--splicing-manifest` is optional.
     #[clap(long)]
     pub config: PathBuf,
 
-    /// A generated manifest of splicing inputs
+    /// A generated manifest of splicing inputs. Derived from `--config` if it is a
+    /// `cargo-bazel.toml` file.
     #[clap(long)]
-    pub splicing_manifest: PathBuf,
+    pub splicing_manifest: Option<PathBuf>,
 
     /// The path to either a Cargo or Bazel lockfile
     #[clap(long)]
@@ -96,16 +101,32 @@ pub struct GenerateOptions {
     /// so this provides a way for the repository rule to force printing.
     #[clap(long)]
     pub warnings_output_path: PathBuf,
+
+    /// A comma separated list of `name=path` pairs of local checkouts which were used in
+    /// place of crates from crates.io while splicing. When set, neither the Bazel nor the
+    /// Cargo lockfile is updated.
+    #[clap(long, env = "CARGO_BAZEL_LOCAL_OVERRIDES")]
+    pub local_overrides: Option<LocalOverrides>,
 }
 
 pub fn generate(opt: GenerateOptions) -> Result<()> {
-    // Load the config
-    let config = Config::try_from_path(&opt.config)?;
+    // Load the config files
+    let (config, splicing_manifest) =
+        load_config_and_splicing_manifest(&opt.config, opt.splicing_manifest.as_deref())?;
 
     // Go straight to rendering if there is no need to repin
     if !opt.repin {
         if let Some(lockfile) = &opt.lockfile {
-            let context = Context::try_from_path(lockfile)?;
+            let context = read_lockfile(lockfile)?.context;
+
+            // The pinned crates may have been resolved with a newer toolchain than the one in use.
+            let rust_version_warnings = match &opt.rustc {
+                Some(rustc_bin) => {
+                    check_context_rust_versions(&context, rustc_bin, config.enforce_rust_version)
+                        .context("Failed to check the `rust-version` of crates")?
+                }
+                None => Vec::new(),
+            };
 
             // Render build files
             let outputs = Renderer::new(
@@ -120,8 +141,6 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
             // Write the outputs to disk
             write_outputs(normalized_outputs, opt.dry_run)?;
 
-            let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?;
-
             write_paths_to_track(
                 &opt.paths_to_track,
                 &opt.warnings_output_path,
@@ -131,6 +150,7 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
                     .values()
                     .filter_map(|crate_context| crate_context.repository.as_ref()),
                 context.unused_patches.iter(),
+                rust_version_warnings,
             )?;
 
             return Ok(());
@@ -170,6 +190,12 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     }
     let (cargo_metadata, cargo_lockfile) = load_metadata(metadata_path, &lockfile_path)?;
 
+    // Catch crates which can't be built by the pinned toolchain before they show up as
+    // compile errors.
+    let rust_version_warnings =
+        check_rust_versions(&cargo_metadata, rustc_bin, config.enforce_rust_version)
+            .context("Failed to check the `rust-version` of crates")?;
+
     // Annotate metadata
     let annotations = Annotations::new(
         cargo_metadata,
@@ -179,7 +205,25 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
         &opt.nonhermetic_root_bazel_workspace_dir,
     )?;
 
-    let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?;
+    // Yanked crates are found in the crate indexes while splicing.
+    let yanked_warnings = check_yanked_crates(
+        &annotations.metadata.workspace_metadata.yanked,
+        config.deny_yanked,
+    )?;
+
+    // Profile settings such as LTO apply to whole builds rather than individual crates.
+    let profile_warnings = annotations
+        .metadata
+        .workspace_metadata
+        .profiles
+        .unsupported
+        .iter()
+        .map(|setting| {
+            format!(
+                "The Cargo profile setting `{}` can't be applied to individual crates and is ignored. Please configure the Rust toolchain to match instead.",
+                setting
+            )
+        });
 
     write_paths_to_track(
         &opt.paths_to_track,
@@ -187,6 +231,11 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
         splicing_manifest.manifests.keys().cloned(),
         annotations.lockfile.crates.values(),
         cargo_lockfile.patch.unused.iter(),
+        rust_version_warnings
+            .into_iter()
+            .chain(yanked_warnings)
+            .chain(profile_warnings)
+            .collect(),
     )?;
 
     // Generate renderable contexts for each package
@@ -205,6 +254,15 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     // Write the outputs to disk
     write_outputs(normalized_outputs, opt.dry_run)?;
 
+    // Local overrides only apply to the current checkout so the lockfiles are left untouched.
+    if opt
+        .local_overrides
+        .as_ref()
+        .is_some_and(|overrides| !overrides.is_empty())
+    {
+        return Ok(());
+    }
+
     // Ensure Bazel lockfiles are written to disk so future generations can be short-circuited.
     if let Some(lockfile) = opt.lockfile {
         let lock_content =
@@ -244,6 +302,7 @@ fn write_paths_to_track<
     manifests: Paths,
     source_annotations: SourceAnnotations,
     unused_patches: UnusedPatches,
+    additional_warnings: Vec<String>,
 ) -> Result<()> {
     let source_annotation_manifests: BTreeSet<_> = source_annotations
         .filter_map(|v| {
@@ -273,6 +332,8 @@ fn write_paths_to_track<
         warnings.push(format!("You have a [patch] Cargo.toml entry that is being ignored by cargo. Unused patch: {} {}{}", unused_patch.name, unused_patch.version, if let Some(source) = unused_patch.source.as_ref() { format!(" ({})", source) } else { String::new() }));
     }
 
+    warnings.extend(additional_warnings);
+
     std::fs::write(
         warnings_output_path,
         serde_json::to_string(&warnings).context("Failed to serialize warnings to track")?,
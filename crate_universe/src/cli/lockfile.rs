@@ -0,0 +1,95 @@
+//! The cli entrypoint for the `lockfile` subcommand
+
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::cli::Result;
+use crate::config::load_config_and_splicing_manifest;
+use crate::lockfile::{lock_context, read_lockfile, write_lockfile, LOCKFILE_SCHEMA_VERSION};
+use crate::metadata::Cargo;
+
+/// Command line options for the `lockfile` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `lockfile` subcommand", version)]
+pub struct LockfileOptions {
+    #[clap(subcommand)]
+    pub command: LockfileCommand,
+}
+
+/// Operations on a cargo-bazel lockfile.
+#[derive(Subcommand, Debug)]
+pub enum LockfileCommand {
+    /// Rewrite a lockfile written with an older schema in the current schema.
+    Migrate(MigrateOptions),
+}
+
+/// Command line options for the `lockfile migrate` subcommand
+#[derive(Args, Debug)]
+pub struct MigrateOptions {
+    /// The lockfile path for reproducible Cargo->Bazel renderings
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The config file with information about the Bazel and Cargo workspace. This may
+    /// also be a `cargo-bazel.toml` file, in which case `--splicing-manifest` is optional.
+    #[clap(long)]
+    pub config: PathBuf,
+
+    /// A generated manifest of splicing inputs. Derived from `--config` if it is a
+    /// `cargo-bazel.toml` file.
+    #[clap(long)]
+    pub splicing_manifest: Option<PathBuf>,
+
+    /// The path to a Cargo binary to use for computing the lockfile digest
+    #[clap(long, env = "CARGO")]
+    pub cargo: PathBuf,
+
+    /// The path to a rustc binary to use for computing the lockfile digest
+    #[clap(long, env = "RUSTC")]
+    pub rustc: PathBuf,
+
+    /// Print the migrated lockfile instead of writing it to disk.
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+/// Operate on a cargo-bazel lockfile.
+pub fn lockfile(opt: LockfileOptions) -> Result<()> {
+    match opt.command {
+        LockfileCommand::Migrate(opt) => migrate(opt),
+    }
+}
+
+/// Rewrite a lockfile in the current schema. The digest is recomputed from the given
+/// inputs, so they should be the ones the lockfile was pinned with.
+fn migrate(opt: MigrateOptions) -> Result<()> {
+    let lockfile = read_lockfile(&opt.lockfile)?;
+    if !lockfile.is_migrated() {
+        tracing::info!(
+            "Lockfile `{}` already uses schema version {}",
+            opt.lockfile.display(),
+            LOCKFILE_SCHEMA_VERSION
+        );
+        return Ok(());
+    }
+
+    tracing::info!(
+        "Migrating lockfile `{}` from schema version {} to {}",
+        opt.lockfile.display(),
+        lockfile.schema_version,
+        LOCKFILE_SCHEMA_VERSION
+    );
+
+    let (config, splicing_manifest) =
+        load_config_and_splicing_manifest(&opt.config, opt.splicing_manifest.as_deref())?;
+    let context = lock_context(
+        lockfile.context,
+        &config,
+        &splicing_manifest,
+        &Cargo::new(opt.cargo, opt.rustc.clone()),
+        &opt.rustc,
+    )?;
+
+    write_lockfile(context, &opt.lockfile, opt.dry_run)
+}
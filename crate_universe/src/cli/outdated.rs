@@ -0,0 +1,96 @@
+//! The cli entrypoint for the `outdated` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::lockfile::read_lockfile;
+use crate::metadata::Cargo;
+use crate::splicing::cargo_config::CargoConfig;
+use crate::splicing::{find_outdated_crates, load_crate_indexes_with_config, update_crate_indexes};
+
+/// Command line options for the `outdated` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `outdated` subcommand", version)]
+pub struct OutdatedOptions {
+    /// The lockfile path for reproducible Cargo->Bazel renderings
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The path to the Cargo lockfile the `--lockfile` was rendered from. This determines
+    /// the registry each crate is sourced from.
+    #[clap(long)]
+    pub cargo_lockfile: PathBuf,
+
+    /// The path to a Cargo configuration file with registry replacements to respect.
+    #[clap(long)]
+    pub cargo_config: Option<PathBuf>,
+
+    /// If true, the report is printed as JSON instead of a table.
+    #[clap(long)]
+    pub json: bool,
+
+    /// If true, registries aren't queried and the local copies of their indexes are used as is.
+    #[clap(long)]
+    pub offline: bool,
+
+    /// The path to a Cargo binary to use for locating crate indexes
+    #[clap(long, env = "CARGO")]
+    pub cargo: PathBuf,
+
+    /// The path to a rustc binary for use with Cargo
+    #[clap(long, env = "RUSTC")]
+    pub rustc: PathBuf,
+}
+
+/// Report crates in the lockfile for which newer versions are available.
+///
+/// Cargo fetches the newest index entries of each crate first, unless `--offline` is set, as the
+/// local checkout of git indexes and the cache of sparse indexes only contain what was needed
+/// when dependencies were last resolved.
+pub fn outdated(opt: OutdatedOptions) -> Result<()> {
+    let context = read_lockfile(&opt.lockfile)?.context;
+
+    let content = fs::read_to_string(&opt.cargo_lockfile).with_context(|| {
+        format!(
+            "Unable to read Cargo lockfile `{}`",
+            opt.cargo_lockfile.display()
+        )
+    })?;
+    let cargo_lockfile = cargo_lock::Lockfile::from_str(&content).with_context(|| {
+        format!(
+            "Could not parse Cargo lockfile `{}`",
+            opt.cargo_lockfile.display()
+        )
+    })?;
+
+    let cargo_config = opt
+        .cargo_config
+        .as_deref()
+        .map(CargoConfig::try_from_path)
+        .transpose()?;
+
+    let cargo = Cargo::new(opt.cargo, opt.rustc);
+    if !opt.offline {
+        update_crate_indexes(
+            &cargo,
+            &context,
+            &cargo_lockfile,
+            opt.cargo_config.as_deref(),
+        )?;
+    }
+    let crate_indexes =
+        load_crate_indexes_with_config(&cargo, &cargo_lockfile, cargo_config.as_ref())?;
+
+    let report = find_outdated_crates(&context, &cargo_lockfile, &crate_indexes)?;
+    if opt.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{report}");
+    }
+
+    Ok(())
+}
@@ -1,16 +1,13 @@
 //! The cli entrypoint for the `query` subcommand
 
-use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{bail, Result};
 use clap::Parser;
 
-use crate::config::Config;
-use crate::context::Context;
-use crate::lockfile::Digest;
+use crate::config::load_config_and_splicing_manifest;
+use crate::lockfile::{read_lockfile, Digest, LOCKFILE_SCHEMA_VERSION};
 use crate::metadata::Cargo;
-use crate::splicing::SplicingManifest;
 
 /// Command line options for the `query` subcommand
 #[derive(Parser, Debug)]
@@ -20,13 +17,15 @@ pub struct QueryOptions {
     #[clap(long)]
     pub lockfile: PathBuf,
 
-    /// The config file with information about the Bazel and Cargo workspace
+    /// The config file with information about the Bazel and Cargo workspace. This may
+    /// also be a `cargo-bazel.toml` file, in which case `--splicing-manifest` is optional.
     #[clap(long)]
     pub config: PathBuf,
 
-    /// A generated manifest of splicing inputs
+    /// A generated manifest of splicing inputs. Derived from `--config` if it is a
+    /// `cargo-bazel.toml` file.
     #[clap(long)]
-    pub splicing_manifest: PathBuf,
+    pub splicing_manifest: Option<PathBuf>,
 
     /// The path to a Cargo binary to use for gathering metadata
     #[clap(long, env = "CARGO")]
@@ -39,21 +38,9 @@ pub struct QueryOptions {
 
 /// Determine if the current lockfile needs to be re-pinned
 pub fn query(opt: QueryOptions) -> Result<()> {
-    // Read the lockfile
-    let content = match fs::read_to_string(&opt.lockfile) {
-        Ok(c) => c,
-        Err(e) => bail!(
-            "Unable to read lockfile `{}`\n{:?}",
-            opt.lockfile.display(),
-            e
-        ),
-    };
-
-    // Deserialize it so we can easily compare it with
-    let lockfile: Context = match serde_json::from_str(&content) {
-        Ok(ctx) => ctx,
-        Err(_) => bail!("Could not load lockfile"),
-    };
+    // Read the lockfile, migrating it if it was written with an older schema
+    let loaded = read_lockfile(&opt.lockfile)?;
+    let lockfile = &loaded.context;
 
     // Check to see if a digest has been set
     let digest = match &lockfile.checksum {
@@ -61,14 +48,26 @@ pub fn query(opt: QueryOptions) -> Result<()> {
         None => bail!("No digest provided in lockfile"),
     };
 
-    // Load the config file
-    let config = Config::try_from_path(&opt.config)?;
+    // Lockfiles written with an older schema were pinned under a digest scheme which can't
+    // be reproduced once cargo-bazel is upgraded, so their digest is trusted until they're
+    // migrated or repinned.
+    if loaded.is_migrated() {
+        eprintln!(
+            "Lockfile `{}` has an outdated schema (version {}), auto-migrated to version {}. Run `cargo-bazel lockfile migrate` or repin to upgrade it on disk.",
+            opt.lockfile.display(),
+            loaded.schema_version,
+            LOCKFILE_SCHEMA_VERSION
+        );
+        return Ok(());
+    }
 
-    let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?;
+    // Load the config files
+    let (config, splicing_manifest) =
+        load_config_and_splicing_manifest(&opt.config, opt.splicing_manifest.as_deref())?;
 
     // Generate a new digest so we can compare it with the one in the lockfile
     let expected = Digest::new(
-        &lockfile,
+        lockfile,
         &config,
         &splicing_manifest,
         &Cargo::new(opt.cargo, opt.rustc.clone()),
@@ -82,3 +81,40 @@ pub fn query(opt: QueryOptions) -> Result<()> {
     // There is no need to repin
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn query_outdated_lockfile_schema() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let lockfile = temp_dir.as_ref().join("cargo-bazel-lock.json");
+        fs::write(
+            &lockfile,
+            r#"{
+                "checksum": "88b94b0cef41a247986119e4268aef8285864e2ee03d1f12709a7903dda6e886",
+                "crates": {},
+                "binary_crates": [],
+                "workspace_members": {},
+                "conditions": {},
+                "direct_deps": [],
+                "direct_dev_deps": []
+            }"#,
+        )
+        .unwrap();
+
+        // The digest of a version 0 lockfile is trusted, so none of the inputs are needed.
+        let missing = temp_dir.as_ref().join("missing");
+        query(QueryOptions {
+            lockfile,
+            config: missing.clone(),
+            splicing_manifest: Some(missing.clone()),
+            cargo: missing.clone(),
+            rustc: missing,
+        })
+        .unwrap();
+    }
+}
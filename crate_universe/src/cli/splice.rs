@@ -7,19 +7,22 @@ use camino::Utf8PathBuf;
 use clap::Parser;
 
 use crate::cli::Result;
-use crate::config::Config;
+use crate::config::load_config_and_splicing_manifest;
 use crate::metadata::{
     write_metadata, Cargo, CargoUpdateRequest, Generator, MetadataGenerator, TreeResolver,
 };
-use crate::splicing::{generate_lockfile, Splicer, SplicingManifest, WorkspaceMetadata};
+use crate::splicing::{
+    apply_upgrade_request, generate_lockfile, LocalOverrides, Splicer, WorkspaceMetadata,
+};
 
 /// Command line options for the `splice` subcommand
 #[derive(Parser, Debug)]
 #[clap(about = "Command line options for the `splice` subcommand", version)]
 pub struct SpliceOptions {
-    /// A generated manifest of splicing inputs
+    /// A generated manifest of splicing inputs. Derived from `--config` if it is a
+    /// `cargo-bazel.toml` file.
     #[clap(long)]
-    pub splicing_manifest: PathBuf,
+    pub splicing_manifest: Option<PathBuf>,
 
     /// The path to a [Cargo.lock](https://doc.rust-lang.org/cargo/guide/cargo-toml-vs-cargo-lock.html) file.
     #[clap(long)]
@@ -46,7 +49,8 @@ pub struct SpliceOptions {
     #[clap(long)]
     pub cargo_config: Option<PathBuf>,
 
-    /// The path to the config file (containing [crate::config::Config].)
+    /// The path to the config file (containing [crate::config::Config].) This may also be
+    /// a `cargo-bazel.toml` file, in which case `--splicing-manifest` is optional.
     #[clap(long)]
     pub config: PathBuf,
 
@@ -57,13 +61,19 @@ pub struct SpliceOptions {
     /// The path to a rustc binary for use with Cargo
     #[clap(long, env = "RUSTC")]
     pub rustc: PathBuf,
+
+    /// A comma separated list of `name=path` pairs of local checkouts to use in place
+    /// of crates from crates.io. These are never written to any manifest or lockfile.
+    #[clap(long, env = "CARGO_BAZEL_LOCAL_OVERRIDES")]
+    pub local_overrides: Option<LocalOverrides>,
 }
 
 /// Combine a set of disjoint manifests into a single workspace.
 pub fn splice(opt: SpliceOptions) -> Result<()> {
     // Load the all config files required for splicing a workspace
-    let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)
-        .context("Failed to parse splicing manifest")?;
+    let (config, splicing_manifest) =
+        load_config_and_splicing_manifest(&opt.config, opt.splicing_manifest.as_deref())
+            .context("Failed to parse config")?;
 
     // Determine the splicing workspace
     let temp_dir;
@@ -76,10 +86,16 @@ pub fn splice(opt: SpliceOptions) -> Result<()> {
         }
     };
 
-    // Generate a splicer for creating a Cargo workspace manifest
-    let splicer = Splicer::new(splicing_dir, splicing_manifest)?;
+    let cargo = Cargo::new(opt.cargo, opt.rustc.clone())
+        .with_registry_credentials(splicing_manifest.registry_credentials()?);
 
-    let cargo = Cargo::new(opt.cargo, opt.rustc.clone());
+    // Rewrite the spliced-from manifests if an upgrade was requested
+    apply_upgrade_request(&cargo, &splicing_manifest, &opt.cargo_lockfile, &opt.repin)
+        .context("Failed to upgrade manifests")?;
+
+    // Generate a splicer for creating a Cargo workspace manifest
+    let splicer = Splicer::new(splicing_dir, splicing_manifest)?
+        .with_local_overrides(opt.local_overrides.unwrap_or_default());
 
     // Splice together the manifest
     let manifest_path = splicer
@@ -92,12 +108,12 @@ pub fn splice(opt: SpliceOptions) -> Result<()> {
         &opt.cargo_lockfile,
         cargo.clone(),
         &opt.repin,
+        config.version_resolution,
     )
     .context("Failed to generate lockfile")?;
 
-    let config = Config::try_from_path(&opt.config).context("Failed to parse config")?;
-
     let resolver_data = TreeResolver::new(cargo.clone())
+        .with_version_resolution(config.version_resolution)
         .generate(
             manifest_path.as_path_buf(),
             &config.supported_platform_triples,
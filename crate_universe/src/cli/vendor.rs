@@ -1,6 +1,10 @@
 //! The cli entrypoint for the `vendor` subcommand
 
-use std::collections::{BTreeSet, HashMap};
+mod prune;
+mod verify;
+mod watch;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -10,15 +14,24 @@ use std::sync::Arc;
 use anyhow::{anyhow, bail, Context as AnyhowContext};
 use camino::Utf8PathBuf;
 use clap::Parser;
+use serde::{Deserialize, Serialize};
 
-use crate::config::{Config, VendorMode};
+use crate::config::{is_standalone_config, load_config_and_splicing_manifest, Config, VendorMode};
 use crate::context::Context;
 use crate::metadata::CargoUpdateRequest;
-use crate::metadata::TreeResolver;
+use crate::metadata::{check_rust_versions, TreeResolver};
 use crate::metadata::{Annotations, Cargo, Generator, MetadataGenerator, VendorGenerator};
 use crate::rendering::{render_module_label, write_outputs, Renderer};
-use crate::splicing::{generate_lockfile, Splicer, SplicingManifest, WorkspaceMetadata};
-use crate::utils::normalize_cargo_file_paths;
+use crate::splicing::{
+    apply_upgrade_request, check_yanked_crates, generate_lockfile, Splicer, SplicingManifest,
+    WorkspaceMetadata,
+};
+use crate::utils::diff::unified_diff;
+use crate::utils::{normalize_cargo_file_paths, sanitize_repository_name};
+
+use self::prune::prune_vendored_sources;
+use self::verify::{verify_outputs, verify_vendored_crate, Drift};
+use self::watch::{diff_crates, Watcher};
 
 /// Command line options for the `vendor` subcommand
 #[derive(Parser, Debug)]
@@ -36,13 +49,15 @@ pub struct VendorOptions {
     #[clap(long)]
     pub buildifier: Option<PathBuf>,
 
-    /// The config file with information about the Bazel and Cargo workspace
+    /// The config file with information about the Bazel and Cargo workspace. This may
+    /// also be a `cargo-bazel.toml` file, in which case `--splicing-manifest` is optional.
     #[clap(long)]
     pub config: PathBuf,
 
-    /// A generated manifest of splicing inputs
+    /// A generated manifest of splicing inputs. Derived from `--config` if it is a
+    /// `cargo-bazel.toml` file.
     #[clap(long)]
-    pub splicing_manifest: PathBuf,
+    pub splicing_manifest: Option<PathBuf>,
 
     /// The path to a [Cargo.lock](https://doc.rust-lang.org/cargo/guide/cargo-toml-vs-cargo-lock.html) file.
     #[clap(long)]
@@ -76,6 +91,26 @@ pub struct VendorOptions {
     #[clap(long)]
     pub dry_run: bool,
 
+    /// If true, outputs are rendered into a temporary directory and compared against the
+    /// committed ones instead of being written. Any difference is printed as a unified diff and
+    /// results in a non-zero exit code. This mode requires an existing `--cargo-lockfile` and
+    /// cannot be combined with `--repin`.
+    #[clap(long, conflicts_with_all = ["dry_run", "repin"])]
+    pub verify: bool,
+
+    /// If true, files of locally vendored crates which are not used by any generated target
+    /// (e.g. tests, benchmarks and documentation) are removed. Crates using the default `**`
+    /// data globs keep all of their files. Per-crate annotations `vendor_keep_glob` and
+    /// `vendor_exclude_glob` adjust which files are kept.
+    #[clap(long)]
+    pub prune_sources: bool,
+
+    /// If true, vendoring is repeated whenever the config, any spliced manifest or the Cargo
+    /// config changes. Changes are debounced, only changed outputs are rewritten and the
+    /// crates which changed are printed after each run. Runs until interrupted.
+    #[clap(long, conflicts_with = "verify")]
+    pub watch: bool,
+
     /// The path to the Bazel root workspace (i.e. the directory containing the WORKSPACE.bazel file or similar).
     /// BE CAREFUL with this value. We never want to include it in a lockfile hash (to keep lockfiles portable),
     /// which means you also should not use it anywhere that _should_ be guarded by a lockfile hash.
@@ -84,6 +119,26 @@ pub struct VendorOptions {
     pub nonhermetic_root_bazel_workspace_dir: Utf8PathBuf,
 }
 
+/// The contents of a `.cargo-checksum.json` file written by `cargo vendor`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct CargoChecksums {
+    /// The sha256 of each file in the crate keyed by their path relative to the crate root.
+    pub(crate) files: BTreeMap<String, String>,
+
+    /// The sha256 of the `.crate` file the sources were extracted from, if any.
+    pub(crate) package: Option<String>,
+}
+
+impl CargoChecksums {
+    pub(crate) fn try_from_path<T: AsRef<Path>>(path: T) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
 /// Run buildifier on a given file.
 fn buildifier_format(bin: &Path, file: &Path) -> anyhow::Result<ExitStatus> {
     let status = process::Command::new(bin)
@@ -115,6 +170,109 @@ fn bzlmod_tidy(bin: &Path, workspace_dir: &Path) -> anyhow::Result<ExitStatus> {
     Ok(status)
 }
 
+/// Compare freshly rendered outputs against the ones committed to the workspace.
+///
+/// Outputs are written to a temporary directory and formatted the same way a regular run would
+/// so they can be compared byte for byte. For [VendorMode::Local], the sources of every vendored
+/// crate are additionally checked against the checksums recorded by `cargo vendor` and
+/// `Cargo.lock`.
+fn verify_vendor(
+    outputs: BTreeMap<PathBuf, String>,
+    workspace_dir: &Path,
+    vendor_dir: &Path,
+    vendor_mode: Option<&VendorMode>,
+    cargo_lockfile: &cargo_lock::Lockfile,
+    cargo_lockfile_path: &Path,
+    buildifier: Option<&Path>,
+) -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+
+    // Mirror the path transformations `normalize_cargo_file_paths` applies without
+    // renaming anything on disk.
+    let outputs: BTreeMap<PathBuf, String> = outputs
+        .into_iter()
+        .map(|(path, content)| {
+            let parent = path.parent().and_then(Path::to_str).unwrap_or_default();
+            let path = if parent.contains('+') {
+                PathBuf::from(sanitize_repository_name(
+                    path.to_str().expect("All file paths should be strings"),
+                ))
+            } else {
+                path
+            };
+            (path, content)
+        })
+        .collect();
+
+    let temp_outputs = outputs
+        .iter()
+        .map(|(path, content)| (temp_dir.path().join(path), content.clone()))
+        .collect();
+    write_outputs(temp_outputs, false).context("Failed writing output files")?;
+
+    let mut rendered = BTreeMap::new();
+    for path in outputs.into_keys() {
+        let temp_path = temp_dir.path().join(&path);
+        if let Some(buildifier_bin) = buildifier {
+            buildifier_format(buildifier_bin, &temp_path)
+                .with_context(|| format!("Failed to run buildifier on {}", temp_path.display()))?;
+        }
+        let content = fs::read_to_string(&temp_path)
+            .with_context(|| format!("Failed to read {}", temp_path.display()))?;
+        rendered.insert(path, content);
+    }
+
+    let mut drift = Drift::default();
+
+    // Vendored crates are named the same way `cargo vendor --versioned-dirs` names them.
+    let vendored_crates: BTreeMap<PathBuf, Option<String>> =
+        if matches!(vendor_mode, Some(VendorMode::Local)) {
+            cargo_lockfile
+                .packages
+                .iter()
+                .filter(|pkg| pkg.source.is_some())
+                .map(|pkg| {
+                    (
+                        vendor_dir.join(sanitize_repository_name(&format!(
+                            "{}-{}",
+                            pkg.name, pkg.version
+                        ))),
+                        pkg.checksum.as_ref().map(ToString::to_string),
+                    )
+                })
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+    verify_outputs(
+        &rendered,
+        workspace_dir,
+        vendor_dir,
+        &vendored_crates.keys().cloned().collect(),
+        &mut drift,
+    )?;
+
+    for (crate_dir, checksum) in &vendored_crates {
+        verify_vendored_crate(crate_dir, checksum.as_deref(), &mut drift)?;
+    }
+
+    // A lockfile which would be changed by splicing means the vendored outputs are stale.
+    let committed_lockfile = fs::read_to_string(cargo_lockfile_path)
+        .with_context(|| format!("Failed to read {}", cargo_lockfile_path.display()))?;
+    let display = cargo_lockfile_path.display();
+    if let Some(diff) = unified_diff(
+        &committed_lockfile,
+        &cargo_lockfile.to_string(),
+        &format!("a/{display}"),
+        &format!("b/{display}"),
+    ) {
+        drift.push(diff);
+    }
+
+    drift.into_result()
+}
+
 /// Info about a Bazel workspace
 struct BazelInfo {
     /// The version of Bazel being used
@@ -193,23 +351,144 @@ impl TryFrom<HashMap<String, String>> for BazelInfo {
     }
 }
 
+/// The results of vendoring which later runs in `--watch` mode build upon.
+struct VendorRun {
+    /// The context outputs were rendered from.
+    context: Context,
+
+    /// The content of each output as rendered, before formatting.
+    outputs: BTreeMap<PathBuf, String>,
+
+    /// The content of the generated Cargo lockfile.
+    cargo_lockfile: String,
+}
+
 pub fn vendor(opt: VendorOptions) -> anyhow::Result<()> {
-    let bazel_info = BazelInfo::try_new(&opt.bazel, &opt.workspace_dir)?;
+    // Standalone configs describe manifests with real paths and are meant to be usable
+    // without Bazel, so only generated splicing manifests need Bazel's help to resolve.
+    let bazel_info = if is_standalone_config(&opt.config) && opt.splicing_manifest.is_none() {
+        None
+    } else {
+        Some(BazelInfo::try_new(&opt.bazel, &opt.workspace_dir)?)
+    };
+
+    if opt.watch {
+        return watch_vendor(&opt, bazel_info.as_ref());
+    }
+
+    run_vendor(&opt, bazel_info.as_ref(), &opt.repin, None)?;
+    Ok(())
+}
+
+/// Load the splicing manifest, resolving the paths of generated ones with Bazel.
+fn load_splicing_inputs(
+    opt: &VendorOptions,
+    bazel_info: Option<&BazelInfo>,
+) -> anyhow::Result<(Config, SplicingManifest)> {
+    let (config, splicing_manifest) =
+        load_config_and_splicing_manifest(&opt.config, opt.splicing_manifest.as_deref())?;
+    let splicing_manifest = match bazel_info {
+        Some(info) => splicing_manifest.resolve(&opt.workspace_dir, &info.output_base),
+        None => splicing_manifest,
+    };
+    Ok((config, splicing_manifest))
+}
+
+/// The files which `--watch` mode monitors: the config files, every manifest being
+/// spliced and the Cargo config.
+fn watched_paths(
+    opt: &VendorOptions,
+    bazel_info: Option<&BazelInfo>,
+) -> anyhow::Result<BTreeSet<PathBuf>> {
+    let (_, splicing_manifest) = load_splicing_inputs(opt, bazel_info)?;
+
+    let mut paths = BTreeSet::from([opt.config.clone()]);
+    paths.extend(opt.splicing_manifest.clone());
+    paths.extend(opt.cargo_config.clone());
+    paths.extend(
+        splicing_manifest
+            .manifests
+            .into_keys()
+            .chain(splicing_manifest.cargo_config)
+            .map(Utf8PathBuf::into_std_path_buf),
+    );
+    Ok(paths)
+}
+
+/// Vendor outputs again whenever any watched file changes until interrupted.
+fn watch_vendor(opt: &VendorOptions, bazel_info: Option<&BazelInfo>) -> anyhow::Result<()> {
+    let mut watcher = Watcher::default();
+    let mut previous: Option<VendorRun> = None;
+
+    // Upgrades are only requested once rather than on every change.
+    let mut repin = opt.repin.clone();
+
+    loop {
+        watcher.mark();
+        match run_vendor(opt, bazel_info, &repin, previous.as_ref()) {
+            Ok(run) => {
+                if let Some(previous) = &previous {
+                    let changes = diff_crates(&previous.context, &run.context);
+                    if changes.is_empty() {
+                        println!("No crates changed");
+                    }
+                    for change in changes {
+                        println!("  {}", change);
+                    }
+                }
+                previous = Some(run);
+                repin = None;
+            }
+            // Inputs are often invalid while being edited so errors don't stop watching.
+            Err(err) => tracing::error!("Failed to vendor crates: {:?}", err),
+        }
+
+        match watched_paths(opt, bazel_info) {
+            Ok(paths) => watcher.set_paths(paths),
+            Err(err) => tracing::warn!(
+                "Failed to update the watched files, changes to new manifests will be missed: {:?}",
+                err
+            ),
+        }
+        if watcher.len() == 0 {
+            watcher.set_paths(BTreeSet::from([opt.config.clone()]));
+        }
 
+        println!("Watching {} files for changes...", watcher.len());
+        for path in watcher.wait_for_changes() {
+            println!("Changed: {}", path.display());
+        }
+    }
+}
+
+/// Splice, resolve and render the crates of the workspace.
+///
+/// When a `previous` run is provided, sources are only vendored again if the Cargo lockfile
+/// changed. Otherwise only outputs which changed since then are written.
+fn run_vendor(
+    opt: &VendorOptions,
+    bazel_info: Option<&BazelInfo>,
+    repin: &Option<CargoUpdateRequest>,
+    previous: Option<&VendorRun>,
+) -> anyhow::Result<VendorRun> {
     // Load the all config files required for splicing a workspace
-    let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?
-        .resolve(&opt.workspace_dir, &bazel_info.output_base);
+    let (config, splicing_manifest) = load_splicing_inputs(opt, bazel_info)?;
 
     let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
     let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.as_ref().to_path_buf())
         .unwrap_or_else(|path| panic!("Temporary directory wasn't valid UTF-8: {:?}", path));
 
+    let cargo = Cargo::new(opt.cargo.clone(), opt.rustc.clone())
+        .with_registry_credentials(splicing_manifest.registry_credentials()?);
+
+    // Rewrite the spliced-from manifests if an upgrade was requested
+    apply_upgrade_request(&cargo, &splicing_manifest, &opt.cargo_lockfile, repin)
+        .context("Failed to upgrade manifests")?;
+
     // Generate a splicer for creating a Cargo workspace manifest
     let splicer =
         Splicer::new(temp_dir_path, splicing_manifest).context("Failed to create splicer")?;
 
-    let cargo = Cargo::new(opt.cargo, opt.rustc.clone());
-
     // Splice together the manifest
     let manifest_path = splicer
         .splice_workspace()
@@ -220,16 +499,16 @@ pub fn vendor(opt: VendorOptions) -> anyhow::Result<()> {
         &manifest_path,
         &opt.cargo_lockfile,
         cargo.clone(),
-        &opt.repin,
+        repin,
+        config.version_resolution,
     )?;
 
-    // Load the config from disk
-    let config = Config::try_from_path(&opt.config)?;
-
-    let resolver_data = TreeResolver::new(cargo.clone()).generate(
-        manifest_path.as_path_buf(),
-        &config.supported_platform_triples,
-    )?;
+    let resolver_data = TreeResolver::new(cargo.clone())
+        .with_version_resolution(config.version_resolution)
+        .generate(
+            manifest_path.as_path_buf(),
+            &config.supported_platform_triples,
+        )?;
 
     // Write the registry url info to the manifest now that a lockfile has been generated
     WorkspaceMetadata::write_registry_urls_and_feature_map(
@@ -246,6 +525,12 @@ pub fn vendor(opt: VendorOptions) -> anyhow::Result<()> {
         .with_rustc(opt.rustc.clone())
         .generate(manifest_path.as_path_buf())?;
 
+    for warning in check_rust_versions(&cargo_metadata, &opt.rustc, config.enforce_rust_version)
+        .context("Failed to check the `rust-version` of crates")?
+    {
+        tracing::warn!("{}", warning);
+    }
+
     // Annotate metadata
     let annotations = Annotations::new(
         cargo_metadata,
@@ -255,6 +540,13 @@ pub fn vendor(opt: VendorOptions) -> anyhow::Result<()> {
         &opt.nonhermetic_root_bazel_workspace_dir,
     )?;
 
+    for warning in check_yanked_crates(
+        &annotations.metadata.workspace_metadata.yanked,
+        config.deny_yanked,
+    )? {
+        tracing::warn!("{}", warning);
+    }
+
     // Generate renderable contexts for earch package
     let context = Context::new(annotations, config.rendering.are_sources_present())?;
 
@@ -265,53 +557,123 @@ pub fn vendor(opt: VendorOptions) -> anyhow::Result<()> {
     )
     .render(&context, None)?;
 
-    // First ensure vendoring and rendering happen in a clean directory
     let vendor_dir_label = render_module_label(&config.rendering.crates_module_template, "BUILD")?;
     let vendor_dir = opt.workspace_dir.join(vendor_dir_label.package().unwrap());
-    if vendor_dir.exists() {
+
+    if opt.verify {
+        let cargo_lockfile_path = opt
+            .cargo_lockfile
+            .as_deref()
+            .context("The `--cargo-lockfile` argument is required with `--verify`")?;
+        verify_vendor(
+            outputs.clone(),
+            &opt.workspace_dir,
+            &vendor_dir,
+            config.rendering.vendor_mode.as_ref(),
+            &cargo_lockfile,
+            cargo_lockfile_path,
+            opt.buildifier.as_deref(),
+        )?;
+        return Ok(VendorRun {
+            context,
+            outputs,
+            cargo_lockfile: cargo_lockfile.to_string(),
+        });
+    }
+
+    // First ensure vendoring and rendering happen in a clean directory. Later runs in
+    // `--watch` mode instead only replace what changed.
+    if previous.is_none() && vendor_dir.exists() {
         fs::remove_dir_all(&vendor_dir)
             .with_context(|| format!("Failed to delete {}", vendor_dir.display()))?;
     }
 
     // Store the updated Cargo.lock
+    let cargo_lockfile = cargo_lockfile.to_string();
     if let Some(path) = &opt.cargo_lockfile {
-        fs::write(path, cargo_lockfile.to_string())
+        fs::write(path, &cargo_lockfile)
             .context("Failed to write Cargo.lock file back to the workspace.")?;
     }
 
-    if matches!(config.rendering.vendor_mode, Some(VendorMode::Local)) {
+    let lockfile_changed = previous.is_none_or(|run| run.cargo_lockfile != cargo_lockfile);
+    let revendor =
+        matches!(config.rendering.vendor_mode, Some(VendorMode::Local)) && lockfile_changed;
+    if revendor {
         VendorGenerator::new(cargo, opt.rustc.clone())
             .generate(manifest_path.as_path_buf(), &vendor_dir)
             .context("Failed to vendor dependencies")?;
+
+        if opt.prune_sources {
+            prune_vendored_sources(&context, &vendor_dir)
+                .context("Failed to prune vendored sources")?;
+        }
     }
 
     // make cargo versioned crates compatible with bazel labels
     let normalized_outputs = normalize_cargo_file_paths(outputs, &opt.workspace_dir);
 
+    if let Some(run) = previous {
+        for stale in run
+            .outputs
+            .keys()
+            .filter(|path| !normalized_outputs.contains_key(*path))
+        {
+            if stale.exists() {
+                fs::remove_file(stale)
+                    .with_context(|| format!("Failed to delete {}", stale.display()))?;
+            }
+        }
+    }
+    let changed_outputs = outputs_to_write(&normalized_outputs, previous, revendor);
+
     // buildifier files to check
-    let file_names: BTreeSet<PathBuf> = normalized_outputs.keys().cloned().collect();
+    let file_names: BTreeSet<PathBuf> = changed_outputs.keys().cloned().collect();
 
     // Write outputs
-    write_outputs(normalized_outputs, opt.dry_run).context("Failed writing output files")?;
+    write_outputs(changed_outputs, opt.dry_run).context("Failed writing output files")?;
 
     // Optionally apply buildifier fixes
-    if let Some(buildifier_bin) = opt.buildifier {
-        for file in file_names {
+    if let Some(buildifier_bin) = &opt.buildifier {
+        for file in &file_names {
             let file_path = opt.workspace_dir.join(file);
-            buildifier_format(&buildifier_bin, &file_path)
+            buildifier_format(buildifier_bin, &file_path)
                 .with_context(|| format!("Failed to run buildifier on {}", file_path.display()))?;
         }
     }
 
     // Optionally perform bazel mod tidy to update the MODULE.bazel file
-    if bazel_info.release >= semver::Version::new(7, 0, 0) {
-        let module_bazel = opt.workspace_dir.join("MODULE.bazel");
-        if module_bazel.exists() {
-            bzlmod_tidy(&opt.bazel, &opt.workspace_dir)?;
+    if let Some(bazel_info) = bazel_info {
+        if bazel_info.release >= semver::Version::new(7, 0, 0) && !file_names.is_empty() {
+            let module_bazel = opt.workspace_dir.join("MODULE.bazel");
+            if module_bazel.exists() {
+                bzlmod_tidy(&opt.bazel, &opt.workspace_dir)?;
+            }
         }
     }
 
-    Ok(())
+    Ok(VendorRun {
+        context,
+        outputs: normalized_outputs,
+        cargo_lockfile,
+    })
+}
+
+/// Select the outputs to write. Only outputs which changed since the `previous` run need
+/// to be written, unless sources were vendored again: `cargo vendor` deletes everything
+/// else in the vendor directory, including the outputs of the previous run.
+fn outputs_to_write(
+    outputs: &BTreeMap<PathBuf, String>,
+    previous: Option<&VendorRun>,
+    revendored: bool,
+) -> BTreeMap<PathBuf, String> {
+    match previous {
+        Some(run) if !revendored => outputs
+            .iter()
+            .filter(|(path, content)| run.outputs.get(*path) != Some(*content))
+            .map(|(path, content)| (path.clone(), content.clone()))
+            .collect(),
+        _ => outputs.clone(),
+    }
 }
 
 #[cfg(test)]
@@ -359,4 +721,39 @@ mod tests {
         );
         assert_eq!(PathBuf::from("/tmp/output_base"), info.output_base);
     }
+
+    #[test]
+    fn revendoring_rewrites_all_outputs() {
+        let previous = VendorRun {
+            context: Context::default(),
+            outputs: BTreeMap::from([
+                (PathBuf::from("crates/BUILD.bazel"), "build".to_owned()),
+                (PathBuf::from("crates/defs.bzl"), "defs".to_owned()),
+            ]),
+            cargo_lockfile: String::new(),
+        };
+        let outputs = BTreeMap::from([
+            (PathBuf::from("crates/BUILD.bazel"), "build".to_owned()),
+            (
+                PathBuf::from("crates/alias_rules.bzl"),
+                "alias_rules".to_owned(),
+            ),
+            (PathBuf::from("crates/defs.bzl"), "changed defs".to_owned()),
+        ]);
+
+        assert_eq!(
+            outputs_to_write(&outputs, Some(&previous), false),
+            BTreeMap::from([
+                (
+                    PathBuf::from("crates/alias_rules.bzl"),
+                    "alias_rules".to_owned()
+                ),
+                (PathBuf::from("crates/defs.bzl"), "changed defs".to_owned()),
+            ])
+        );
+
+        // `cargo vendor` deleted the unchanged outputs too.
+        assert_eq!(outputs_to_write(&outputs, Some(&previous), true), outputs);
+        assert_eq!(outputs_to_write(&outputs, None, false), outputs);
+    }
 }
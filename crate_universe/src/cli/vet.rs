@@ -0,0 +1,80 @@
+//! The cli entrypoint for the `vet` subcommand
+
+mod check;
+mod store;
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::lockfile::read_lockfile;
+
+use self::check::vet_context;
+use self::store::Store;
+
+/// Command line options for the `vet` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `vet` subcommand", version)]
+pub struct VetOptions {
+    /// The lockfile path for reproducible Cargo->Bazel renderings
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The directory containing the `cargo vet` `config.toml`, `audits.toml` and
+    /// `imports.lock` files. Defaults to `supply-chain` within `--workspace-dir`.
+    #[clap(long)]
+    pub supply_chain_dir: Option<PathBuf>,
+
+    /// The root of the workspace the supply-chain directory is in.
+    #[clap(long, env = "BUILD_WORKSPACE_DIRECTORY")]
+    pub workspace_dir: Option<PathBuf>,
+}
+
+/// Check that every third-party crate in the lockfile is covered by a `cargo vet` audit or
+/// exemption for the criteria required of it.
+///
+/// Only dependencies built for one of the supported platforms are considered, matching what
+/// Bazel builds rather than everything Cargo resolved.
+pub fn vet(opt: VetOptions) -> Result<()> {
+    let supply_chain_dir = match (opt.supply_chain_dir, opt.workspace_dir) {
+        (Some(dir), _) => dir,
+        (None, Some(workspace_dir)) => workspace_dir.join("supply-chain"),
+        (None, None) => bail!("One of `--supply-chain-dir` or `--workspace-dir` is required"),
+    };
+
+    let context = read_lockfile(&opt.lockfile)?.context;
+
+    let store = Store::load(&supply_chain_dir).with_context(|| {
+        format!(
+            "Failed to load supply-chain data from {}",
+            supply_chain_dir.display()
+        )
+    })?;
+
+    let (failures, checked) = vet_context(&context, &store);
+    let (failures, unverified): (Vec<_>, Vec<_>) = failures
+        .into_iter()
+        .partition(|failure| failure.is_failure());
+    for failure in &unverified {
+        tracing::warn!("{failure}");
+    }
+    if !failures.is_empty() {
+        for failure in &failures {
+            println!("{failure}");
+        }
+        bail!(
+            "{} of {} crates failed to vet",
+            failures
+                .iter()
+                .map(|failure| failure.crate_id())
+                .collect::<BTreeSet<_>>()
+                .len(),
+            checked
+        );
+    }
+
+    println!("Vetted {checked} crates");
+    Ok(())
+}
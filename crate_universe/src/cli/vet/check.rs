@@ -0,0 +1,629 @@
+//! Verification of a resolved [Context] against `cargo vet` audits.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use semver::Version;
+
+use crate::config::CrateId;
+use crate::context::crate_context::{CrateContext, CrateDependency};
+use crate::context::Context;
+use crate::metadata::SourceAnnotation;
+use crate::select::Select;
+
+use super::store::{
+    AuditsFile, ExemptionEntry, PublisherAuditEntry, PublisherEntry, Store, SAFE_TO_DEPLOY,
+    SAFE_TO_RUN,
+};
+
+/// A reason a crate failed to vet.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum VetFailure {
+    /// An audit declares the version as violating the given criteria.
+    Violation {
+        crate_id: CrateId,
+        criteria: Vec<String>,
+    },
+
+    /// No chain of audits or exemptions certifies the version for `criteria`.
+    Missing {
+        crate_id: CrateId,
+        criteria: String,
+
+        /// The closest version which is certified for `criteria`, from which a delta audit
+        /// would be sufficient.
+        delta_from: Option<Version>,
+    },
+
+    /// A wildcard audit or trusted publisher may certify the version for `criteria`, but
+    /// `imports.lock` doesn't record who published it. `cargo vet` fetches this from crates.io,
+    /// which isn't supported here.
+    UnknownPublisher { crate_id: CrateId, criteria: String },
+}
+
+impl VetFailure {
+    pub(crate) fn crate_id(&self) -> &CrateId {
+        match self {
+            Self::Violation { crate_id, .. }
+            | Self::Missing { crate_id, .. }
+            | Self::UnknownPublisher { crate_id, .. } => crate_id,
+        }
+    }
+
+    /// Whether the crate is known not to satisfy its criteria, rather than unverifiable.
+    pub(crate) fn is_failure(&self) -> bool {
+        !matches!(self, Self::UnknownPublisher { .. })
+    }
+}
+
+impl fmt::Display for VetFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Violation { crate_id, criteria } => write!(
+                f,
+                "`{crate_id}` is marked as violating `{}`",
+                criteria.join("`, `")
+            ),
+            Self::Missing {
+                crate_id,
+                criteria,
+                delta_from: Some(from),
+            } => write!(
+                f,
+                "`{crate_id}` is missing a `{criteria}` audit. A delta audit `{} {from} -> {}` is needed",
+                crate_id.name, crate_id.version
+            ),
+            Self::Missing {
+                crate_id,
+                criteria,
+                delta_from: None,
+            } => write!(
+                f,
+                "`{crate_id}` is missing a `{criteria}` audit. No version is audited so a full audit is needed",
+            ),
+            Self::UnknownPublisher { crate_id, criteria } => write!(
+                f,
+                "`{crate_id}` may be certified for `{criteria}` by a wildcard audit or trusted publisher, but its publisher isn't in `imports.lock`. Run `cargo vet` to record it",
+            ),
+        }
+    }
+}
+
+/// The transitive implications of every criteria.
+struct CriteriaGraph<'a> {
+    audits: &'a AuditsFile,
+}
+
+impl CriteriaGraph<'_> {
+    /// All criteria satisfied by satisfying `criteria`.
+    fn closure<'b>(&self, criteria: impl IntoIterator<Item = &'b String>) -> BTreeSet<String> {
+        let mut result = BTreeSet::new();
+        let mut queue: VecDeque<String> = criteria.into_iter().cloned().collect();
+        while let Some(criteria) = queue.pop_front() {
+            if !result.insert(criteria.clone()) {
+                continue;
+            }
+            if criteria == SAFE_TO_DEPLOY {
+                queue.push_back(SAFE_TO_RUN.to_owned());
+            }
+            if let Some(entry) = self.audits.criteria.get(&criteria) {
+                queue.extend(entry.implies.iter().cloned());
+            }
+        }
+        result
+    }
+}
+
+/// Whether a crate is sourced from outside the workspace.
+fn is_third_party(context: &Context, crate_id: &CrateId) -> bool {
+    !context.workspace_members.contains_key(crate_id)
+        && context.crates.get(crate_id).is_some_and(|krate| {
+            krate
+                .repository
+                .as_ref()
+                .is_some_and(|repo| !matches!(repo, SourceAnnotation::Path { .. }))
+        })
+}
+
+/// All dependencies of `deps` which are built for at least one supported platform.
+fn built_deps<'a>(
+    context: &'a Context,
+    deps: &'a Select<BTreeSet<CrateDependency>>,
+) -> impl Iterator<Item = CrateId> + 'a {
+    deps.items()
+        .into_iter()
+        .filter(|(configuration, _)| match configuration {
+            None => true,
+            Some(configuration) => context
+                .conditions
+                .get(configuration)
+                .is_some_and(|triples| !triples.is_empty()),
+        })
+        .map(|(_, dep)| dep.id)
+}
+
+/// The normal (including build script and proc-macro) and dev dependencies of a crate.
+fn dependencies(context: &Context, krate: &CrateContext) -> (BTreeSet<CrateId>, BTreeSet<CrateId>) {
+    let mut normal: BTreeSet<CrateId> = built_deps(context, &krate.common_attrs.deps)
+        .chain(built_deps(context, &krate.common_attrs.proc_macro_deps))
+        .collect();
+    if let Some(attrs) = &krate.build_script_attrs {
+        normal.extend(built_deps(context, &attrs.deps));
+        normal.extend(built_deps(context, &attrs.proc_macro_deps));
+        normal.extend(built_deps(context, &attrs.link_deps));
+    }
+    let dev = built_deps(context, &krate.common_attrs.deps_dev)
+        .chain(built_deps(context, &krate.common_attrs.proc_macro_deps_dev))
+        .collect();
+    (normal, dev)
+}
+
+/// Determine the criteria required of every third-party crate reachable from a workspace member.
+fn required_criteria(context: &Context, store: &Store) -> BTreeMap<CrateId, BTreeSet<String>> {
+    let default_criteria = store
+        .config
+        .default_criteria
+        .clone()
+        .unwrap_or_else(|| SAFE_TO_DEPLOY.to_owned());
+
+    let mut required: BTreeMap<CrateId, BTreeSet<String>> = BTreeMap::new();
+    let mut queue: VecDeque<(CrateId, Vec<String>)> = VecDeque::new();
+
+    // Crates depend on criteria based on the policy of their dependent.
+    let enqueue = |queue: &mut VecDeque<(CrateId, Vec<String>)>,
+                   dependent: &CrateId,
+                   deps: BTreeSet<CrateId>,
+                   criteria: &[String]| {
+        let policy = store.config.policy(&dependent.name, &dependent.version);
+        for dep in deps {
+            let criteria = policy
+                .and_then(|policy| policy.dependency_criteria.get(&dep.name))
+                .map(Vec::as_slice)
+                .unwrap_or(criteria);
+            queue.push_back((dep, criteria.to_vec()));
+        }
+    };
+
+    for member in context.workspace_members.keys() {
+        let Some(krate) = context.crates.get(member) else {
+            continue;
+        };
+        let policy = store.config.policy(&member.name, &member.version);
+        let criteria = policy
+            .and_then(|policy| policy.criteria.clone())
+            .unwrap_or_else(|| vec![default_criteria.clone()]);
+        let dev_criteria = policy
+            .and_then(|policy| policy.dev_criteria.clone())
+            .unwrap_or_else(|| vec![SAFE_TO_RUN.to_owned()]);
+
+        let (normal, dev) = dependencies(context, krate);
+        enqueue(&mut queue, member, normal, &criteria);
+        enqueue(&mut queue, member, dev, &dev_criteria);
+    }
+
+    while let Some((crate_id, criteria)) = queue.pop_front() {
+        if !is_third_party(context, &crate_id) {
+            continue;
+        }
+        let entry = required.entry(crate_id.clone()).or_default();
+        let new_criteria: Vec<String> = criteria
+            .into_iter()
+            .filter(|criteria| entry.insert(criteria.clone()))
+            .collect();
+        if new_criteria.is_empty() {
+            continue;
+        }
+
+        // Dependencies of third-party crates are held to the same criteria.
+        let (normal, _) = dependencies(context, &context.crates[&crate_id]);
+        enqueue(&mut queue, &crate_id, normal, &new_criteria);
+    }
+
+    required
+}
+
+/// Check a single version of a crate against its audits, exemptions, and the wildcard audits
+/// and trusted entries applying to the versions in `publishers`.
+fn check_crate(
+    crate_id: &CrateId,
+    required: &BTreeSet<String>,
+    graph: &CriteriaGraph,
+    exemptions: &[ExemptionEntry],
+    publishers: &[PublisherEntry],
+) -> Vec<VetFailure> {
+    let audits = graph
+        .audits
+        .audits
+        .get(&crate_id.name)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let publisher_audits: Vec<&PublisherAuditEntry> =
+        [&graph.audits.wildcard_audits, &graph.audits.trusted]
+            .into_iter()
+            .filter_map(|entries| entries.get(&crate_id.name))
+            .flatten()
+            .collect();
+
+    let violations: Vec<String> = audits
+        .iter()
+        .filter(|audit| {
+            audit
+                .violation
+                .as_ref()
+                .is_some_and(|req| req.matches(&crate_id.version))
+        })
+        .flat_map(|audit| audit.criteria.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if !violations.is_empty() {
+        return vec![VetFailure::Violation {
+            crate_id: crate_id.clone(),
+            criteria: violations,
+        }];
+    }
+
+    // Audits form a graph from "nothing" (`None`) to versions. Full audits and exemptions
+    // are edges from `None` while deltas connect two versions.
+    let edges: Vec<(Option<&Version>, &Version, BTreeSet<String>)> = audits
+        .iter()
+        .filter(|audit| audit.violation.is_none())
+        .filter_map(|audit| {
+            let (from, to) = match (&audit.version, &audit.delta) {
+                (Some(version), _) => (None, version),
+                (None, Some(delta)) => (Some(&delta.from), &delta.to),
+                (None, None) => return None,
+            };
+            Some((from, to, graph.closure(&audit.criteria)))
+        })
+        .chain(
+            exemptions
+                .iter()
+                .map(|exemption| (None, &exemption.version, graph.closure(&exemption.criteria))),
+        )
+        // Versions published by a trusted user are certified like full audits.
+        .chain(publishers.iter().flat_map(|publisher| {
+            publisher_audits
+                .iter()
+                .filter(|audit| audit.covers(publisher))
+                .map(|audit| (None, &publisher.version, graph.closure(&audit.criteria)))
+        }))
+        .collect();
+    let publisher_known = publishers
+        .iter()
+        .any(|publisher| publisher.version == crate_id.version);
+
+    required
+        .iter()
+        .filter_map(|criteria| {
+            // Find every version certified for the criteria.
+            let mut certified: BTreeSet<&Version> = BTreeSet::new();
+            let mut queue: VecDeque<Option<&Version>> = VecDeque::from([None]);
+            while let Some(from) = queue.pop_front() {
+                for (_, to, _) in edges.iter().filter(|(edge_from, _, satisfies)| {
+                    *edge_from == from && satisfies.contains(criteria)
+                }) {
+                    if certified.insert(to) {
+                        queue.push_back(Some(to));
+                    }
+                }
+            }
+
+            if certified.contains(&crate_id.version) {
+                return None;
+            }
+
+            if !publisher_known
+                && publisher_audits
+                    .iter()
+                    .any(|audit| graph.closure(&audit.criteria).contains(criteria))
+            {
+                return Some(VetFailure::UnknownPublisher {
+                    crate_id: crate_id.clone(),
+                    criteria: criteria.clone(),
+                });
+            }
+
+            // Suggest a delta from the newest older version, or the oldest newer one.
+            let delta_from = certified
+                .iter()
+                .filter(|version| **version < &crate_id.version)
+                .max()
+                .or_else(|| certified.iter().min())
+                .map(|version| (*version).clone());
+
+            Some(VetFailure::Missing {
+                crate_id: crate_id.clone(),
+                criteria: criteria.clone(),
+                delta_from,
+            })
+        })
+        .collect()
+}
+
+/// Check every third-party crate in `context` which is built for a supported platform.
+///
+/// Returns the failures, including crates which couldn't be verified, and the number of crates
+/// which were checked.
+pub(crate) fn vet_context(context: &Context, store: &Store) -> (Vec<VetFailure>, usize) {
+    let graph = CriteriaGraph {
+        audits: &store.audits,
+    };
+    let required = required_criteria(context, store);
+
+    let failures = required
+        .iter()
+        .flat_map(|(crate_id, criteria)| {
+            let exemptions: Vec<ExemptionEntry> = store
+                .config
+                .exemptions
+                .get(&crate_id.name)
+                .map(|exemptions| {
+                    exemptions
+                        .iter()
+                        .filter(|exemption| exemption.version == crate_id.version)
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+            let publishers = store
+                .publishers
+                .get(&crate_id.name)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            check_crate(crate_id, criteria, &graph, &exemptions, publishers)
+        })
+        .collect();
+
+    (failures, required.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::cli::vet::store::ConfigFile;
+    use crate::context::crate_context::CommonAttributes;
+
+    fn crate_dependency(crate_id: &CrateId) -> CrateDependency {
+        CrateDependency {
+            id: crate_id.clone(),
+            target: crate_id.name.clone(),
+            alias: None,
+            local_path: None,
+        }
+    }
+
+    fn mock_crate(crate_id: &CrateId, third_party: bool) -> CrateContext {
+        serde_json::from_value(serde_json::json!({
+            "name": crate_id.name,
+            "version": crate_id.version,
+            "repository": third_party.then(|| serde_json::json!({
+                "Http": { "url": format!("https://example.com/{}.crate", crate_id.name) },
+            })),
+            "license_ids": [],
+        }))
+        .unwrap()
+    }
+
+    /// A workspace member depending on `log` and, only on an unsupported platform, `winapi`,
+    /// with a dev dependency on `tracing`, which itself depends on `log`.
+    fn mock_context() -> Context {
+        let member = CrateId::new("member".to_owned(), Version::new(0, 1, 0));
+        let log = CrateId::new("log".to_owned(), Version::new(0, 4, 21));
+        let tracing = CrateId::new("tracing".to_owned(), Version::new(0, 1, 40));
+        let winapi = CrateId::new("winapi".to_owned(), Version::new(0, 3, 9));
+
+        let mut context = Context::default();
+        let mut member_crate = mock_crate(&member, false);
+        let mut deps = Select::new();
+        deps.insert(crate_dependency(&log), None);
+        deps.insert(crate_dependency(&winapi), Some("cfg(windows)".to_owned()));
+        let mut deps_dev = Select::new();
+        deps_dev.insert(crate_dependency(&tracing), None);
+        member_crate.common_attrs = CommonAttributes {
+            deps,
+            deps_dev,
+            ..CommonAttributes::default()
+        };
+
+        let mut tracing_crate = mock_crate(&tracing, true);
+        tracing_crate
+            .common_attrs
+            .deps
+            .insert(crate_dependency(&log), None);
+
+        context.crates = BTreeMap::from([
+            (member.clone(), member_crate),
+            (log.clone(), mock_crate(&log, true)),
+            (tracing, tracing_crate),
+            (winapi.clone(), mock_crate(&winapi, true)),
+        ]);
+        context.workspace_members = BTreeMap::from([(member, String::new())]);
+        context
+            .conditions
+            .insert("cfg(windows)".to_owned(), BTreeSet::new());
+        context
+    }
+
+    fn store(config: &str, audits: &str) -> Store {
+        Store {
+            config: toml::from_str::<ConfigFile>(config).unwrap(),
+            audits: toml::from_str(audits).unwrap(),
+            publishers: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn required_criteria_follow_policies_and_platforms() {
+        let context = mock_context();
+        let required = required_criteria(&context, &Store::default());
+        assert_eq!(
+            required
+                .iter()
+                .map(|(id, criteria)| (id.to_string(), criteria.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "log 0.4.21".to_owned(),
+                    BTreeSet::from([SAFE_TO_DEPLOY.to_owned(), SAFE_TO_RUN.to_owned()])
+                ),
+                (
+                    "tracing 0.1.40".to_owned(),
+                    BTreeSet::from([SAFE_TO_RUN.to_owned()])
+                ),
+            ]
+        );
+
+        let store = store(
+            indoc::indoc! {r#"
+                [policy.member]
+                dev-criteria = "safe-to-deploy"
+
+                [policy.tracing]
+                dependency-criteria = { log = ["crypto-reviewed"] }
+            "#},
+            "",
+        );
+        let required = required_criteria(&context, &store);
+        assert_eq!(
+            required[&CrateId::new("log".to_owned(), Version::new(0, 4, 21))],
+            BTreeSet::from([SAFE_TO_DEPLOY.to_owned(), "crypto-reviewed".to_owned()])
+        );
+    }
+
+    #[test]
+    fn audits_and_missing_deltas() {
+        let context = mock_context();
+        let store = store(
+            indoc::indoc! {r#"
+                [[exemptions.tracing]]
+                version = "0.1.40"
+                criteria = "safe-to-run"
+            "#},
+            indoc::indoc! {r#"
+                [[audits.log]]
+                who = "Someone"
+                criteria = "safe-to-deploy"
+                version = "0.4.19"
+
+                [[audits.log]]
+                who = "Someone"
+                criteria = "safe-to-deploy"
+                delta = "0.4.19 -> 0.4.20"
+            "#},
+        );
+
+        let (failures, checked) = vet_context(&context, &store);
+        assert_eq!(checked, 2);
+        assert_eq!(
+            failures,
+            vec![
+                VetFailure::Missing {
+                    crate_id: CrateId::new("log".to_owned(), Version::new(0, 4, 21)),
+                    criteria: SAFE_TO_DEPLOY.to_owned(),
+                    delta_from: Some(Version::new(0, 4, 20)),
+                },
+                VetFailure::Missing {
+                    crate_id: CrateId::new("log".to_owned(), Version::new(0, 4, 21)),
+                    criteria: SAFE_TO_RUN.to_owned(),
+                    delta_from: Some(Version::new(0, 4, 20)),
+                },
+            ]
+        );
+        assert_eq!(
+            failures[0].to_string(),
+            "`log 0.4.21` is missing a `safe-to-deploy` audit. A delta audit `log 0.4.20 -> 0.4.21` is needed"
+        );
+
+        // A delta audit certifies the version, and `safe-to-deploy` implies `safe-to-run`.
+        let mut store = store;
+        store.audits.audits.get_mut("log").unwrap().push(
+            toml::from_str(indoc::indoc! {r#"
+                criteria = "safe-to-deploy"
+                delta = "0.4.20 -> 0.4.21"
+            "#})
+            .unwrap(),
+        );
+        assert_eq!(vet_context(&context, &store).0, Vec::new());
+    }
+
+    #[test]
+    fn violations() {
+        let context = mock_context();
+        let store = store(
+            "",
+            indoc::indoc! {r#"
+                [[audits.log]]
+                who = "Someone"
+                criteria = "safe-to-run"
+                violation = ">=0.4.21"
+            "#},
+        );
+        let (failures, _) = vet_context(&context, &store);
+        assert_eq!(
+            failures[0],
+            VetFailure::Violation {
+                crate_id: CrateId::new("log".to_owned(), Version::new(0, 4, 21)),
+                criteria: vec![SAFE_TO_RUN.to_owned()],
+            }
+        );
+    }
+
+    #[test]
+    fn wildcard_audits_and_trusted_publishers() {
+        let context = mock_context();
+        let mut store = store(
+            indoc::indoc! {r#"
+                [[exemptions.tracing]]
+                version = "0.1.40"
+                criteria = "safe-to-run"
+            "#},
+            indoc::indoc! {r#"
+                [[trusted.log]]
+                criteria = "safe-to-deploy"
+                user-id = 1
+                start = "2020-01-01"
+                end = "2030-01-01"
+            "#},
+        );
+        let log = CrateId::new("log".to_owned(), Version::new(0, 4, 21));
+
+        // Without a publisher, the trusted entry can't be verified.
+        assert_eq!(
+            vet_context(&context, &store).0,
+            vec![
+                VetFailure::UnknownPublisher {
+                    crate_id: log.clone(),
+                    criteria: SAFE_TO_DEPLOY.to_owned(),
+                },
+                VetFailure::UnknownPublisher {
+                    crate_id: log.clone(),
+                    criteria: SAFE_TO_RUN.to_owned(),
+                },
+            ]
+        );
+        assert!(!vet_context(&context, &store).0[0].is_failure());
+
+        let publisher = |when: &str, user_id: u64| PublisherEntry {
+            version: log.version.clone(),
+            when: when.to_owned(),
+            user_id,
+        };
+        store.publishers = BTreeMap::from([("log".to_owned(), vec![publisher("2024-05-01", 1)])]);
+        assert_eq!(vet_context(&context, &store).0, Vec::new());
+
+        // Versions published by someone else, or outside of the period, aren't certified.
+        for publisher in [publisher("2024-05-01", 2), publisher("2030-01-01", 1)] {
+            store.publishers = BTreeMap::from([("log".to_owned(), vec![publisher])]);
+            let failures = vet_context(&context, &store).0;
+            assert_eq!(failures.len(), 2);
+            assert!(failures.iter().all(VetFailure::is_failure));
+        }
+
+        // Wildcard audits certify versions in the same way.
+        store.audits.wildcard_audits = std::mem::take(&mut store.audits.trusted);
+        store.publishers = BTreeMap::from([("log".to_owned(), vec![publisher("2024-05-01", 1)])]);
+        assert_eq!(vet_context(&context, &store).0, Vec::new());
+    }
+}
@@ -0,0 +1,445 @@
+//! Parsing of the [cargo vet](https://mozilla.github.io/cargo-vet/) `supply-chain` directory.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Deserializer};
+
+/// The criteria every crate is held to unless a policy says otherwise.
+pub(crate) const SAFE_TO_DEPLOY: &str = "safe-to-deploy";
+
+/// The criteria dev dependencies are held to unless a policy says otherwise.
+pub(crate) const SAFE_TO_RUN: &str = "safe-to-run";
+
+/// Accept either a single string or a list of strings.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Accept a date as either a string or a TOML date, as `YYYY-MM-DD`.
+fn date<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        String(String),
+        Toml(toml::value::Datetime),
+    }
+
+    Ok(match Date::deserialize(deserializer)? {
+        Date::String(value) => value,
+        Date::Toml(value) => value.to_string(),
+    })
+}
+
+fn optional_one_or_many<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    one_or_many(deserializer).map(Some)
+}
+
+/// An audit of the changes between two versions of a crate (`1.0.0 -> 1.1.0`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Delta {
+    pub(crate) from: Version,
+    pub(crate) to: Version,
+}
+
+impl<'de> Deserialize<'de> for Delta {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        let parse = || -> Result<Self> {
+            let Some((from, to)) = value.split_once("->") else {
+                bail!("Expected `<from> -> <to>`");
+            };
+            Ok(Self {
+                from: Version::parse(from.trim())?,
+                to: Version::parse(to.trim())?,
+            })
+        };
+        parse().map_err(|err| serde::de::Error::custom(format!("Invalid delta `{value}`: {err}")))
+    }
+}
+
+impl fmt::Display for Delta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)
+    }
+}
+
+/// A custom criteria defined in `audits.toml`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CriteriaEntry {
+    /// Other criteria which this criteria implies.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) implies: Vec<String>,
+}
+
+/// A single `[[audits.<crate>]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct AuditEntry {
+    /// The criteria the audited code satisfies.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) criteria: Vec<String>,
+
+    /// The version which was fully audited.
+    pub(crate) version: Option<Version>,
+
+    /// The versions between which the changes were audited.
+    pub(crate) delta: Option<Delta>,
+
+    /// Versions which were found to violate the criteria.
+    pub(crate) violation: Option<VersionReq>,
+}
+
+/// A `[[wildcard-audits.<crate>]]` or `[[trusted.<crate>]]` entry, certifying every version
+/// of a crate published by a user between two dates.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PublisherAuditEntry {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) criteria: Vec<String>,
+
+    /// The crates.io id of the publisher.
+    pub(crate) user_id: u64,
+
+    /// The first day versions are certified, inclusive.
+    #[serde(deserialize_with = "date")]
+    pub(crate) start: String,
+
+    /// The last day versions are certified, exclusive.
+    #[serde(deserialize_with = "date")]
+    pub(crate) end: String,
+}
+
+impl PublisherAuditEntry {
+    /// Whether the entry certifies a version with the given publisher.
+    pub(crate) fn covers(&self, publisher: &PublisherEntry) -> bool {
+        self.user_id == publisher.user_id
+            && self.start <= publisher.when
+            && publisher.when < self.end
+    }
+}
+
+/// The contents of `audits.toml`, or of an import within `imports.lock`.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct AuditsFile {
+    #[serde(default)]
+    pub(crate) criteria: BTreeMap<String, CriteriaEntry>,
+
+    #[serde(default)]
+    pub(crate) audits: BTreeMap<String, Vec<AuditEntry>>,
+
+    #[serde(default, rename = "wildcard-audits")]
+    pub(crate) wildcard_audits: BTreeMap<String, Vec<PublisherAuditEntry>>,
+
+    /// Publishers trusted by the workspace. These are never imported.
+    #[serde(default)]
+    pub(crate) trusted: BTreeMap<String, Vec<PublisherAuditEntry>>,
+}
+
+/// A `[[publisher.<crate>]]` entry of `imports.lock`, recording who published a version.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PublisherEntry {
+    pub(crate) version: Version,
+
+    /// The day the version was published.
+    #[serde(deserialize_with = "date")]
+    pub(crate) when: String,
+
+    pub(crate) user_id: u64,
+}
+
+/// A `[policy.<crate>]` entry.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PolicyEntry {
+    /// The criteria required of the crate's dependencies.
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub(crate) criteria: Option<Vec<String>>,
+
+    /// The criteria required of the crate's dev dependencies.
+    #[serde(default, deserialize_with = "optional_one_or_many")]
+    pub(crate) dev_criteria: Option<Vec<String>>,
+
+    /// Criteria required of specific dependencies, overriding `criteria` and `dev_criteria`.
+    #[serde(default)]
+    pub(crate) dependency_criteria: BTreeMap<String, Vec<String>>,
+}
+
+/// A single `[[exemptions.<crate>]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ExemptionEntry {
+    pub(crate) version: Version,
+
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) criteria: Vec<String>,
+}
+
+/// The contents of `config.toml`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ConfigFile {
+    /// The criteria required of dependencies without a policy. Defaults to [SAFE_TO_DEPLOY].
+    pub(crate) default_criteria: Option<String>,
+
+    /// Other organizations' audits which are trusted. The audits themselves are in `imports.lock`.
+    #[serde(default)]
+    pub(crate) imports: BTreeMap<String, toml::Value>,
+
+    /// Policies keyed by crate name, optionally suffixed with `:<version>`.
+    #[serde(default)]
+    pub(crate) policy: BTreeMap<String, PolicyEntry>,
+
+    #[serde(default)]
+    pub(crate) exemptions: BTreeMap<String, Vec<ExemptionEntry>>,
+}
+
+impl ConfigFile {
+    /// The policy of a crate, whether keyed by name alone or by name and version.
+    pub(crate) fn policy(&self, name: &str, version: &Version) -> Option<&PolicyEntry> {
+        self.policy
+            .get(&format!("{name}:{version}"))
+            .or_else(|| self.policy.get(name))
+    }
+}
+
+/// The contents of `imports.lock`.
+#[derive(Debug, Default, Clone, Deserialize)]
+struct ImportsLock {
+    #[serde(default)]
+    publisher: BTreeMap<String, Vec<PublisherEntry>>,
+
+    #[serde(default)]
+    audits: BTreeMap<String, AuditsFile>,
+}
+
+/// All `cargo vet` data of a workspace.
+#[derive(Debug, Default)]
+pub(crate) struct Store {
+    pub(crate) config: ConfigFile,
+
+    /// The workspace's own audits merged with all imported ones. Custom criteria of imports
+    /// are namespaced as `<import>::<criteria>`.
+    pub(crate) audits: AuditsFile,
+
+    /// The publishers of crate versions, which wildcard audits and trusted entries apply to.
+    pub(crate) publishers: BTreeMap<String, Vec<PublisherEntry>>,
+}
+
+fn read_toml<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+impl Store {
+    /// Load the `config.toml`, `audits.toml` and `imports.lock` files of a `supply-chain`
+    /// directory.
+    pub(crate) fn load(supply_chain_dir: &Path) -> Result<Self> {
+        let config: ConfigFile = read_toml(&supply_chain_dir.join("config.toml"))?;
+        let audits: AuditsFile = read_toml(&supply_chain_dir.join("audits.toml"))?;
+
+        let imports_path = supply_chain_dir.join("imports.lock");
+        let imports: ImportsLock = if imports_path.exists() {
+            read_toml(&imports_path)?
+        } else {
+            ImportsLock::default()
+        };
+
+        Ok(Self::new(config, audits, imports.audits, imports.publisher))
+    }
+
+    fn new(
+        config: ConfigFile,
+        mut audits: AuditsFile,
+        imports: BTreeMap<String, AuditsFile>,
+        publishers: BTreeMap<String, Vec<PublisherEntry>>,
+    ) -> Self {
+        for (import, imported) in imports {
+            // Only audits of imports which are still configured are trusted.
+            if !config.imports.contains_key(&import) {
+                continue;
+            }
+
+            let namespace = |criteria: &String| match criteria.as_str() {
+                SAFE_TO_DEPLOY | SAFE_TO_RUN => criteria.clone(),
+                _ => format!("{import}::{criteria}"),
+            };
+
+            for (name, entry) in &imported.criteria {
+                audits.criteria.insert(
+                    namespace(name),
+                    CriteriaEntry {
+                        implies: entry.implies.iter().map(namespace).collect(),
+                    },
+                );
+            }
+            for (name, entries) in imported.audits {
+                audits
+                    .audits
+                    .entry(name)
+                    .or_default()
+                    .extend(entries.into_iter().map(|entry| AuditEntry {
+                        criteria: entry.criteria.iter().map(namespace).collect(),
+                        ..entry
+                    }));
+            }
+            for (name, entries) in imported.wildcard_audits {
+                audits
+                    .wildcard_audits
+                    .entry(name)
+                    .or_default()
+                    .extend(entries.into_iter().map(|entry| PublisherAuditEntry {
+                        criteria: entry.criteria.iter().map(namespace).collect(),
+                        ..entry
+                    }));
+            }
+        }
+
+        Self {
+            config,
+            audits,
+            publishers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_supply_chain() {
+        let config: ConfigFile = toml::from_str(indoc::indoc! {r#"
+            [cargo-vet]
+            version = "0.10"
+
+            [imports.example]
+            url = "https://example.com/audits.toml"
+
+            [policy.my-crate]
+            criteria = "safe-to-run"
+            dependency-criteria = { log = ["safe-to-deploy"] }
+
+            [[exemptions.log]]
+            version = "0.4.20"
+            criteria = "safe-to-deploy"
+        "#})
+        .unwrap();
+        assert_eq!(
+            config
+                .policy("my-crate", &Version::new(0, 1, 0))
+                .unwrap()
+                .criteria,
+            Some(vec![SAFE_TO_RUN.to_owned()])
+        );
+        assert_eq!(config.exemptions["log"][0].version, Version::new(0, 4, 20));
+
+        let audits: AuditsFile = toml::from_str(indoc::indoc! {r#"
+            [criteria.crypto-reviewed]
+            description = "Cryptography was reviewed"
+            implies = "safe-to-run"
+
+            [[audits.log]]
+            who = "Someone <someone@example.com>"
+            criteria = ["safe-to-deploy", "crypto-reviewed"]
+            delta = "0.4.20 -> 0.4.21"
+
+            [[audits.log]]
+            who = "Someone <someone@example.com>"
+            criteria = "safe-to-deploy"
+            violation = ">=0.5.0"
+        "#})
+        .unwrap();
+        assert_eq!(
+            audits.criteria["crypto-reviewed"].implies,
+            vec![SAFE_TO_RUN.to_owned()]
+        );
+        assert_eq!(
+            audits.audits["log"][0].delta.as_ref().unwrap().to_string(),
+            "0.4.20 -> 0.4.21"
+        );
+        assert!(audits.audits["log"][1]
+            .violation
+            .as_ref()
+            .unwrap()
+            .matches(&Version::new(0, 5, 1)));
+
+        let imported: AuditsFile = toml::from_str(indoc::indoc! {r#"
+            [criteria.reviewed]
+            description = "Reviewed"
+
+            [[audits.serde]]
+            who = "Someone Else"
+            criteria = ["safe-to-deploy", "reviewed"]
+            version = "1.0.0"
+
+            [[wildcard-audits.cxx]]
+            who = "Someone Else"
+            criteria = "reviewed"
+            user-id = 3618
+            start = "2019-03-12"
+            end = 2024-04-27
+        "#})
+        .unwrap();
+        let imports: ImportsLock = toml::from_str(indoc::indoc! {r#"
+            [[publisher.cxx]]
+            version = "1.0.94"
+            when = "2023-03-30"
+            user-id = 3618
+            user-login = "dtolnay"
+        "#})
+        .unwrap();
+        let store = Store::new(
+            config,
+            audits,
+            BTreeMap::from([
+                ("example".to_owned(), imported.clone()),
+                ("removed".to_owned(), imported),
+            ]),
+            imports.publisher,
+        );
+        assert!(store.audits.criteria.contains_key("example::reviewed"));
+        assert!(!store.audits.criteria.contains_key("removed::reviewed"));
+        assert_eq!(
+            store.audits.audits["serde"][0].criteria,
+            vec![SAFE_TO_DEPLOY.to_owned(), "example::reviewed".to_owned()]
+        );
+        assert_eq!(store.audits.audits["serde"].len(), 1);
+
+        let wildcard_audit = &store.audits.wildcard_audits["cxx"][0];
+        assert_eq!(
+            wildcard_audit.criteria,
+            vec!["example::reviewed".to_owned()]
+        );
+        assert_eq!(wildcard_audit.end, "2024-04-27");
+        assert!(wildcard_audit.covers(&store.publishers["cxx"][0]));
+    }
+}
@@ -0,0 +1,110 @@
+//! The cli entrypoint for the `why` subcommand
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use camino::Utf8PathBuf;
+use clap::Parser;
+
+use crate::cli::Result;
+use crate::config::load_config_and_splicing_manifest;
+use crate::metadata::{Cargo, FeatureProvenance, TreeResolver};
+use crate::splicing::{generate_lockfile, Splicer};
+
+/// Command line options for the `why` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `why` subcommand", version)]
+pub struct WhyOptions {
+    /// The feature to explain, in the form `crate/feature` (e.g. `tokio/full`).
+    pub feature: String,
+
+    /// The platform triple to explain the feature for. This must be one of the
+    /// `supported_platform_triples` of the config.
+    #[clap(long)]
+    pub platform: String,
+
+    /// A generated manifest of splicing inputs. Derived from `--config` if it is a
+    /// `cargo-bazel.toml` file.
+    #[clap(long)]
+    pub splicing_manifest: Option<PathBuf>,
+
+    /// The path to a [Cargo.lock](https://doc.rust-lang.org/cargo/guide/cargo-toml-vs-cargo-lock.html) file.
+    #[clap(long)]
+    pub cargo_lockfile: Option<PathBuf>,
+
+    /// The path to the config file (containing [crate::config::Config].) This may also be
+    /// a `cargo-bazel.toml` file, in which case `--splicing-manifest` is optional.
+    #[clap(long)]
+    pub config: PathBuf,
+
+    /// The path to a Cargo binary to use for gathering metadata
+    #[clap(long, env = "CARGO")]
+    pub cargo: PathBuf,
+
+    /// The path to a rustc binary for use with Cargo
+    #[clap(long, env = "RUSTC")]
+    pub rustc: PathBuf,
+}
+
+/// Print the chains of dependents and features which enable a feature of a crate.
+pub fn why(opt: WhyOptions) -> Result<()> {
+    let (crate_name, feature) = opt.feature.split_once('/').ok_or_else(|| {
+        anyhow!(
+            "Features must be in the form `crate/feature`. Got: `{}`",
+            opt.feature
+        )
+    })?;
+
+    let (config, splicing_manifest) =
+        load_config_and_splicing_manifest(&opt.config, opt.splicing_manifest.as_deref())
+            .context("Failed to parse config")?;
+
+    let platform = config
+        .supported_platform_triples
+        .iter()
+        .find(|triple| triple.to_bazel() == opt.platform)
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "`{}` is not one of the supported platform triples: {:?}",
+                opt.platform,
+                config.supported_platform_triples
+            )
+        })?;
+
+    let temp_dir = tempfile::tempdir().context("Failed to generate temporary directory")?;
+    let splicing_dir = Utf8PathBuf::from_path_buf(temp_dir.as_ref().to_path_buf())
+        .unwrap_or_else(|path| panic!("Temporary directory wasn't valid UTF-8: {:?}", path));
+
+    let cargo = Cargo::new(opt.cargo, opt.rustc.clone())
+        .with_registry_credentials(splicing_manifest.registry_credentials()?);
+
+    let manifest_path = Splicer::new(splicing_dir, splicing_manifest)?
+        .splice_workspace()
+        .context("Failed to splice workspace")?;
+
+    generate_lockfile(
+        &manifest_path,
+        &opt.cargo_lockfile,
+        cargo.clone(),
+        &None,
+        config.version_resolution,
+    )
+    .context("Failed to generate lockfile")?;
+
+    // Features are resolved for every platform, as they are when generating, since proc-macros
+    // and build scripts are resolved for the host platforms among them.
+    let resolver_data = TreeResolver::new(cargo)
+        .with_version_resolution(config.version_resolution)
+        .with_feature_provenance(true)
+        .generate(
+            manifest_path.as_path_buf(),
+            &config.supported_platform_triples,
+        )
+        .context("Failed to generate features")?;
+
+    let provenance = FeatureProvenance::new(&resolver_data, &platform, crate_name, feature)?;
+    print!("{provenance}");
+
+    Ok(())
+}
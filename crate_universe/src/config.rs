@@ -1,5 +1,8 @@
 //! A module for configuration information
 
+mod default_annotations;
+mod standalone;
+
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Formatter;
@@ -18,9 +21,12 @@ use serde::de::{Deserializer, SeqAccess, Unexpected, Visitor};
 use serde::{Deserialize, Serialize, Serializer};
 
 use crate::select::{Select, Selectable};
-use crate::utils::starlark::Label;
+use crate::utils::starlark::{Label, Repository};
 use crate::utils::target_triple::TargetTriple;
 
+pub(crate) use self::default_annotations::*;
+pub(crate) use self::standalone::*;
+
 /// Representations of different kinds of crate vendoring into workspaces.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -44,6 +50,65 @@ impl std::fmt::Display for VendorMode {
     }
 }
 
+/// How Cargo picks versions of dependencies when resolving the spliced workspace.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VersionResolution {
+    /// The newest versions allowed by each requirement. This is Cargo's default behavior.
+    #[default]
+    Maximal,
+
+    /// The oldest versions allowed by each requirement, for all dependencies
+    /// (`-Zminimal-versions`).
+    Minimal,
+
+    /// The oldest versions allowed by each requirement of a workspace member, with transitive
+    /// dependencies resolved as usual (`-Zdirect-minimal-versions`).
+    DirectMinimal,
+}
+
+impl VersionResolution {
+    pub(crate) fn is_maximal(&self) -> bool {
+        *self == Self::Maximal
+    }
+
+    /// The unstable Cargo flag which selects this resolution mode, if one is needed.
+    pub(crate) fn cargo_flag(&self) -> Option<&'static str> {
+        match self {
+            Self::Maximal => None,
+            Self::Minimal => Some("-Zminimal-versions"),
+            Self::DirectMinimal => Some("-Zdirect-minimal-versions"),
+        }
+    }
+}
+
+/// How the data of the crates module is split across files.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CratesModuleSharding {
+    /// One shard for each workspace member package.
+    Package,
+
+    /// One shard for each top level directory containing workspace member packages.
+    Directory,
+}
+
+impl CratesModuleSharding {
+    /// The name of the shard holding the data of a workspace member `package`.
+    pub(crate) fn shard_name(&self, package: &str) -> String {
+        let name = match self {
+            Self::Package => package,
+            Self::Directory => package.split('/').next().unwrap_or_default(),
+        };
+        if name.is_empty() {
+            // The root package of the workspace.
+            "_root".to_owned()
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct RenderConfig {
@@ -71,6 +136,12 @@ pub(crate) struct RenderConfig {
     #[serde(default = "default_crates_module_template")]
     pub(crate) crates_module_template: String,
 
+    /// Whether to split the dependency data of the `defs.bzl` crates module into shards.
+    /// BUILD files load the dependency macros from the shard of their package, so only
+    /// its data is loaded. The macros of `defs.bzl` fail with the shard to load instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) crates_module_sharding: Option<CratesModuleSharding>,
+
     /// The pattern used for a crate's repository name.
     /// Eg. `{repository}__{name}-{version}`
     #[serde(default = "default_crate_repository_template")]
@@ -95,6 +166,7 @@ pub(crate) struct RenderConfig {
     pub(crate) platforms_template: String,
 
     /// The command to use for regenerating generated files.
+    #[serde(default)]
     pub(crate) regen_command: String,
 
     /// An optional configuration for rendering content to be rendered into repositories.
@@ -106,7 +178,19 @@ pub(crate) struct RenderConfig {
 
     /// Whether to generate cargo_toml_env_vars targets.
     /// This is expected to always be true except for bootstrapping.
+    #[serde(default = "default_generate_cargo_toml_env_vars")]
     pub(crate) generate_cargo_toml_env_vars: bool,
+
+    /// Whether to generate a `rust_doc` target for every library crate, along with a target in
+    /// the crates module aggregating the documentation of every version of them behind a single
+    /// index. Can be overridden for each crate with [CrateAnnotations::gen_rust_doc].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) generate_rust_docs: bool,
+
+    /// Whether to restrict the visibility of the aliases for each direct dependency to the
+    /// packages of the workspace members which declare it, as Cargo would.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) strict_visibility: bool,
 }
 
 // Default is manually implemented so that the default values match the default
@@ -120,15 +204,18 @@ impl Default for RenderConfig {
             crate_label_template: default_crate_label_template(),
             crate_alias_template: default_crate_alias_template(),
             crates_module_template: default_crates_module_template(),
+            crates_module_sharding: None,
             crate_repository_template: default_crate_repository_template(),
             default_alias_rule: AliasRule::default(),
             default_package_name: Option::default(),
             generate_cargo_toml_env_vars: default_generate_cargo_toml_env_vars(),
+            generate_rust_docs: false,
             generate_target_compatible_with: default_generate_target_compatible_with(),
             platforms_template: default_platforms_template(),
             regen_command: String::default(),
             vendor_mode: Option::default(),
             generate_rules_license_metadata: default_generate_rules_license_metadata(),
+            strict_visibility: false,
         }
     }
 }
@@ -251,10 +338,26 @@ pub(crate) struct CrateAnnotations {
     /// [proc_macro_deps](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-proc_macro_deps) attribute.
     pub(crate) proc_macro_deps: Option<Select<BTreeSet<Label>>>,
 
+    /// The names of crates to remove from the resolved `deps` of the target, on the platforms of
+    /// their configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) deps_remove: Option<Select<BTreeSet<String>>>,
+
+    /// The names of crates to remove from the resolved `proc_macro_deps` of the target, on the
+    /// platforms of their configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) proc_macro_deps_remove: Option<Select<BTreeSet<String>>>,
+
     /// Additional data to pass to  the target's
     /// [crate_features](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-crate_features) attribute.
     pub(crate) crate_features: Option<Select<BTreeSet<String>>>,
 
+    /// Features to remove from the resolved `crate_features` of the target, on the platforms of
+    /// their configuration. Dependencies only enabled by a removed feature are not removed with
+    /// it; see [CrateAnnotations::deps_remove].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) crate_features_remove: Option<Select<BTreeSet<String>>>,
+
     /// Additional data to pass to  the target's
     /// [data](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-data) attribute.
     pub(crate) data: Option<Select<BTreeSet<Label>>>,
@@ -276,8 +379,14 @@ pub(crate) struct CrateAnnotations {
     pub(crate) compile_data_glob_excludes: Option<BTreeSet<String>>,
 
     /// If true, disables pipelining for library targets generated for this crate.
+    #[serde(default)]
     pub(crate) disable_pipelining: bool,
 
+    /// Whether or not to generate a `rust_doc` target for the crate's library. Overrides
+    /// [RenderConfig::generate_rust_docs].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) gen_rust_doc: Option<bool>,
+
     /// Additional data to pass to  the target's
     /// [rustc_env](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-rustc_env) attribute.
     pub(crate) rustc_env: Option<Select<BTreeMap<String, String>>>,
@@ -364,6 +473,15 @@ pub(crate) struct CrateAnnotations {
 
     /// The crates to use instead of the generated one.
     pub(crate) override_targets: Option<BTreeMap<String, Label>>,
+
+    /// Glob patterns of files to always keep when pruning the sources of locally vendored crates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) vendor_keep_glob: Option<BTreeSet<String>>,
+
+    /// Glob patterns of files to remove when pruning the sources of locally vendored crates, even
+    /// if they are used by a generated target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) vendor_exclude_glob: Option<BTreeSet<String>>,
 }
 
 macro_rules! joined_extra_member {
@@ -411,10 +529,14 @@ impl Add for CrateAnnotations {
             gen_build_script: self.gen_build_script.or(rhs.gen_build_script),
             deps: select_merge(self.deps, rhs.deps),
             proc_macro_deps: select_merge(self.proc_macro_deps, rhs.proc_macro_deps),
+            deps_remove: select_merge(self.deps_remove, rhs.deps_remove),
+            proc_macro_deps_remove: select_merge(self.proc_macro_deps_remove, rhs.proc_macro_deps_remove),
             crate_features: select_merge(self.crate_features, rhs.crate_features),
+            crate_features_remove: select_merge(self.crate_features_remove, rhs.crate_features_remove),
             data: select_merge(self.data, rhs.data),
             data_glob: joined_extra_member!(self.data_glob, rhs.data_glob, BTreeSet::new, BTreeSet::extend),
             disable_pipelining: self.disable_pipelining || rhs.disable_pipelining,
+            gen_rust_doc: self.gen_rust_doc.or(rhs.gen_rust_doc),
             compile_data: select_merge(self.compile_data, rhs.compile_data),
             compile_data_glob: joined_extra_member!(self.compile_data_glob, rhs.compile_data_glob, BTreeSet::new, BTreeSet::extend),
             compile_data_glob_excludes: joined_extra_member!(self.compile_data_glob_excludes, rhs.compile_data_glob_excludes, BTreeSet::new, BTreeSet::extend),
@@ -441,6 +563,8 @@ impl Add for CrateAnnotations {
             extra_aliased_targets: joined_extra_member!(self.extra_aliased_targets, rhs.extra_aliased_targets, BTreeMap::new, BTreeMap::extend),
             alias_rule: self.alias_rule.or(rhs.alias_rule),
             override_targets: self.override_targets.or(rhs.override_targets),
+            vendor_keep_glob: joined_extra_member!(self.vendor_keep_glob, rhs.vendor_keep_glob, BTreeSet::new, BTreeSet::extend),
+            vendor_exclude_glob: joined_extra_member!(self.vendor_exclude_glob, rhs.vendor_exclude_glob, BTreeSet::new, BTreeSet::extend),
         };
 
         output
@@ -453,72 +577,212 @@ impl Sum for CrateAnnotations {
     }
 }
 
-/// A subset of `crate.annotation` that we allow packages to define in their
-/// free-form Cargo.toml metadata.
+/// The `crate.annotation` fields a package may define for itself in its free-form Cargo.toml
+/// metadata.
 ///
 /// ```toml
 /// [package.metadata.bazel]
-/// additive_build_file_contents = """
+/// additive_build_file_content = """
 ///     ...
 /// """
-/// data = ["font.woff2"]
+/// data = [":font.woff2"]
+/// build_script_tools = ["@protoc//:protoc"]
 /// extra_aliased_targets = { ... }
 /// gen_build_script = false
 /// ```
 ///
-/// These are considered default values which apply if the Bazel workspace does
-/// not specify a different value for the same annotation in their
-/// crates_repository attributes.
-#[derive(Debug, Deserialize)]
-pub(crate) struct AnnotationsProvidedByPackage {
-    pub(crate) gen_build_script: Option<bool>,
-    pub(crate) data: Option<Select<BTreeSet<Label>>>,
-    pub(crate) data_glob: Option<BTreeSet<String>>,
-    pub(crate) deps: Option<Select<BTreeSet<Label>>>,
-    pub(crate) compile_data: Option<Select<BTreeSet<Label>>>,
-    pub(crate) compile_data_glob: Option<BTreeSet<String>>,
-    pub(crate) compile_data_glob_excludes: Option<BTreeSet<String>>,
-    pub(crate) rustc_env: Option<Select<BTreeMap<String, String>>>,
-    pub(crate) rustc_env_files: Option<Select<BTreeSet<String>>>,
-    pub(crate) rustc_flags: Option<Select<Vec<String>>>,
-    pub(crate) build_script_env: Option<Select<BTreeMap<String, String>>>,
-    pub(crate) build_script_rustc_env: Option<Select<BTreeMap<String, String>>>,
-    pub(crate) build_script_rundir: Option<Select<String>>,
-    pub(crate) additive_build_file_content: Option<String>,
-    pub(crate) extra_aliased_targets: Option<BTreeMap<String, String>>,
+/// Every field of [CrateAnnotations] is accepted. These are considered default values which
+/// apply only where the Bazel workspace does not specify a value for the same field in its own
+/// annotations. The values of a field are never merged. Labels without a repository
+/// (`//pkg:target`) refer to the package's own sources and are resolved against the repository
+/// the crate is generated in.
+///
+/// Unknown keys and invalid values are ignored with a warning. The set of supported annotations
+/// evolves over time across different versions of crate_universe, and we don't want a library
+/// to be impossible to import into Bazel for having old or broken annotations. The Bazel
+/// workspace can specify its own correct annotations.
+///
+/// Annotations in [LOCAL_PACKAGE_KEYS] run arbitrary tools or replace the package's sources and
+/// targets, so they are only honored for packages whose sources are part of the workspace (path
+/// dependencies and workspace members) and ignored with a warning for registry and git sources.
+pub(crate) struct AnnotationsProvidedByPackage;
+
+/// The annotations a package may only provide for itself if it isn't downloaded.
+const LOCAL_PACKAGE_KEYS: &[&str] = &[
+    "additive_build_file_content",
+    "build_script_toolchains",
+    "build_script_tools",
+    "override_targets",
+    "patch_args",
+    "patch_tool",
+    "patches",
+];
+
+impl AnnotationsProvidedByPackage {
+    /// Parse the `bazel` table of a package's metadata.
+    ///
+    /// `package_root` is the label of a target at the root of the crate's generated package.
+    pub(crate) fn parse(package: &Package, package_root: &Label) -> CrateAnnotations {
+        let Some(table) = package.metadata["bazel"].as_object() else {
+            return CrateAnnotations::default();
+        };
+
+        let known_keys = match serde_json::to_value(CrateAnnotations::default()) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+
+        let mut valid = serde_json::Map::new();
+        for (key, value) in table {
+            if package.source.is_some() && LOCAL_PACKAGE_KEYS.contains(&key.as_str()) {
+                tracing::warn!(
+                    "Ignoring key `{}` in the `[package.metadata.bazel]` of `{} {}`: it is only honored for path dependencies and workspace members",
+                    key,
+                    package.name,
+                    package.version
+                );
+                continue;
+            }
+            let single = serde_json::Value::Object(serde_json::Map::from_iter([(
+                key.clone(),
+                value.clone(),
+            )]));
+            match CrateAnnotations::deserialize(&single) {
+                Ok(annotations)
+                    if annotations == CrateAnnotations::default()
+                        && !known_keys.contains_key(key) =>
+                {
+                    tracing::warn!(
+                        "Ignoring unknown key `{}` in the `[package.metadata.bazel]` of `{} {}`",
+                        key,
+                        package.name,
+                        package.version
+                    );
+                }
+                Ok(_) => {
+                    valid.insert(key.clone(), value.clone());
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Ignoring invalid key `{}` in the `[package.metadata.bazel]` of `{} {}`: {}",
+                        key,
+                        package.name,
+                        package.version,
+                        err
+                    );
+                }
+            }
+        }
+
+        // Each key was deserialized successfully on its own, so together they are as well.
+        let annotations =
+            CrateAnnotations::deserialize(serde_json::Value::Object(valid)).unwrap_or_default();
+        annotations.resolve_package_labels(package_root)
+    }
+}
+
+/// Resolve a label from a package's metadata which has no repository against the package's
+/// generated repository.
+fn resolve_package_label(label: Label, package_root: &Label) -> Label {
+    match (label, package_root) {
+        (
+            Label::Absolute {
+                repository: Repository::Local,
+                package,
+                target,
+            },
+            Label::Absolute {
+                repository,
+                package: root,
+                ..
+            },
+        ) => Label::Absolute {
+            repository: repository.clone(),
+            package: [root.as_str(), package.as_str()]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("/"),
+            target,
+        },
+        (label, _) => label,
+    }
 }
 
 impl CrateAnnotations {
-    pub(crate) fn apply_defaults_from_package_metadata(
-        &mut self,
-        pkg_metadata: &serde_json::Value,
-    ) {
+    fn resolve_package_labels(mut self, package_root: &Label) -> Self {
+        let resolve = |label| resolve_package_label(label, package_root);
+        let resolve_select =
+            |select: Option<Select<BTreeSet<Label>>>| select.map(|select| select.map(resolve));
+
+        self.deps = resolve_select(self.deps);
+        self.proc_macro_deps = resolve_select(self.proc_macro_deps);
+        self.data = resolve_select(self.data);
+        self.compile_data = resolve_select(self.compile_data);
+        self.build_script_deps = resolve_select(self.build_script_deps);
+        self.build_script_link_deps = resolve_select(self.build_script_link_deps);
+        self.build_script_proc_macro_deps = resolve_select(self.build_script_proc_macro_deps);
+        self.build_script_compile_data = resolve_select(self.build_script_compile_data);
+        self.build_script_data = resolve_select(self.build_script_data);
+        self.build_script_tools = resolve_select(self.build_script_tools);
+        self.build_script_toolchains = self
+            .build_script_toolchains
+            .map(|labels| labels.into_iter().map(resolve).collect());
+        self.override_targets = self.override_targets.map(|targets| {
+            targets
+                .into_iter()
+                .map(|(kind, label)| (kind, resolve(label)))
+                .collect()
+        });
+        self
+    }
+
+    /// Fill in every field the workspace did not annotate with the value the package provides
+    /// for itself. See [AnnotationsProvidedByPackage].
+    pub(crate) fn apply_defaults_from_package_metadata(&mut self, provided: CrateAnnotations) {
         #[deny(unused_variables)]
-        let AnnotationsProvidedByPackage {
+        let CrateAnnotations {
+            gen_binaries,
             gen_build_script,
+            deps,
+            proc_macro_deps,
+            deps_remove,
+            proc_macro_deps_remove,
+            crate_features,
+            crate_features_remove,
             data,
             data_glob,
-            deps,
             compile_data,
             compile_data_glob,
             compile_data_glob_excludes,
+            disable_pipelining,
+            gen_rust_doc,
             rustc_env,
             rustc_env_files,
             rustc_flags,
+            build_script_deps,
+            build_script_link_deps,
+            build_script_proc_macro_deps,
+            build_script_compile_data,
+            build_script_data,
+            build_script_tools,
+            build_script_data_glob,
             build_script_env,
             build_script_rustc_env,
+            build_script_toolchains,
+            build_script_use_default_shell_env,
             build_script_rundir,
             additive_build_file_content,
+            shallow_since,
+            patch_args,
+            patch_tool,
+            patches,
             extra_aliased_targets,
-        } = match AnnotationsProvidedByPackage::deserialize(&pkg_metadata["bazel"]) {
-            Ok(annotations) => annotations,
-            // Ignore bad annotations. The set of supported annotations evolves
-            // over time across different versions of crate_universe, and we
-            // don't want a library to be impossible to import into Bazel for
-            // having old or broken annotations. The Bazel workspace can specify
-            // its own correct annotations.
-            Err(_) => return,
-        };
+            alias_rule,
+            override_targets,
+            vendor_keep_glob,
+            vendor_exclude_glob,
+        } = provided;
 
         fn default<T>(workspace_value: &mut Option<T>, default_value: Option<T>) {
             if workspace_value.is_none() {
@@ -526,28 +790,61 @@ impl CrateAnnotations {
             }
         }
 
+        default(&mut self.gen_binaries, gen_binaries);
         default(&mut self.gen_build_script, gen_build_script);
-        default(&mut self.gen_build_script, gen_build_script);
+        default(&mut self.deps, deps);
+        default(&mut self.proc_macro_deps, proc_macro_deps);
+        default(&mut self.deps_remove, deps_remove);
+        default(&mut self.proc_macro_deps_remove, proc_macro_deps_remove);
+        default(&mut self.crate_features, crate_features);
+        default(&mut self.crate_features_remove, crate_features_remove);
         default(&mut self.data, data);
         default(&mut self.data_glob, data_glob);
-        default(&mut self.deps, deps);
         default(&mut self.compile_data, compile_data);
         default(&mut self.compile_data_glob, compile_data_glob);
         default(
             &mut self.compile_data_glob_excludes,
             compile_data_glob_excludes,
         );
+        self.disable_pipelining |= disable_pipelining;
+        default(&mut self.gen_rust_doc, gen_rust_doc);
         default(&mut self.rustc_env, rustc_env);
         default(&mut self.rustc_env_files, rustc_env_files);
         default(&mut self.rustc_flags, rustc_flags);
+        default(&mut self.build_script_deps, build_script_deps);
+        default(&mut self.build_script_link_deps, build_script_link_deps);
+        default(
+            &mut self.build_script_proc_macro_deps,
+            build_script_proc_macro_deps,
+        );
+        default(
+            &mut self.build_script_compile_data,
+            build_script_compile_data,
+        );
+        default(&mut self.build_script_data, build_script_data);
+        default(&mut self.build_script_tools, build_script_tools);
+        default(&mut self.build_script_data_glob, build_script_data_glob);
         default(&mut self.build_script_env, build_script_env);
         default(&mut self.build_script_rustc_env, build_script_rustc_env);
+        default(&mut self.build_script_toolchains, build_script_toolchains);
+        default(
+            &mut self.build_script_use_default_shell_env,
+            build_script_use_default_shell_env,
+        );
         default(&mut self.build_script_rundir, build_script_rundir);
         default(
             &mut self.additive_build_file_content,
             additive_build_file_content,
         );
+        default(&mut self.shallow_since, shallow_since);
+        default(&mut self.patch_args, patch_args);
+        default(&mut self.patch_tool, patch_tool);
+        default(&mut self.patches, patches);
         default(&mut self.extra_aliased_targets, extra_aliased_targets);
+        default(&mut self.alias_rule, alias_rule);
+        default(&mut self.override_targets, override_targets);
+        default(&mut self.vendor_keep_glob, vendor_keep_glob);
+        default(&mut self.vendor_exclude_glob, vendor_exclude_glob);
     }
 }
 
@@ -702,6 +999,10 @@ pub(crate) struct Config {
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub(crate) annotations: BTreeMap<CrateNameAndVersionReq, CrateAnnotations>,
 
+    /// Built-in annotations for commonly used crates, applied beneath [Config::annotations].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) default_annotations: Option<DefaultAnnotations>,
+
     /// Settings used to determine various render info
     pub(crate) rendering: RenderConfig,
 
@@ -711,6 +1012,20 @@ pub(crate) struct Config {
     /// A set of platform triples to use in generated select statements
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub(crate) supported_platform_triples: BTreeSet<TargetTriple>,
+
+    /// Whether or not to fail generation if a resolved crate declares a `rust-version` newer
+    /// than the rustc in use. Such crates are otherwise reported as warnings.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) enforce_rust_version: bool,
+
+    /// Whether or not to fail generation if a resolved crate has been yanked from its registry.
+    /// Such crates are otherwise reported as warnings.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) deny_yanked: bool,
+
+    /// How versions of dependencies are chosen when generating a new lockfile
+    #[serde(default, skip_serializing_if = "VersionResolution::is_maximal")]
+    pub(crate) version_resolution: VersionResolution,
 }
 
 impl Config {
@@ -898,6 +1213,113 @@ mod test {
 
     use crate::test::*;
 
+    #[test]
+    fn annotations_provided_by_package() {
+        let mut package = mock_cargo_metadata_package();
+        package.source = None;
+        package.metadata = serde_json::json!({
+            "bazel": {
+                "build_script_tools": ["//tools:protoc", "@protoc//:protoc"],
+                "data": [":font.woff2"],
+                "gen_binaries": ["mock-bin"],
+                "gen_build_script": "sometimes",
+                "not_an_annotation": true,
+            }
+        });
+        let package_root = Label::from_str("@crates__mock-pkg-3.3.3//:mock-pkg").unwrap();
+
+        let annotations = AnnotationsProvidedByPackage::parse(&package, &package_root);
+        assert_eq!(
+            annotations,
+            CrateAnnotations {
+                build_script_tools: Some(Select::from_value(BTreeSet::from([
+                    Label::from_str("@crates__mock-pkg-3.3.3//tools:protoc").unwrap(),
+                    Label::from_str("@protoc//:protoc").unwrap(),
+                ]))),
+                data: Some(Select::from_value(BTreeSet::from([Label::from_str(
+                    ":font.woff2"
+                )
+                .unwrap()]))),
+                gen_binaries: Some(GenBinaries::Some(BTreeSet::from(["mock-bin".to_owned()]))),
+                ..CrateAnnotations::default()
+            }
+        );
+
+        // Vendored crates live in a package of the workspace.
+        let package_root = Label::from_str("//vendor/mock-pkg-3.3.3:mock-pkg").unwrap();
+        let annotations = AnnotationsProvidedByPackage::parse(&package, &package_root);
+        assert_eq!(
+            annotations.build_script_tools.unwrap().values()[1],
+            Label::from_str("//vendor/mock-pkg-3.3.3/tools:protoc").unwrap()
+        );
+    }
+
+    #[test]
+    fn downloaded_packages_cannot_provide_local_annotations() {
+        let mut package = mock_cargo_metadata_package();
+        package.metadata = serde_json::json!({
+            "bazel": {
+                "additive_build_file_content": "genrule(...)",
+                "build_script_tools": ["@protoc//:protoc"],
+                "data": [":font.woff2"],
+                "override_targets": {"lib": "@evil//:lib"},
+                "patches": ["//:evil.patch"],
+            }
+        });
+        let package_root = Label::from_str("@crates__mock-pkg-3.3.3//:mock-pkg").unwrap();
+
+        for source in [
+            "registry+https://github.com/rust-lang/crates.io-index",
+            "git+https://github.com/mock/mock-pkg?rev=abc#abc",
+        ] {
+            package.source = Some(cargo_metadata::Source {
+                repr: source.to_owned(),
+            });
+            assert_eq!(
+                AnnotationsProvidedByPackage::parse(&package, &package_root),
+                CrateAnnotations {
+                    data: Some(Select::from_value(BTreeSet::from([Label::from_str(
+                        ":font.woff2"
+                    )
+                    .unwrap()]))),
+                    ..CrateAnnotations::default()
+                },
+                "{source}"
+            );
+        }
+
+        package.source = None;
+        let annotations = AnnotationsProvidedByPackage::parse(&package, &package_root);
+        assert!(annotations.additive_build_file_content.is_some());
+        assert!(annotations.build_script_tools.is_some());
+        assert!(annotations.override_targets.is_some());
+        assert!(annotations.patches.is_some());
+    }
+
+    #[test]
+    fn workspace_annotations_take_precedence_over_package() {
+        let mut annotations = CrateAnnotations {
+            rustc_flags: Some(Select::from_value(vec!["--cfg=workspace".to_owned()])),
+            ..CrateAnnotations::default()
+        };
+        annotations.apply_defaults_from_package_metadata(CrateAnnotations {
+            rustc_flags: Some(Select::from_value(vec!["--cfg=package".to_owned()])),
+            crate_features: Some(Select::from_value(BTreeSet::from(["std".to_owned()]))),
+            disable_pipelining: true,
+            ..CrateAnnotations::default()
+        });
+
+        assert_eq!(
+            annotations,
+            CrateAnnotations {
+                rustc_flags: Some(Select::from_value(vec!["--cfg=workspace".to_owned()])),
+                crate_features: Some(Select::from_value(BTreeSet::from(["std".to_owned()]))),
+                disable_pipelining: true,
+                ..CrateAnnotations::default()
+            }
+        );
+    }
+
     #[test]
     fn test_crate_id_serde() {
         let id: CrateId = serde_json::from_str("\"crate 0.1.0\"").unwrap();
@@ -991,6 +1413,7 @@ mod test {
         assert!(config.cargo_config.is_none());
         assert!(!config.generate_binaries);
         assert!(!config.generate_build_scripts);
+        assert_eq!(config.version_resolution, VersionResolution::Maximal);
 
         // Render Config
         assert_eq!(
@@ -998,4 +1421,38 @@ mod test {
             "//custom/platform:{triple}"
         );
     }
+
+    #[test]
+    fn version_resolution_serde() {
+        assert_eq!(
+            serde_json::from_str::<VersionResolution>("\"direct_minimal\"").unwrap(),
+            VersionResolution::DirectMinimal
+        );
+        assert_eq!(
+            serde_json::to_string(&VersionResolution::Minimal).unwrap(),
+            "\"minimal\""
+        );
+
+        assert_eq!(VersionResolution::Maximal.cargo_flag(), None);
+        assert_eq!(
+            VersionResolution::Minimal.cargo_flag(),
+            Some("-Zminimal-versions")
+        );
+        assert_eq!(
+            VersionResolution::DirectMinimal.cargo_flag(),
+            Some("-Zdirect-minimal-versions")
+        );
+    }
+
+    #[test]
+    fn crates_module_shard_names() {
+        let package = CratesModuleSharding::Package;
+        assert_eq!(package.shard_name("tools/lib"), "tools/lib");
+        assert_eq!(package.shard_name(""), "_root");
+
+        let directory = CratesModuleSharding::Directory;
+        assert_eq!(directory.shard_name("tools/lib"), "tools");
+        assert_eq!(directory.shard_name("app"), "app");
+        assert_eq!(directory.shard_name(""), "_root");
+    }
 }
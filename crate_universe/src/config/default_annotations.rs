@@ -0,0 +1,110 @@
+//! A versioned set of [CrateAnnotations] for crates which commonly need them to build with Bazel.
+//!
+//! Each version is frozen once released so that opting into one never changes the outputs of a
+//! repository. Changes to the set are shipped as a new version.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{CrateAnnotations, CrateNameAndVersionReq};
+
+/// A released version of the default annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DefaultAnnotationsVersion {
+    V1,
+}
+
+impl DefaultAnnotationsVersion {
+    fn content(&self) -> &'static str {
+        match self {
+            Self::V1 => include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/config/default_annotations/v1.json"
+            )),
+        }
+    }
+}
+
+/// The default annotations a workspace opted into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DefaultAnnotations {
+    /// The version of the default annotations to use.
+    pub(crate) version: DefaultAnnotationsVersion,
+
+    /// The names of crates whose default annotations should not be applied.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) disabled: BTreeSet<String>,
+}
+
+impl DefaultAnnotations {
+    /// The annotations of the selected version, excluding those of any disabled crates.
+    pub(crate) fn annotations(&self) -> Result<BTreeMap<CrateNameAndVersionReq, CrateAnnotations>> {
+        let mut annotations: BTreeMap<CrateNameAndVersionReq, CrateAnnotations> =
+            serde_json::from_str(self.version.content()).with_context(|| {
+                format!("Failed to parse the {:?} default annotations", self.version)
+            })?;
+
+        let unknown: BTreeSet<&String> = self
+            .disabled
+            .iter()
+            .filter(|name| !annotations.keys().any(|id| &id.name == *name))
+            .collect();
+        if !unknown.is_empty() {
+            bail!(
+                "Default annotations were disabled for crates which have none. Please remove them: {:?}",
+                unknown
+            );
+        }
+
+        annotations.retain(|id, _| !self.disabled.contains(&id.name));
+        Ok(annotations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disable_default_annotations() {
+        let all = DefaultAnnotations {
+            version: DefaultAnnotationsVersion::V1,
+            disabled: BTreeSet::new(),
+        }
+        .annotations()
+        .unwrap();
+        assert!(all.keys().any(|id| id.name == "libz-sys"));
+
+        let enabled = DefaultAnnotations {
+            version: DefaultAnnotationsVersion::V1,
+            disabled: BTreeSet::from(["libz-sys".to_owned()]),
+        }
+        .annotations()
+        .unwrap();
+        assert!(!enabled.keys().any(|id| id.name == "libz-sys"));
+        assert_eq!(enabled.len(), all.len() - 1);
+
+        let unknown = DefaultAnnotations {
+            version: DefaultAnnotationsVersion::V1,
+            disabled: BTreeSet::from(["not-a-crate".to_owned()]),
+        }
+        .annotations();
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn deserialize_default_annotations() {
+        let default_annotations: DefaultAnnotations =
+            serde_json::from_str(r#"{"version": "v1", "disabled": ["libz-sys"]}"#).unwrap();
+        assert_eq!(
+            default_annotations,
+            DefaultAnnotations {
+                version: DefaultAnnotationsVersion::V1,
+                disabled: BTreeSet::from(["libz-sys".to_owned()]),
+            }
+        );
+    }
+}
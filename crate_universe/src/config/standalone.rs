@@ -0,0 +1,315 @@
+//! Support for a human-written `cargo-bazel.toml` configuration file.
+//!
+//! The repository rules produce a [Config] and a [SplicingManifest] as JSON. This module allows
+//! the same information to be described in a single TOML file so the CLI can be used without Bazel.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Deserialize;
+
+use crate::config::{
+    Config, CrateAnnotations, CrateNameAndVersionReq, DefaultAnnotations, RenderConfig,
+    VersionResolution,
+};
+use crate::splicing::SplicingManifest;
+use crate::utils::starlark::Label;
+use crate::utils::target_triple::TargetTriple;
+
+/// The contents of a `cargo-bazel.toml` file.
+///
+/// All paths are relative to the directory containing the file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StandaloneConfig {
+    /// See [Config::generate_binaries].
+    #[serde(default)]
+    generate_binaries: bool,
+
+    /// See [Config::generate_build_scripts].
+    #[serde(default = "default_generate_build_scripts")]
+    generate_build_scripts: bool,
+
+    /// See [Config::annotations].
+    #[serde(default)]
+    annotations: BTreeMap<CrateNameAndVersionReq, CrateAnnotations>,
+
+    /// See [Config::default_annotations].
+    default_annotations: Option<DefaultAnnotations>,
+
+    /// See [Config::rendering].
+    rendering: RenderConfig,
+
+    /// The path to a Cargo config file.
+    cargo_config: Option<Utf8PathBuf>,
+
+    /// See [Config::supported_platform_triples].
+    #[serde(default)]
+    supported_platform_triples: BTreeSet<TargetTriple>,
+
+    /// The paths of the `Cargo.toml` files to splice together.
+    #[serde(default)]
+    manifests: BTreeSet<Utf8PathBuf>,
+
+    /// Packages to add to the spliced workspace in addition to the ones found in `manifests`.
+    #[serde(default)]
+    packages: BTreeMap<String, cargo_toml::DependencyDetail>,
+
+    /// See [Config::enforce_rust_version].
+    #[serde(default)]
+    enforce_rust_version: bool,
+
+    /// See [Config::deny_yanked].
+    #[serde(default)]
+    deny_yanked: bool,
+
+    /// See [Config::version_resolution].
+    #[serde(default)]
+    version_resolution: VersionResolution,
+
+    /// The Cargo resolver version to use for splicing.
+    #[serde(default = "default_resolver_version")]
+    resolver_version: cargo_toml::Resolver,
+}
+
+fn default_generate_build_scripts() -> bool {
+    true
+}
+
+fn default_resolver_version() -> cargo_toml::Resolver {
+    cargo_toml::Resolver::V2
+}
+
+impl StandaloneConfig {
+    pub(crate) fn try_from_path<T: AsRef<Path>>(path: T) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref())
+            .with_context(|| format!("Failed to read {}", path.as_ref().display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.as_ref().display()))
+    }
+
+    /// Convert the config into the [Config] and [SplicingManifest] the repository rules would
+    /// have produced. `root` is the directory relative paths in the config are resolved against.
+    pub(crate) fn into_config_and_splicing_manifest(
+        self,
+        root: &Utf8Path,
+    ) -> Result<(Config, SplicingManifest)> {
+        if self.manifests.is_empty() && self.packages.is_empty() {
+            bail!("At least one of `manifests` or `packages` must be set");
+        }
+
+        let manifests = self
+            .manifests
+            .into_iter()
+            .map(|path| {
+                let label = manifest_label(&path)?;
+                Ok((root.join(path), label))
+            })
+            .collect::<Result<BTreeMap<Utf8PathBuf, Label>>>()?;
+
+        let cargo_config_path = self.cargo_config.map(|path| root.join(path));
+        let cargo_config = cargo_config_path
+            .as_ref()
+            .map(|path| {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read Cargo config {path}"))?;
+                toml::from_str::<toml::Value>(&content)
+                    .with_context(|| format!("Failed to parse Cargo config {path}"))
+            })
+            .transpose()?;
+
+        let config = Config {
+            generate_binaries: self.generate_binaries,
+            generate_build_scripts: self.generate_build_scripts,
+            annotations: self.annotations,
+            default_annotations: self.default_annotations,
+            rendering: self.rendering,
+            cargo_config,
+            supported_platform_triples: self.supported_platform_triples,
+            enforce_rust_version: self.enforce_rust_version,
+            deny_yanked: self.deny_yanked,
+            version_resolution: self.version_resolution,
+        };
+
+        let splicing_manifest = SplicingManifest {
+            direct_packages: self.packages,
+            manifests,
+            cargo_config: cargo_config_path,
+            resolver_version: self.resolver_version,
+        };
+
+        Ok((config, splicing_manifest))
+    }
+}
+
+/// Whether or not `path` names a `cargo-bazel.toml` style config rather than a JSON [Config].
+pub(crate) fn is_standalone_config(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// Derive the label of a manifest from its path relative to the workspace root.
+fn manifest_label(path: &Utf8Path) -> Result<Label> {
+    if path.is_absolute() {
+        bail!("Manifest paths must be relative to the config file. Got `{path}`");
+    }
+    if path.file_name() != Some("Cargo.toml") {
+        bail!("Manifest paths must point to a `Cargo.toml` file. Got `{path}`");
+    }
+    let package = path.parent().map(Utf8Path::as_str).unwrap_or_default();
+    Label::from_str(&format!("//{package}:Cargo.toml"))
+}
+
+/// Load a [Config] and [SplicingManifest] for a CLI invocation.
+///
+/// If `config` is a `cargo-bazel.toml` file, the splicing manifest is derived from it unless one
+/// was explicitly provided. Otherwise both are expected to be the JSON files produced by the
+/// repository rules.
+pub(crate) fn load_config_and_splicing_manifest(
+    config: &Path,
+    splicing_manifest: Option<&Path>,
+) -> Result<(Config, SplicingManifest)> {
+    if is_standalone_config(config) {
+        let root = config
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", config.display()))?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let root = Utf8PathBuf::from_path_buf(root)
+            .unwrap_or_else(|path| panic!("Config directory wasn't valid UTF-8: {:?}", path));
+        let (config, derived_splicing_manifest) =
+            StandaloneConfig::try_from_path(config)?.into_config_and_splicing_manifest(&root)?;
+        let splicing_manifest = match splicing_manifest {
+            Some(path) => SplicingManifest::try_from_path(path)?,
+            None => derived_splicing_manifest,
+        };
+        return Ok((config, splicing_manifest));
+    }
+
+    let Some(splicing_manifest) = splicing_manifest else {
+        bail!("The `--splicing-manifest` argument is required unless `--config` is a `cargo-bazel.toml` file");
+    };
+
+    Ok((
+        Config::try_from_path(config)?,
+        SplicingManifest::try_from_path(splicing_manifest)?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::select::Select;
+
+    #[test]
+    fn standalone_config() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(temp_dir.as_ref()).unwrap();
+        fs::write(
+            root.join("config.toml"),
+            "[registries.my-registry]\nindex = \"sparse+https://example.com/\"\n",
+        )
+        .unwrap();
+
+        let standalone: StandaloneConfig = toml::from_str(indoc::indoc! {r#"
+            cargo_config = "config.toml"
+            manifests = ["Cargo.toml", "tools/foo/Cargo.toml"]
+            supported_platform_triples = ["x86_64-unknown-linux-gnu"]
+
+            [packages.serde]
+            version = "1.0"
+            features = ["derive"]
+
+            [rendering]
+            repository_name = "crates"
+            crates_module_template = "//3rdparty/crates:{file}"
+
+            [annotations."rand 0.8.5"]
+            crate_features = ["small_rng"]
+        "#})
+        .unwrap();
+
+        let (config, splicing_manifest) =
+            standalone.into_config_and_splicing_manifest(root).unwrap();
+
+        assert!(!config.generate_binaries);
+        assert!(config.generate_build_scripts);
+        assert_eq!(config.rendering.repository_name, "crates");
+        assert_eq!(
+            config.rendering.crates_module_template,
+            "//3rdparty/crates:{file}"
+        );
+        assert!(config.rendering.generate_cargo_toml_env_vars);
+        assert_eq!(
+            config.supported_platform_triples,
+            BTreeSet::from([TargetTriple::from_bazel(
+                "x86_64-unknown-linux-gnu".to_owned()
+            )])
+        );
+        assert_eq!(
+            config
+                .annotations
+                .get(&CrateNameAndVersionReq::new(
+                    "rand".to_owned(),
+                    "0.8.5".parse().unwrap(),
+                ))
+                .unwrap()
+                .crate_features,
+            Some(Select::from_value(BTreeSet::from(["small_rng".to_owned()])))
+        );
+        assert!(config.cargo_config.unwrap().get("registries").is_some());
+
+        assert_eq!(
+            splicing_manifest.manifests,
+            BTreeMap::from([
+                (
+                    root.join("Cargo.toml"),
+                    Label::from_str("//:Cargo.toml").unwrap()
+                ),
+                (
+                    root.join("tools/foo/Cargo.toml"),
+                    Label::from_str("//tools/foo:Cargo.toml").unwrap()
+                ),
+            ])
+        );
+        assert_eq!(
+            splicing_manifest.cargo_config,
+            Some(root.join("config.toml"))
+        );
+        assert_eq!(
+            splicing_manifest.direct_packages["serde"].version,
+            Some("1.0".to_owned())
+        );
+        assert_eq!(splicing_manifest.resolver_version, cargo_toml::Resolver::V2);
+    }
+
+    #[test]
+    fn standalone_config_requires_inputs() {
+        let standalone: StandaloneConfig = toml::from_str(indoc::indoc! {r#"
+            [rendering]
+            repository_name = "crates"
+        "#})
+        .unwrap();
+
+        assert!(standalone
+            .into_config_and_splicing_manifest(Utf8Path::new("/tmp"))
+            .is_err());
+    }
+
+    #[test]
+    fn standalone_config_rejects_non_manifest_paths() {
+        assert!(manifest_label(Utf8Path::new("foo/BUILD.bazel")).is_err());
+        assert!(manifest_label(Utf8Path::new("/foo/Cargo.toml")).is_err());
+    }
+
+    #[test]
+    fn is_standalone() {
+        assert!(is_standalone_config(Path::new("cargo-bazel.toml")));
+        assert!(!is_standalone_config(Path::new("config.json")));
+    }
+}
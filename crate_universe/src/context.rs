@@ -4,7 +4,6 @@ pub(crate) mod crate_context;
 mod platforms;
 
 use std::collections::{BTreeMap, BTreeSet};
-use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -47,20 +46,10 @@ pub(crate) struct Context {
     pub(crate) direct_dev_deps: BTreeSet<CrateId>,
 
     /// A list of `[patch]` entries from the Cargo.lock file which were not used in the resolve.
-    // TODO: Remove the serde(default) after this has been released for a few versions.
-    // This prevents previous lockfiles (from before this field) from failing to parse with the current version of rules_rust.
-    // After we've supported this (so serialised it in lockfiles) for a few versions,
-    // we can remove the default fallback because existing lockfiles should have the key present.
-    #[serde(default)]
     pub(crate) unused_patches: BTreeSet<cargo_lock::Dependency>,
 }
 
 impl Context {
-    pub(crate) fn try_from_path<T: AsRef<Path>>(path: T) -> Result<Self> {
-        let data = fs::read_to_string(path.as_ref())?;
-        Ok(serde_json::from_str(&data)?)
-    }
-
     pub(crate) fn new(annotations: Annotations, sources_are_present: bool) -> anyhow::Result<Self> {
         // Build a map of crate contexts
         let crates: BTreeMap<CrateId, CrateContext> = annotations
@@ -68,16 +57,22 @@ impl Context {
             .crates
             .values()
             .map(|annotation| {
-                let context = CrateContext::new(
+                let mut context = CrateContext::new(
                     annotation,
                     &annotations.metadata.packages,
                     &annotations.lockfile.crates,
                     &annotations.pairred_extras,
                     &annotations.metadata.workspace_metadata.tree_metadata,
+                    &annotations.config.supported_platform_triples,
                     annotations.config.generate_binaries,
                     annotations.config.generate_build_scripts,
                     sources_are_present,
                 )?;
+                context.common_attrs.profile_rustc_flags = annotations
+                    .metadata
+                    .workspace_metadata
+                    .profiles
+                    .rustc_flags(&context.name);
                 let id = CrateId::new(context.name.clone(), context.version.clone());
                 Ok::<_, anyhow::Error>((id, context))
             })
@@ -207,24 +202,39 @@ impl Context {
     pub(crate) fn workspace_member_deps(&self) -> BTreeSet<CrateDependency> {
         self.workspace_members
             .keys()
-            .map(move |id| &self.crates[id])
-            .flat_map(|ctx| {
-                IntoIterator::into_iter([
-                    &ctx.common_attrs.deps,
-                    &ctx.common_attrs.deps_dev,
-                    &ctx.common_attrs.proc_macro_deps,
-                    &ctx.common_attrs.proc_macro_deps_dev,
-                ])
-                .flat_map(|deps| deps.values())
-                .chain(
-                    ctx.build_script_attrs
-                        .iter()
-                        .flat_map(|attrs| attrs.deps.values()),
-                )
-            })
+            .flat_map(|id| self.direct_deps_of(id))
             .collect()
     }
 
+    /// Map each direct dependency of a workspace member crate to the members which declare it.
+    pub(crate) fn workspace_member_dependents(
+        &self,
+    ) -> BTreeMap<CrateDependency, BTreeSet<&CrateId>> {
+        let mut dependents: BTreeMap<CrateDependency, BTreeSet<&CrateId>> = BTreeMap::new();
+        for id in self.workspace_members.keys() {
+            for dep in self.direct_deps_of(id) {
+                dependents.entry(dep).or_default().insert(id);
+            }
+        }
+        dependents
+    }
+
+    fn direct_deps_of(&self, id: &CrateId) -> impl Iterator<Item = CrateDependency> + '_ {
+        let ctx = &self.crates[id];
+        IntoIterator::into_iter([
+            &ctx.common_attrs.deps,
+            &ctx.common_attrs.deps_dev,
+            &ctx.common_attrs.proc_macro_deps,
+            &ctx.common_attrs.proc_macro_deps_dev,
+        ])
+        .flat_map(|deps| deps.values())
+        .chain(
+            ctx.build_script_attrs
+                .iter()
+                .flat_map(|attrs| attrs.deps.values()),
+        )
+    }
+
     /// Returns if there are any dependencies that have the same crate name and crate version.
     ///
     /// For example, this would return true if you have the following in your workspace:
@@ -7,12 +7,15 @@ use cargo_metadata::{Node, Package, PackageId};
 use serde::{Deserialize, Serialize};
 
 use crate::config::{AliasRule, CrateId, GenBinaries};
+use crate::context::platforms::resolve_cfg_triples;
 use crate::metadata::{
     CrateAnnotation, Dependency, PairedExtras, SourceAnnotation, TreeResolverMetadata,
 };
-use crate::select::Select;
+use crate::select::{Select, SelectableOrderedValue};
+use crate::splicing::CompilationModeFlags;
 use crate::utils::sanitize_module_name;
-use crate::utils::starlark::{Glob, Label};
+use crate::utils::starlark::{looks_like_bazel_configuration_label, Glob, Label};
+use crate::utils::target_triple::TargetTriple;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct CrateDependency {
@@ -144,6 +147,10 @@ pub(crate) struct CommonAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) rustc_flags: Select<Vec<String>>,
 
+    /// Flags derived from Cargo profiles. These precede `rustc_flags`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) profile_rustc_flags: CompilationModeFlags,
+
     pub(crate) version: String,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -171,6 +178,7 @@ impl Default for CommonAttributes {
             rustc_env: Default::default(),
             rustc_env_files: Default::default(),
             rustc_flags: Default::default(),
+            profile_rustc_flags: Default::default(),
             version: Default::default(),
             tags: Default::default(),
         }
@@ -349,6 +357,12 @@ pub(crate) struct CrateContext {
     #[serde(default)]
     pub(crate) disable_pipelining: bool,
 
+    /// Whether to generate a `rust_doc` target for the crate's library, overriding
+    /// [crate::config::RenderConfig::generate_rust_docs].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) gen_rust_doc: Option<bool>,
+
     /// Extra targets that should be aliased.
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     #[serde(default)]
@@ -363,6 +377,21 @@ pub(crate) struct CrateContext {
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     #[serde(default)]
     pub(crate) override_targets: BTreeMap<String, Label>,
+
+    /// Glob patterns of files to always keep when pruning vendored sources.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    #[serde(default)]
+    pub(crate) vendor_keep_glob: BTreeSet<String>,
+
+    /// Glob patterns of files to remove when pruning vendored sources.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    #[serde(default)]
+    pub(crate) vendor_exclude_glob: BTreeSet<String>,
+
+    /// The oldest version of rustc the crate supports, from its `rust-version`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) rust_version: Option<semver::Version>,
 }
 
 impl CrateContext {
@@ -373,6 +402,7 @@ impl CrateContext {
         source_annotations: &BTreeMap<PackageId, SourceAnnotation>,
         extras: &BTreeMap<CrateId, PairedExtras>,
         resolver_data: &TreeResolverMetadata,
+        supported_platform_triples: &BTreeSet<TargetTriple>,
         include_binaries: bool,
         include_build_scripts: bool,
         sources_are_present: bool,
@@ -543,7 +573,7 @@ impl CrateContext {
         };
 
         // Create the crate's context and apply extra settings
-        Ok(CrateContext {
+        CrateContext {
             name: package.name.clone(),
             version: package.version.clone(),
             license: package.license.clone(),
@@ -557,11 +587,16 @@ impl CrateContext {
             build_script_attrs,
             additive_build_file_content: None,
             disable_pipelining: false,
+            gen_rust_doc: None,
             extra_aliased_targets: BTreeMap::new(),
             alias_rule: None,
             override_targets: BTreeMap::new(),
+            vendor_keep_glob: BTreeSet::new(),
+            vendor_exclude_glob: BTreeSet::new(),
+            rust_version: package.rust_version.clone(),
         }
-        .with_overrides(extras))
+        .with_overrides(extras)
+        .with_removals(extras, supported_platform_triples)
     }
 
     fn with_overrides(mut self, extras: &BTreeMap<CrateId, PairedExtras>) -> Self {
@@ -622,6 +657,11 @@ impl CrateContext {
                 self.disable_pipelining = true;
             }
 
+            // Rust doc
+            if crate_extra.gen_rust_doc.is_some() {
+                self.gen_rust_doc = crate_extra.gen_rust_doc;
+            }
+
             // Rustc flags
             if let Some(extra) = &crate_extra.rustc_flags {
                 self.common_attrs.rustc_flags =
@@ -761,11 +801,65 @@ impl CrateContext {
             if let Some(override_targets) = &crate_extra.override_targets {
                 self.override_targets.extend(override_targets.clone());
             }
+
+            // Vendored source pruning
+            if let Some(extra) = &crate_extra.vendor_keep_glob {
+                self.vendor_keep_glob.extend(extra.clone());
+            }
+            if let Some(extra) = &crate_extra.vendor_exclude_glob {
+                self.vendor_exclude_glob.extend(extra.clone());
+            }
         }
 
         self
     }
 
+    /// Apply the subtractive annotations (e.g. [crate::config::CrateAnnotations::deps_remove])
+    /// to the resolved features and dependencies of the crate.
+    fn with_removals(
+        mut self,
+        extras: &BTreeMap<CrateId, PairedExtras>,
+        supported_platform_triples: &BTreeSet<TargetTriple>,
+    ) -> anyhow::Result<Self> {
+        let id = CrateId::new(self.name.clone(), self.version.clone());
+        let Some(paired_override) = extras.get(&id) else {
+            return Ok(self);
+        };
+        let crate_extra = &paired_override.crate_extra;
+
+        // Crate features
+        if let Some(remove) = &crate_extra.crate_features_remove {
+            self.common_attrs.crate_features = subtract(
+                std::mem::take(&mut self.common_attrs.crate_features),
+                remove,
+                |feature, name| feature == name,
+                supported_platform_triples,
+            )?;
+        }
+
+        // Deps
+        if let Some(remove) = &crate_extra.deps_remove {
+            self.common_attrs.deps = subtract(
+                std::mem::take(&mut self.common_attrs.deps),
+                remove,
+                |dep, name| dep.id.name == name,
+                supported_platform_triples,
+            )?;
+        }
+
+        // Proc macro deps
+        if let Some(remove) = &crate_extra.proc_macro_deps_remove {
+            self.common_attrs.proc_macro_deps = subtract(
+                std::mem::take(&mut self.common_attrs.proc_macro_deps),
+                remove,
+                |dep, name| dep.id.name == name,
+                supported_platform_triples,
+            )?;
+        }
+
+        Ok(self)
+    }
+
     fn locate_license_file(package: &Package) -> Option<String> {
         if let Some(license_file_path) = &package.license_file {
             return Some(license_file_path.to_string());
@@ -890,6 +984,87 @@ impl CrateContext {
     }
 }
 
+/// Remove the values of `select` which `matches` an entry of `remove`, on the platforms of the
+/// entry's configuration.
+///
+/// Values which remain on only some of the platforms they applied to are split into
+/// configurations of the remaining platform triples. Values of configurations which are not
+/// triples or `cfg` expressions (e.g. Bazel labels) are only removed by common entries.
+fn subtract<T>(
+    select: Select<BTreeSet<T>>,
+    remove: &Select<BTreeSet<String>>,
+    matches: impl Fn(&T, &str) -> bool,
+    supported_platform_triples: &BTreeSet<TargetTriple>,
+) -> anyhow::Result<Select<BTreeSet<T>>>
+where
+    T: SelectableOrderedValue,
+{
+    let removals = remove
+        .items()
+        .into_iter()
+        .map(|(configuration, name)| {
+            let triples = configuration
+                .map(|configuration| {
+                    resolve_cfg_triples(&configuration, supported_platform_triples)
+                })
+                .transpose()?;
+            Ok((name, triples))
+        })
+        .collect::<anyhow::Result<Vec<(String, Option<BTreeSet<TargetTriple>>)>>>()?;
+
+    // The platforms a value is removed from, where `None` is all of them.
+    let removed_from = |value: &T| -> Option<Option<BTreeSet<TargetTriple>>> {
+        let mut result: Option<BTreeSet<TargetTriple>> = None;
+        for (_, triples) in removals.iter().filter(|(name, _)| matches(value, name)) {
+            match triples {
+                None => return Some(None),
+                Some(triples) => result
+                    .get_or_insert_with(BTreeSet::new)
+                    .extend(triples.clone()),
+            }
+        }
+        result.map(Some)
+    };
+
+    let (common, selects) = select.into_parts();
+    let mut result = Select::new();
+    for value in common {
+        match removed_from(&value) {
+            None => result.insert(value, None),
+            Some(None) => {}
+            Some(Some(removed)) => {
+                for triple in supported_platform_triples.difference(&removed) {
+                    result.insert(value.clone(), Some(triple.to_bazel()));
+                }
+            }
+        }
+    }
+    for (configuration, values) in selects {
+        for value in values {
+            match removed_from(&value) {
+                None => result.insert(value, Some(configuration.clone())),
+                Some(None) => {}
+                Some(Some(removed)) => {
+                    if looks_like_bazel_configuration_label(&configuration) {
+                        result.insert(value, Some(configuration.clone()));
+                        continue;
+                    }
+                    let triples = resolve_cfg_triples(&configuration, supported_platform_triples)?;
+                    if triples.is_disjoint(&removed) {
+                        result.insert(value, Some(configuration.clone()));
+                        continue;
+                    }
+                    for triple in triples.difference(&removed) {
+                        result.insert(value.clone(), Some(triple.to_bazel()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -928,6 +1103,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -945,6 +1121,90 @@ mod test {
         );
     }
 
+    #[test]
+    fn subtract_on_platforms() {
+        let supported_platform_triples = BTreeSet::from([
+            TargetTriple::from_bazel("aarch64-apple-darwin".to_owned()),
+            TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+        ]);
+
+        let mut features = Select::<BTreeSet<String>>::new();
+        for feature in ["a", "b", "c"] {
+            features.insert(feature.to_owned(), None);
+        }
+        features.insert("d".to_owned(), Some("x86_64-unknown-linux-gnu".to_owned()));
+        features.insert("e".to_owned(), Some("cfg(unix)".to_owned()));
+        features.insert("f".to_owned(), Some("@platforms//os:linux".to_owned()));
+
+        let mut remove = Select::<BTreeSet<String>>::new();
+        remove.insert("a".to_owned(), None);
+        for feature in ["b", "e", "f"] {
+            remove.insert(
+                feature.to_owned(),
+                Some(r#"cfg(target_os = "linux")"#.to_owned()),
+            );
+        }
+
+        let result = subtract(
+            features,
+            &remove,
+            |feature, name| feature == name,
+            &supported_platform_triples,
+        )
+        .unwrap();
+
+        let mut expected = Select::<BTreeSet<String>>::new();
+        expected.insert("c".to_owned(), None);
+        expected.insert("b".to_owned(), Some("aarch64-apple-darwin".to_owned()));
+        expected.insert("e".to_owned(), Some("aarch64-apple-darwin".to_owned()));
+        expected.insert("d".to_owned(), Some("x86_64-unknown-linux-gnu".to_owned()));
+        expected.insert("f".to_owned(), Some("@platforms//os:linux".to_owned()));
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn context_with_removals() {
+        let annotations = common_annotations();
+
+        let package_id = PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        };
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let mut pairred_extras = BTreeMap::new();
+        pairred_extras.insert(
+            CrateId::new("common".to_owned(), semver::Version::new(0, 1, 0)),
+            PairedExtras {
+                package_id,
+                crate_extra: CrateAnnotations {
+                    deps_remove: Some(Select::from_value(BTreeSet::from(["bitflags".to_owned()]))),
+                    ..CrateAnnotations::default()
+                },
+            },
+        );
+
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert!(!context
+            .common_attrs
+            .deps
+            .values()
+            .iter()
+            .any(|dep| dep.id.name == "bitflags"));
+    }
+
     #[test]
     fn context_with_overrides() {
         let annotations = common_annotations();
@@ -977,6 +1237,7 @@ mod test {
             &annotations.lockfile.crates,
             &pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1047,6 +1308,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1095,6 +1357,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1132,6 +1395,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1175,6 +1439,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1282,6 +1547,7 @@ mod test {
             CargoTreeEntry {
                 features: BTreeSet::from(["unique_feature".to_owned()]),
                 deps: BTreeSet::new(),
+                enabled_by: BTreeMap::new(),
             },
             // The common config
             None,
@@ -1308,6 +1574,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
@@ -1344,6 +1611,7 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            &annotations.config.supported_platform_triples,
             include_binaries,
             include_build_scripts,
             are_sources_present,
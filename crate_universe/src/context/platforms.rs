@@ -36,8 +36,46 @@ pub(crate) fn resolve_cfg_platforms(
         })
         .collect();
 
-    // Generate target information for each triple string
-    let target_infos = supported_platform_triples
+    let target_infos = target_infos(supported_platform_triples)?;
+    let mut conditions = configurations
+        .into_iter()
+        .map(|cfg| {
+            let triples = matching_triples(&cfg, &target_infos)?;
+            Ok((cfg, triples))
+        })
+        .collect::<Result<BTreeMap<String, BTreeSet<TargetTriple>>>>()?;
+    // Insert identity relationships.
+    for target_triple in supported_platform_triples.iter() {
+        conditions
+            .entry(target_triple.to_bazel())
+            .or_default()
+            .insert(target_triple.clone());
+    }
+    Ok(conditions)
+}
+
+/// Resolve the supported platform triples a single configuration applies to.
+///
+/// Configurations may be `cfg` expressions or platform triples in either their Cargo or Bazel
+/// form.
+pub(crate) fn resolve_cfg_triples(
+    configuration: &str,
+    supported_platform_triples: &BTreeSet<TargetTriple>,
+) -> Result<BTreeSet<TargetTriple>> {
+    if let Some(triple) = supported_platform_triples
+        .iter()
+        .find(|triple| triple.to_bazel() == configuration)
+    {
+        return Ok(BTreeSet::from([triple.clone()]));
+    }
+    matching_triples(configuration, &target_infos(supported_platform_triples)?)
+}
+
+/// Generate target information for each triple string
+fn target_infos(
+    supported_platform_triples: &BTreeSet<TargetTriple>,
+) -> Result<BTreeMap<&TargetTriple, &'static TargetInfo>> {
+    supported_platform_triples
         .iter()
         .map(
             |target_triple| match get_builtin_target_by_triple(&target_triple.to_cargo()) {
@@ -48,64 +86,40 @@ pub(crate) fn resolve_cfg_platforms(
                 )),
             },
         )
-        .collect::<Result<BTreeMap<&TargetTriple, &'static TargetInfo>>>()?;
+        .collect()
+}
 
+/// Check a configuration against each supported triple.
+fn matching_triples(
+    cfg: &str,
+    target_infos: &BTreeMap<&TargetTriple, &'static TargetInfo>,
+) -> Result<BTreeSet<TargetTriple>> {
     // `cfg-expr` does not understand configurations that are simply platform triples
     // (`x86_64-unknown-linux-gnu` vs `cfg(target = "x86_64-unkonwn-linux-gnu")`). So
     // in order to parse configurations, the text is renamed for the check but the
     // original is retained for comaptibility with the manifest.
-    let rename = |cfg: &str| -> String { format!("cfg(target = \"{cfg}\")") };
-    let original_cfgs: BTreeMap<String, String> = configurations
-        .iter()
-        .filter(|cfg| !cfg.starts_with("cfg("))
-        .map(|cfg| (rename(cfg), cfg.clone()))
-        .collect();
+    let cfg = match cfg.starts_with("cfg(") {
+        true => cfg.to_owned(),
+        false => format!("cfg(target = \"{cfg}\")"),
+    };
 
-    let mut conditions = configurations
-        .into_iter()
-        // `cfg-expr` requires that the expressions be actual `cfg` expressions. Any time
-        // there's a target triple (which is a valid constraint), convert it to a cfg expression.
-        .map(|cfg| match cfg.starts_with("cfg(") {
-            true => cfg,
-            false => rename(&cfg),
-        })
-        // Check the current configuration with against each supported triple
-        .map(|cfg| {
-            let expression =
-                Expression::parse(&cfg).context(format!("Failed to parse expression: '{cfg}'"))?;
-
-            let triples = target_infos
-                .iter()
-                .filter(|(_, target_info)| {
-                    expression.eval(|p| match p {
-                        Predicate::Target(tp) => tp.matches(**target_info),
-                        Predicate::KeyValue { key, val } => {
-                            *key == "target" && val == &target_info.triple.as_str()
-                        }
-                        // For now there is no other kind of matching
-                        _ => false,
-                    })
-                })
-                .map(|(triple, _)| (*triple).clone())
-                .collect();
-
-            // Map any renamed configurations back to their original IDs
-            let cfg = match original_cfgs.get(&cfg) {
-                Some(orig) => orig.clone(),
-                None => cfg,
-            };
+    let expression =
+        Expression::parse(&cfg).context(format!("Failed to parse expression: '{cfg}'"))?;
 
-            Ok((cfg, triples))
+    Ok(target_infos
+        .iter()
+        .filter(|(_, target_info)| {
+            expression.eval(|p| match p {
+                Predicate::Target(tp) => tp.matches(**target_info),
+                Predicate::KeyValue { key, val } => {
+                    *key == "target" && val == &target_info.triple.as_str()
+                }
+                // For now there is no other kind of matching
+                _ => false,
+            })
         })
-        .collect::<Result<BTreeMap<String, BTreeSet<TargetTriple>>>>()?;
-    // Insert identity relationships.
-    for target_triple in supported_platform_triples.iter() {
-        conditions
-            .entry(target_triple.to_bazel())
-            .or_default()
-            .insert(target_triple.clone());
-    }
-    Ok(conditions)
+        .map(|(triple, _)| (*triple).clone())
+        .collect())
 }
 
 #[cfg(test)]
@@ -157,9 +171,13 @@ mod test {
             license_file: None,
             additive_build_file_content: None,
             disable_pipelining: false,
+            gen_rust_doc: None,
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            vendor_keep_glob: BTreeSet::default(),
+            vendor_exclude_glob: BTreeSet::default(),
+            rust_version: None,
         };
 
         let configurations =
@@ -216,9 +234,13 @@ mod test {
             license_file: None,
             additive_build_file_content: None,
             disable_pipelining: false,
+            gen_rust_doc: None,
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            vendor_keep_glob: BTreeSet::default(),
+            vendor_exclude_glob: BTreeSet::default(),
+            rust_version: None,
         }
     }
 
@@ -303,9 +325,13 @@ mod test {
             license_file: None,
             additive_build_file_content: None,
             disable_pipelining: false,
+            gen_rust_doc: None,
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            vendor_keep_glob: BTreeSet::default(),
+            vendor_exclude_glob: BTreeSet::default(),
+            rust_version: None,
         };
 
         let configurations =
@@ -370,9 +396,13 @@ mod test {
             license_file: None,
             additive_build_file_content: None,
             disable_pipelining: false,
+            gen_rust_doc: None,
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            vendor_keep_glob: BTreeSet::default(),
+            vendor_exclude_glob: BTreeSet::default(),
+            rust_version: None,
         };
 
         let configurations =
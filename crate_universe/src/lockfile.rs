@@ -9,6 +9,7 @@ use std::process::Command;
 use anyhow::{bail, Context as AnyhowContext, Result};
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use sha2::{Digest as Sha2Digest, Sha256};
 
 use crate::config::Config;
@@ -35,9 +36,109 @@ pub(crate) fn lock_context(
     })
 }
 
+/// The version of the lockfile schema written by this version of cargo-bazel.
+///
+/// Bump this along with a new entry in [MIGRATIONS] whenever a change to the lockfile
+/// would prevent older lockfiles from being read.
+pub(crate) const LOCKFILE_SCHEMA_VERSION: u32 = 1;
+
+/// A migration upgrading the JSON of a lockfile by one schema version.
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Migrations from each schema version to the next, starting from version 0.
+const MIGRATIONS: [Migration; LOCKFILE_SCHEMA_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Version 0 lockfiles predate `schema_version`. The oldest of them also predate
+/// `unused_patches`.
+fn migrate_v0_to_v1(lockfile: &mut Map<String, Value>) -> Result<()> {
+    lockfile
+        .entry("unused_patches")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    Ok(())
+}
+
+/// The serialized form of a lockfile.
+#[derive(Serialize)]
+struct VersionedLockfile<'a> {
+    schema_version: u32,
+
+    #[serde(flatten)]
+    context: &'a Context,
+}
+
+/// A lockfile read from disk and migrated to [LOCKFILE_SCHEMA_VERSION].
+#[derive(Debug)]
+pub(crate) struct LoadedLockfile {
+    pub(crate) context: Context,
+
+    /// The schema version of the lockfile before it was migrated.
+    pub(crate) schema_version: u32,
+}
+
+impl LoadedLockfile {
+    /// Whether or not the lockfile was written with an older schema.
+    pub(crate) fn is_migrated(&self) -> bool {
+        self.schema_version != LOCKFILE_SCHEMA_VERSION
+    }
+}
+
+/// Parse the content of a lockfile, migrating it from older schema versions.
+pub(crate) fn parse_lockfile(content: &str) -> Result<LoadedLockfile> {
+    let mut value: Value = serde_json::from_str(content).context("Lockfile is not valid JSON")?;
+    let Some(lockfile) = value.as_object_mut() else {
+        bail!("Lockfile is not a JSON object");
+    };
+
+    let schema_version = match lockfile.remove("schema_version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .with_context(|| format!("Invalid lockfile `schema_version`: {}", version))?,
+        None => 0,
+    };
+    if schema_version > LOCKFILE_SCHEMA_VERSION {
+        bail!(
+            "Lockfile schema version {} is newer than version {}, the newest supported by this version of cargo-bazel. Update rules_rust or repin the lockfile.",
+            schema_version,
+            LOCKFILE_SCHEMA_VERSION
+        );
+    }
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(schema_version as usize) {
+        migration(lockfile).with_context(|| {
+            format!(
+                "Failed to migrate lockfile from schema version {} to {}",
+                version,
+                version + 1
+            )
+        })?;
+    }
+
+    let context = serde_json::from_value(value).context("Failed to deserialize lockfile")?;
+
+    Ok(LoadedLockfile {
+        context,
+        schema_version,
+    })
+}
+
+/// Read a lockfile from disk, migrating it from older schema versions. Use
+/// [LoadedLockfile::is_migrated] to find out whether it needs to be rewritten.
+pub(crate) fn read_lockfile(path: &Path) -> Result<LoadedLockfile> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Unable to read lockfile `{}`", path.display()))?;
+    let lockfile = parse_lockfile(&content)
+        .with_context(|| format!("Could not load lockfile `{}`", path.display()))?;
+
+    Ok(lockfile)
+}
+
 /// Write a [crate::context::Context] to disk
 pub(crate) fn write_lockfile(lockfile: Context, path: &Path, dry_run: bool) -> Result<()> {
-    let content = serde_json::to_string_pretty(&lockfile)?;
+    let content = serde_json::to_string_pretty(&VersionedLockfile {
+        schema_version: LOCKFILE_SCHEMA_VERSION,
+        context: &lockfile,
+    })?;
 
     if dry_run {
         println!("{content:#?}");
@@ -67,7 +168,6 @@ impl Digest {
         let splicing_metadata = SplicingMetadata::try_from((*splicing_manifest).clone())?;
         let cargo_version = cargo_bin.full_version()?;
         let rustc_version = Self::bin_version(rustc_bin)?;
-        let cargo_bazel_version = env!("CARGO_PKG_VERSION");
 
         // Ensure the checksum of a digest is not present before computing one
         Ok(match context.checksum {
@@ -78,7 +178,7 @@ impl Digest {
                 },
                 config,
                 &splicing_metadata,
-                cargo_bazel_version,
+                LOCKFILE_SCHEMA_VERSION,
                 &cargo_version,
                 &rustc_version,
             ),
@@ -86,7 +186,7 @@ impl Digest {
                 context,
                 config,
                 &splicing_metadata,
-                cargo_bazel_version,
+                LOCKFILE_SCHEMA_VERSION,
                 &cargo_version,
                 &rustc_version,
             ),
@@ -107,7 +207,7 @@ impl Digest {
         context: &Context,
         config: &Config,
         splicing_metadata: &SplicingMetadata,
-        cargo_bazel_version: &str,
+        schema_version: u32,
         cargo_version: &str,
         rustc_version: &str,
     ) -> Self {
@@ -118,9 +218,10 @@ impl Digest {
 
         let mut hasher = Sha256::new();
 
+        // The digest only changes with the version of cargo-bazel when the lockfile schema does.
         hasher.update(Digest::compute_single_hash(
-            cargo_bazel_version,
-            "cargo-bazel version",
+            &schema_version.to_string(),
+            "lockfile schema version",
         ));
         hasher.update(b"\0");
 
@@ -241,13 +342,13 @@ mod test {
             &context,
             &config,
             &splicing_metadata,
-            "0.1.0",
+            1,
             "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
             "rustc 1.57.0 (f1edd0429 2021-11-29)",
         );
 
         assert_eq!(
-            Digest("edd73970897c01af3bb0e6c9d62f572203dd38a03c189dcca555d463990aa086".to_owned()),
+            Digest("88b94b0cef41a247986119e4268aef8285864e2ee03d1f12709a7903dda6e886".to_owned()),
             digest,
         );
     }
@@ -286,13 +387,13 @@ mod test {
             &context,
             &config,
             &splicing_metadata,
-            "0.1.0",
+            1,
             "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
             "rustc 1.57.0 (f1edd0429 2021-11-29)",
         );
 
         assert_eq!(
-            Digest("17a426b0cb5c1eff5114997a35562bc8560afd365ec80ae31b3dcc449ee1fa60".to_owned()),
+            Digest("227247f5b0e6ee47ec9e37ea08b96c0a75608c5a8fc188171319c3e64782df67".to_owned()),
             digest,
         );
     }
@@ -317,13 +418,13 @@ mod test {
             &context,
             &config,
             &splicing_metadata,
-            "0.1.0",
+            1,
             "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
             "rustc 1.57.0 (f1edd0429 2021-11-29)",
         );
 
         assert_eq!(
-            Digest("1e01331686ba1f26f707dc098cd9d21c39d6ccd8e46be03329bb2470d3833e15".to_owned()),
+            Digest("de8ec9823aee7f93a07d9bb3deb858a999a62f12f6e1a100d5b7f1a6ae206470".to_owned()),
             digest,
         );
     }
@@ -340,6 +441,7 @@ mod test {
                         index: "https://artprod.mycompany/artifactory/git/cargo-remote.git"
                             .to_owned(),
                         token: None,
+                        credential_provider: None,
                     },
                 ),
                 (
@@ -347,6 +449,7 @@ mod test {
                     AdditionalRegistry {
                         index: "https://github.com/rust-lang/crates.io-index".to_owned(),
                         token: None,
+                        credential_provider: None,
                     },
                 ),
             ]),
@@ -366,14 +469,58 @@ mod test {
             &context,
             &config,
             &splicing_metadata,
-            "0.1.0",
+            1,
             "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
             "rustc 1.57.0 (f1edd0429 2021-11-29)",
         );
 
         assert_eq!(
-            Digest("45ccf7109db2d274420fac521f4736a1fb55450ec60e6df698e1be4dc2c89fad".to_owned()),
+            Digest("bdfb476cc76adf7f5535560a18d98222c520eee9cb625c7153d5267bcfdb00da".to_owned()),
             digest,
         );
     }
+
+    #[test]
+    fn parse_unversioned_lockfile() {
+        let lockfile = parse_lockfile(
+            r#"{
+                "checksum": null,
+                "crates": {},
+                "binary_crates": [],
+                "workspace_members": {},
+                "conditions": {},
+                "direct_deps": [],
+                "direct_dev_deps": []
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(lockfile.schema_version, 0);
+        assert!(lockfile.is_migrated());
+        assert_eq!(lockfile.context, Context::default());
+    }
+
+    #[test]
+    fn written_lockfile_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.as_ref().join("cargo-bazel-lock.json");
+        write_lockfile(Context::default(), &path, false).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains(&format!(r#""schema_version": {}"#, LOCKFILE_SCHEMA_VERSION)));
+
+        let lockfile = read_lockfile(&path).unwrap();
+        assert_eq!(lockfile.schema_version, LOCKFILE_SCHEMA_VERSION);
+        assert!(!lockfile.is_migrated());
+        assert_eq!(lockfile.context, Context::default());
+    }
+
+    #[test]
+    fn reject_newer_lockfile_schema() {
+        let content = format!(
+            r#"{{"schema_version": {}, "checksum": null}}"#,
+            LOCKFILE_SCHEMA_VERSION + 1
+        );
+        assert!(parse_lockfile(&content).is_err());
+    }
 }
@@ -38,5 +38,21 @@ fn main() -> cli::Result<()> {
             cli::init_logging("Render", level);
             cli::render(opt)
         }
+        cli::Options::Outdated(opt) => {
+            cli::init_logging("Outdated", level);
+            cli::outdated(opt)
+        }
+        cli::Options::Vet(opt) => {
+            cli::init_logging("Vet", level);
+            cli::vet(opt)
+        }
+        cli::Options::Why(opt) => {
+            cli::init_logging("Why", level);
+            cli::why(opt)
+        }
+        cli::Options::Lockfile(opt) => {
+            cli::init_logging("Lockfile", level);
+            cli::lockfile(opt)
+        }
     }
 }
@@ -3,9 +3,12 @@
 mod cargo_bin;
 mod cargo_tree_resolver;
 mod dependency;
+mod feature_provenance;
 mod metadata_annotation;
+mod rust_version;
 mod workspace_discoverer;
 
+use std::collections::BTreeSet;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -17,10 +20,14 @@ use cargo_lock::Lockfile as CargoLockfile;
 use cargo_metadata::Metadata as CargoMetadata;
 use tracing::debug;
 
+use crate::config::VersionResolution;
+
 pub(crate) use self::cargo_bin::*;
 pub(crate) use self::cargo_tree_resolver::*;
 pub(crate) use self::dependency::*;
+pub(crate) use self::feature_provenance::*;
 pub(crate) use self::metadata_annotation::*;
+pub(crate) use self::rust_version::*;
 pub(crate) use self::workspace_discoverer::*;
 
 // TODO: This should also return a set of [crate-index::IndexConfig]s for packages in metadata.packages
@@ -101,6 +108,18 @@ pub enum CargoUpdateRequest {
         /// If set, the `--precise` value that pairs with `--package`.
         version: Option<String>,
     },
+
+    /// Rewrites the version requirements of direct dependencies in the spliced manifests to the
+    /// newest versions available in their registries (like `cargo upgrade`), then translates to
+    /// `cargo update --package foo` for each of the `packages`, or to `cargo update --workspace`
+    /// if none were listed.
+    Upgrade {
+        /// The names of the packages to upgrade. All direct dependencies are upgraded if empty.
+        packages: BTreeSet<String>,
+
+        /// Whether or not requirements may be bumped to semver-incompatible versions.
+        incompatible: bool,
+    },
 }
 
 impl FromStr for CargoUpdateRequest {
@@ -117,6 +136,21 @@ impl FromStr for CargoUpdateRequest {
             return Ok(Self::Workspace);
         }
 
+        // `upgrade` and `upgrade-incompatible`, optionally followed by `=` and a comma
+        // separated list of packages.
+        let (mode, packages) = lower.split_once('=').unwrap_or((lower.as_str(), ""));
+        if ["upgrade", "upgrade-incompatible"].contains(&mode) {
+            return Ok(Self::Upgrade {
+                packages: packages
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|pkg| !pkg.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+                incompatible: mode == "upgrade-incompatible",
+            });
+        }
+
         let mut split = s.splitn(2, '=');
         Ok(Self::Package {
             name: split.next().map(|s| s.to_owned()).unwrap(),
@@ -141,16 +175,28 @@ impl CargoUpdateRequest {
 
                 update_args
             }
+            CargoUpdateRequest::Upgrade { packages, .. } if packages.is_empty() => {
+                vec!["--workspace".to_owned()]
+            }
+            CargoUpdateRequest::Upgrade { packages, .. } => packages
+                .iter()
+                .flat_map(|name| ["--package".to_owned(), name.clone()])
+                .collect(),
         }
     }
 
     /// Calls `cargo update` with arguments specific to the state of the current variant.
-    pub(crate) fn update(&self, manifest: &Path, cargo_bin: &Cargo) -> Result<()> {
+    pub(crate) fn update(
+        &self,
+        manifest: &Path,
+        cargo_bin: &Cargo,
+        version_resolution: VersionResolution,
+    ) -> Result<()> {
         let manifest_dir = manifest.parent().unwrap();
 
         // Simply invoke `cargo update`
         let output = cargo_bin
-            .command()?
+            .command_with_version_resolution(version_resolution)?
             // Cargo detects config files based on `pwd` when running so
             // to ensure user provided Cargo config files are used, it's
             // critical to set the working directory to the manifest dir.
@@ -180,11 +226,22 @@ impl CargoUpdateRequest {
 pub(crate) struct LockGenerator {
     /// Interface to cargo.
     cargo_bin: Cargo,
+
+    /// How versions are chosen when resolving new dependencies.
+    version_resolution: VersionResolution,
 }
 
 impl LockGenerator {
     pub(crate) fn new(cargo_bin: Cargo) -> Self {
-        Self { cargo_bin }
+        Self {
+            cargo_bin,
+            version_resolution: VersionResolution::default(),
+        }
+    }
+
+    pub(crate) fn with_version_resolution(mut self, version_resolution: VersionResolution) -> Self {
+        self.version_resolution = version_resolution;
+        self
     }
 
     #[tracing::instrument(name = "LockGenerator::generate", skip_all)]
@@ -215,7 +272,11 @@ impl LockGenerator {
             fs::copy(lock, &generated_lockfile_path)?;
 
             if let Some(request) = update_request {
-                request.update(manifest_path.as_std_path(), &self.cargo_bin)?;
+                request.update(
+                    manifest_path.as_std_path(),
+                    &self.cargo_bin,
+                    self.version_resolution,
+                )?;
             }
 
             // Ensure the Cargo cache is up to date to simulate the behavior
@@ -259,7 +320,7 @@ impl LockGenerator {
             // Simply invoke `cargo generate-lockfile`
             let output = self
                 .cargo_bin
-                .command()?
+                .command_with_version_resolution(self.version_resolution)?
                 // Cargo detects config files based on `pwd` when running so
                 // to ensure user provided Cargo config files are used, it's
                 // critical to set the working directory to the manifest dir.
@@ -389,6 +450,44 @@ mod test {
         }
     }
 
+    #[test]
+    fn deserialize_cargo_update_request_for_upgrade() {
+        assert_eq!(
+            CargoUpdateRequest::from_str("upgrade").unwrap(),
+            CargoUpdateRequest::Upgrade {
+                packages: BTreeSet::new(),
+                incompatible: false,
+            }
+        );
+        assert_eq!(
+            CargoUpdateRequest::from_str("upgrade-incompatible=serde, tokio").unwrap(),
+            CargoUpdateRequest::Upgrade {
+                packages: BTreeSet::from(["serde".to_owned(), "tokio".to_owned()]),
+                incompatible: true,
+            }
+        );
+    }
+
+    #[test]
+    fn get_update_args_for_upgrade() {
+        let request = CargoUpdateRequest::Upgrade {
+            packages: BTreeSet::new(),
+            incompatible: false,
+        };
+
+        assert_eq!(request.get_update_args(), vec!["--workspace"]);
+
+        let request = CargoUpdateRequest::Upgrade {
+            packages: BTreeSet::from(["serde".to_owned(), "tokio".to_owned()]),
+            incompatible: false,
+        };
+
+        assert_eq!(
+            request.get_update_args(),
+            vec!["--package", "serde", "--package", "tokio"]
+        );
+    }
+
     #[test]
     fn deserialize_cargo_update_request_for_package() {
         let request = CargoUpdateRequest::from_str("cargo-bazel").unwrap();
@@ -10,7 +10,9 @@ use anyhow::{bail, Context, Result};
 use cargo_metadata::MetadataCommand;
 use semver::Version;
 
+use crate::config::VersionResolution;
 use crate::lockfile::Digest;
+use crate::splicing::RegistryCredentials;
 
 /// Cargo encapsulates a path to a `cargo` binary.
 /// Any invocations of `cargo` (either as a `std::process::Command` or via `cargo_metadata`) should
@@ -21,6 +23,7 @@ pub(crate) struct Cargo {
     rustc_path: PathBuf,
     full_version: Arc<Mutex<Option<String>>>,
     cargo_home: Option<PathBuf>,
+    registry_credentials: RegistryCredentials,
 }
 
 impl Cargo {
@@ -30,9 +33,21 @@ impl Cargo {
             rustc_path: rustc,
             full_version: Arc::new(Mutex::new(None)),
             cargo_home: None,
+            registry_credentials: RegistryCredentials::default(),
         }
     }
 
+    /// The private registries whose crates need to be downloaded with authentication. Cargo
+    /// authenticates its own reads of their indexes.
+    pub(crate) fn with_registry_credentials(mut self, credentials: RegistryCredentials) -> Self {
+        self.registry_credentials = credentials;
+        self
+    }
+
+    pub(crate) fn registry_credentials(&self) -> &RegistryCredentials {
+        &self.registry_credentials
+    }
+
     /// Returns a new `Command` for running this cargo.
     pub(crate) fn command(&self) -> Result<Command> {
         let mut command = Command::new(&self.path);
@@ -43,6 +58,27 @@ impl Cargo {
         Ok(command)
     }
 
+    /// Returns a new `Command` for running this cargo which resolves dependency versions
+    /// according to `version_resolution`.
+    pub(crate) fn command_with_version_resolution(
+        &self,
+        version_resolution: VersionResolution,
+    ) -> Result<Command> {
+        let mut command = self.command()?;
+        if let Some(flag) = version_resolution.cargo_flag() {
+            if !self.is_nightly()? {
+                bail!(
+                    "Resolving versions with `{}` requires a nightly cargo, but {} was found. \
+                    Use a nightly Rust toolchain or set `version_resolution = \"maximal\"`.",
+                    flag,
+                    self.full_version()?
+                );
+            }
+            command.arg(flag);
+        }
+        Ok(command)
+    }
+
     /// Returns a new `MetadataCommand` using this cargo.
     /// `manifest_path`, `current_dir`, and `other_options` should not be called on the resturned MetadataCommand - instead pass them as the relevant args.
     pub(crate) fn metadata_command_with_options(
@@ -148,3 +184,38 @@ impl Cargo {
         Ok(map)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cargo_with_version(version: &str) -> Cargo {
+        let cargo = Cargo::new(PathBuf::from("cargo"), PathBuf::from("rustc"));
+        *cargo.full_version.lock().unwrap() = Some(version.to_owned());
+        cargo
+    }
+
+    #[test]
+    fn minimal_version_resolution_requires_nightly() {
+        let stable = cargo_with_version("cargo 1.86.0 (adf9b6ad1 2025-02-28)");
+        let error = stable
+            .command_with_version_resolution(VersionResolution::Minimal)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("requires a nightly cargo"),
+            "{error}"
+        );
+        assert!(stable
+            .command_with_version_resolution(VersionResolution::Maximal)
+            .is_ok());
+
+        let nightly = cargo_with_version("cargo 1.88.0-nightly (d811228b1 2025-04-15)");
+        let command = nightly
+            .command_with_version_resolution(VersionResolution::DirectMinimal)
+            .unwrap();
+        assert!(command
+            .get_args()
+            .any(|arg| arg == "-Zdirect-minimal-versions"));
+        assert!(!command.get_envs().any(|(key, _)| key == "RUSTC_BOOTSTRAP"));
+    }
+}
@@ -12,7 +12,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 use url::Url;
 
-use crate::config::CrateId;
+use crate::config::{CrateId, VersionResolution};
 use crate::metadata::cargo_bin::Cargo;
 use crate::select::{Select, SelectableScalar};
 use crate::utils::symlink::symlink;
@@ -53,6 +53,30 @@ const RUSTC_TRIPLES_WITH_HOST_TOOLS: [&str; 26] = [
     "x86_64-unknown-netbsd",
 ];
 
+/// Something which enables a feature of a crate, i.e. the source of an edge in
+/// `cargo tree --edges features` output.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct FeatureEnabler {
+    /// The crate which depends on the enabled crate or owns the enabling feature.
+    pub crate_id: CrateId,
+
+    /// The enabling feature, if the feature wasn't requested by the dependency declaration itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature: Option<String>,
+}
+
+impl std::fmt::Display for FeatureEnabler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.feature {
+            Some(feature) => write!(f, "{} feature \"{}\"", self.crate_id, feature),
+            None => write!(f, "{}", self.crate_id),
+        }
+    }
+}
+
+/// Each feature of a crate mapped to everything which enables it.
+pub(crate) type FeatureEnablers = BTreeMap<String, BTreeSet<FeatureEnabler>>;
+
 /// Feature resolver info about a given crate.
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub(crate) struct CargoTreeEntry {
@@ -63,6 +87,11 @@ pub(crate) struct CargoTreeEntry {
     /// The dependencies of a given crate based on feature resolution.
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub deps: BTreeSet<CrateId>,
+
+    /// Where each active feature came from. This is only collected when requested
+    /// via [TreeResolver::with_feature_provenance].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub enabled_by: FeatureEnablers,
 }
 
 impl CargoTreeEntry {
@@ -70,16 +99,20 @@ impl CargoTreeEntry {
         Self {
             features: BTreeSet::new(),
             deps: BTreeSet::new(),
+            enabled_by: FeatureEnablers::new(),
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.features.is_empty() && self.deps.is_empty()
+        self.features.is_empty() && self.deps.is_empty() && self.enabled_by.is_empty()
     }
 
     pub fn consume(&mut self, other: Self) {
         self.features.extend(other.features);
         self.deps.extend(other.deps);
+        for (feature, enablers) in other.enabled_by {
+            self.enabled_by.entry(feature).or_default().extend(enablers);
+        }
     }
 }
 
@@ -96,21 +129,44 @@ pub(crate) type TreeResolverMetadata = BTreeMap<CrateId, Select<CargoTreeEntry>>
 pub(crate) struct TreeResolver {
     /// The path to a `cargo` binary
     cargo_bin: Cargo,
+
+    /// How versions are chosen for any dependencies `cargo tree` needs to resolve.
+    version_resolution: VersionResolution,
+
+    /// Whether or not to record what enables each feature.
+    feature_provenance: bool,
 }
 
 impl TreeResolver {
     pub(crate) fn new(cargo_bin: Cargo) -> Self {
-        Self { cargo_bin }
+        Self {
+            cargo_bin,
+            version_resolution: VersionResolution::default(),
+            feature_provenance: false,
+        }
     }
 
-    /// Execute `cargo tree` for each target triple and return the stdout
-    /// streams containing structured output.
+    pub(crate) fn with_version_resolution(mut self, version_resolution: VersionResolution) -> Self {
+        self.version_resolution = version_resolution;
+        self
+    }
+
+    /// Also record the feature edges reported by `cargo tree --edges features` in
+    /// [CargoTreeEntry::enabled_by]. This runs `cargo tree` a second time per platform.
+    pub(crate) fn with_feature_provenance(mut self, feature_provenance: bool) -> Self {
+        self.feature_provenance = feature_provenance;
+        self
+    }
+
+    /// Execute `cargo tree` with the given `--edges` for each target triple and return the
+    /// stdout streams containing structured output.
     fn execute_cargo_tree(
         &self,
         manifest_path: &Path,
         host_triples: &BTreeSet<TargetTriple>,
         target_triples: &BTreeSet<TargetTriple>,
         rustc_wrapper: &Path,
+        edges: &str,
     ) -> Result<BTreeMap<TargetTriple, BTreeMap<TargetTriple, Vec<u8>>>> {
         // A collection of all stdout logs from each process
         let mut stdouts: BTreeMap<TargetTriple, BTreeMap<TargetTriple, Vec<u8>>> = BTreeMap::new();
@@ -149,9 +205,11 @@ impl TreeResolver {
                 // This is unfortunately a bit of a hack. See:
                 // - https://github.com/rust-lang/cargo/issues/9863
                 // - https://github.com/bazelbuild/rules_rust/issues/1662
+                // The project may have been given extra dependencies on proc macros so the
+                // same resolution mode as the lockfile is used for anything that is re-resolved.
                 let child = self
                     .cargo_bin
-                    .command()?
+                    .command_with_version_resolution(self.version_resolution)?
                     // These next two environment variables are used to hack cargo into using a custom
                     // host triple instead of the host triple detected by rustc.
                     .env("RUSTC_WRAPPER", rustc_wrapper)
@@ -162,7 +220,7 @@ impl TreeResolver {
                     .arg("--manifest-path")
                     .arg(manifest_path)
                     .arg("--edges")
-                    .arg("normal,build,dev")
+                    .arg(edges)
                     .arg("--prefix=indent")
                     // https://doc.rust-lang.org/cargo/commands/cargo-tree.html#tree-formatting-options
                     .arg("--format=;{p};{f};")
@@ -343,6 +401,7 @@ impl TreeResolver {
                 &host_triples,
                 target_triples,
                 &rustc_wrapper,
+                "normal,build,dev",
             )?;
 
         let mut metadata: BTreeMap<CrateId, BTreeMap<TargetTriple, CargoTreeEntry>> =
@@ -378,6 +437,34 @@ impl TreeResolver {
             }
         }
 
+        if self.feature_provenance {
+            let feature_tree_streams = self.execute_cargo_tree(
+                &manifest_path_with_transitive_proc_macros,
+                &host_triples,
+                target_triples,
+                &rustc_wrapper,
+                "normal,build,dev,features",
+            )?;
+
+            // Host and target edges aren't distinguished in this output so everything is
+            // attributed to the target platform `cargo tree` was run for.
+            for target_streams in feature_tree_streams.into_values() {
+                for (target_triple, stdout) in target_streams.into_iter() {
+                    for (crate_id, enabled_by) in parse_cargo_tree_feature_edges(stdout.lines())? {
+                        metadata
+                            .entry(crate_id)
+                            .or_default()
+                            .entry(target_triple.clone())
+                            .or_default()
+                            .consume(CargoTreeEntry {
+                                enabled_by,
+                                ..CargoTreeEntry::new()
+                            });
+                    }
+                }
+            }
+        }
+
         // Collect all metadata into a mapping of crate to it's metadata per target.
         let mut result = TreeResolverMetadata::new();
         for (crate_id, tree_data) in metadata.into_iter() {
@@ -406,6 +493,15 @@ impl TreeResolver {
                         },
                     )
                     .unwrap_or_default(),
+                enabled_by: tree_data
+                    .iter()
+                    .fold(None, |common: Option<FeatureEnablers>, (_, data)| {
+                        Some(match common {
+                            Some(common) => intersect_feature_enablers(common, &data.enabled_by),
+                            None => data.enabled_by.clone(),
+                        })
+                    })
+                    .unwrap_or_default(),
             };
             let mut select: Select<CargoTreeEntry> = Select::default();
             for (target_triple, data) in tree_data {
@@ -418,6 +514,15 @@ impl TreeResolver {
                 entry
                     .deps
                     .extend(data.deps.into_iter().filter(|d| !common.deps.contains(d)));
+                for (feature, enablers) in data.enabled_by {
+                    let enablers: BTreeSet<FeatureEnabler> = match common.enabled_by.get(&feature) {
+                        Some(common) => enablers.difference(common).cloned().collect(),
+                        None => enablers,
+                    };
+                    if !enablers.is_empty() {
+                        entry.enabled_by.insert(feature, enablers);
+                    }
+                }
                 if !entry.is_empty() {
                     select.insert(entry, Some(target_triple.to_bazel()));
                 }
@@ -815,28 +920,7 @@ where
             }
             bail!("Unexpected line '{}'", line);
         }
-        // We expect the crate id (parts[1]) to be one of:
-        // `<crate name> v<crate version>`
-        // `<crate name> v<crate version> (<path>)`
-        // `<crate name> v<crate version> (proc-macro)`
-        // `<crate name> v<crate version> (proc-macro) (<path>)`
-        // https://github.com/rust-lang/cargo/blob/19f952f160d4f750d1e12fad2bf45e995719673d/src/cargo/ops/tree/mod.rs#L281
-        let crate_id_parts = parts[1].split(' ').collect::<Vec<_>>();
-        if crate_id_parts.len() < 2 && crate_id_parts.len() > 4 {
-            bail!(
-                "Unexpected crate id format '{}' when parsing 'cargo tree' output.",
-                parts[1]
-            );
-        }
-        let version_str = crate_id_parts[1].strip_prefix('v').ok_or_else(|| {
-            anyhow!(
-                "Unexpected crate version '{}' when parsing 'cargo tree' output.",
-                crate_id_parts[1]
-            )
-        })?;
-        let version = Version::parse(version_str).context("Failed to parse version")?;
-        let crate_id = CrateId::new(crate_id_parts[0].to_owned(), version);
-        let is_proc_macro = crate_id_parts.len() > 2 && crate_id_parts[2] == "(proc-macro)";
+        let (crate_id, is_proc_macro) = parse_cargo_tree_crate_id(parts[1])?;
 
         // Update bookkeeping for dependency tracking. Note that the `cargo tree --prefix=indent`
         // output is expected to have 4 characters per section. We only care about depth but cannot
@@ -942,6 +1026,165 @@ where
     Ok((target_tree_data, host_tree_data))
 }
 
+/// Parses the `{p}` portion of a `cargo tree` line into a [CrateId] and whether
+/// or not the crate is a proc-macro.
+fn parse_cargo_tree_crate_id(package: &str) -> Result<(CrateId, bool)> {
+    // We expect the crate id to be one of:
+    // `<crate name> v<crate version>`
+    // `<crate name> v<crate version> (<path>)`
+    // `<crate name> v<crate version> (proc-macro)`
+    // `<crate name> v<crate version> (proc-macro) (<path>)`
+    // https://github.com/rust-lang/cargo/blob/19f952f160d4f750d1e12fad2bf45e995719673d/src/cargo/ops/tree/mod.rs#L281
+    let crate_id_parts = package.split(' ').collect::<Vec<_>>();
+    if crate_id_parts.len() < 2 && crate_id_parts.len() > 4 {
+        bail!(
+            "Unexpected crate id format '{}' when parsing 'cargo tree' output.",
+            package
+        );
+    }
+    let version_str = crate_id_parts[1].strip_prefix('v').ok_or_else(|| {
+        anyhow!(
+            "Unexpected crate version '{}' when parsing 'cargo tree' output.",
+            crate_id_parts[1]
+        )
+    })?;
+    let version = Version::parse(version_str).context("Failed to parse version")?;
+    let crate_id = CrateId::new(crate_id_parts[0].to_owned(), version);
+    let is_proc_macro = crate_id_parts.len() > 2 && crate_id_parts[2] == "(proc-macro)";
+    Ok((crate_id, is_proc_macro))
+}
+
+/// A node in `cargo tree --edges features` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FeatureTreeNode {
+    Package(CrateId),
+    /// Feature nodes only name their crate. The version is determined by the
+    /// package node beneath them.
+    Feature {
+        name: String,
+        feature: String,
+    },
+}
+
+/// Parses the output of `cargo tree --edges features --format=;{p};{f};` into the features and
+/// dependents which enable each feature of each crate.
+fn parse_cargo_tree_feature_edges<I, S, E>(lines: I) -> Result<BTreeMap<CrateId, FeatureEnablers>>
+where
+    I: Iterator<Item = std::result::Result<S, E>>,
+    S: AsRef<str>,
+    E: std::error::Error + Sync + Send + 'static,
+{
+    let mut nodes: Vec<FeatureTreeNode> = Vec::new();
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    let mut parents: Vec<usize> = Vec::new();
+
+    for line in lines {
+        let line = line?;
+        let line = line.as_ref();
+
+        // Crate names never start with any of the characters used by `--prefix=indent`.
+        let content = line.trim_start_matches([' ', '|', '`', '-']);
+        if content.is_empty() || content.starts_with('[') {
+            // Blank lines separate workspace members and the `[build-dependencies]` and
+            // `[dev-dependencies]` dividers don't change the depth of their children.
+            continue;
+        }
+        let depth = (line.chars().count() - content.chars().count()) / 4;
+
+        let node = if line.contains(';') {
+            let parts = line.split(';').collect::<Vec<_>>();
+            if parts.len() != 4 {
+                bail!("Unexpected line '{}'", line);
+            }
+            FeatureTreeNode::Package(parse_cargo_tree_crate_id(parts[1])?.0)
+        } else {
+            // Feature nodes may be followed by annotations such as `(*)` or `(command-line)`.
+            let (name, feature) = content
+                .split_once(" feature \"")
+                .and_then(|(name, rest)| Some((name, rest.split_once('"')?.0)))
+                .ok_or_else(|| anyhow!("Unexpected line '{}'", line))?;
+            FeatureTreeNode::Feature {
+                name: name.to_owned(),
+                feature: feature.to_owned(),
+            }
+        };
+
+        parents.truncate(depth);
+        if let Some(parent) = parents.last() {
+            edges.push((*parent, nodes.len()));
+        }
+        parents.push(nodes.len());
+        nodes.push(node);
+    }
+
+    // Resolve the versions of feature nodes from the package nodes beneath them. Repeated
+    // nodes marked with `(*)` have no children so they match every version seen for them.
+    let mut resolved: BTreeMap<usize, CrateId> = BTreeMap::new();
+    let mut versions: BTreeMap<(&str, &str), BTreeSet<CrateId>> = BTreeMap::new();
+    for (parent, child) in edges.iter() {
+        if let (FeatureTreeNode::Feature { name, feature }, FeatureTreeNode::Package(crate_id)) =
+            (&nodes[*parent], &nodes[*child])
+        {
+            if *name == crate_id.name {
+                resolved.insert(*parent, crate_id.clone());
+                versions
+                    .entry((name.as_str(), feature.as_str()))
+                    .or_default()
+                    .insert(crate_id.clone());
+            }
+        }
+    }
+    let crate_ids = |index: usize| -> BTreeSet<CrateId> {
+        match &nodes[index] {
+            FeatureTreeNode::Package(crate_id) => BTreeSet::from([crate_id.clone()]),
+            FeatureTreeNode::Feature { name, feature } => match resolved.get(&index) {
+                Some(crate_id) => BTreeSet::from([crate_id.clone()]),
+                None => versions
+                    .get(&(name.as_str(), feature.as_str()))
+                    .cloned()
+                    .unwrap_or_default(),
+            },
+        }
+    };
+
+    let mut result: BTreeMap<CrateId, FeatureEnablers> = BTreeMap::new();
+    for (parent, child) in edges.iter() {
+        let FeatureTreeNode::Feature { feature, .. } = &nodes[*child] else {
+            continue;
+        };
+        let parent_feature = match &nodes[*parent] {
+            FeatureTreeNode::Package(_) => None,
+            FeatureTreeNode::Feature { feature, .. } => Some(feature.clone()),
+        };
+        for crate_id in crate_ids(*child) {
+            for parent_id in crate_ids(*parent) {
+                result
+                    .entry(crate_id.clone())
+                    .or_default()
+                    .entry(feature.clone())
+                    .or_default()
+                    .insert(FeatureEnabler {
+                        crate_id: parent_id,
+                        feature: parent_feature.clone(),
+                    });
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// The enablers of each feature which are common to both `lhs` and `rhs`.
+fn intersect_feature_enablers(lhs: FeatureEnablers, rhs: &FeatureEnablers) -> FeatureEnablers {
+    lhs.into_iter()
+        .filter_map(|(feature, enablers)| {
+            let enablers: BTreeSet<FeatureEnabler> =
+                enablers.intersection(rhs.get(&feature)?).cloned().collect();
+            (!enablers.is_empty()).then_some((feature, enablers))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use std::process;
@@ -1234,6 +1477,7 @@ mod test {
                 CargoTreeEntry {
                     features: BTreeSet::from(["default".to_owned()]),
                     deps: BTreeSet::new(),
+                    enabled_by: BTreeMap::new(),
                 },
                 entry
             );
@@ -1248,6 +1492,7 @@ mod test {
                         "common".to_owned(),
                         Version::new(1, 2, 3)
                     )]),
+                    enabled_by: BTreeMap::new(),
                 },
                 entry
             );
@@ -1263,6 +1508,7 @@ mod test {
                         "common".to_owned(),
                         Version::new(1, 2, 3)
                     )]),
+                    enabled_by: BTreeMap::new(),
                 },
                 entry
             );
@@ -1370,6 +1616,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1377,6 +1624,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "proc-macro".to_owned()]),
                         deps: BTreeSet::from([unicode_ident_id.clone(),]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1384,6 +1632,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "proc-macro".to_owned()]),
                         deps: BTreeSet::from([proc_macro2_id.clone()]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1395,6 +1644,7 @@ mod test {
                             quote_id.clone(),
                             syn_id.clone()
                         ]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1414,6 +1664,7 @@ mod test {
                             quote_id.clone(),
                             unicode_ident_id.clone()
                         ]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1421,6 +1672,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
             ]),
@@ -1451,6 +1703,7 @@ mod test {
                             num_traits_id.clone(),
                             time_id.clone(),
                         ]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1458,6 +1711,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "link".to_owned()]),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1465,6 +1719,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::from([libc_id.clone()]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1472,6 +1727,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["fallback".to_owned()]),
                         deps: BTreeSet::from([core_foundation_sys_id]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1479,6 +1735,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "std".to_owned()]),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1486,6 +1743,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::from([num_traits_id.clone()]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1493,6 +1751,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["i128".to_owned()]),
                         deps: BTreeSet::from([autocfg_id.clone()]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1500,6 +1759,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::from([libc_id]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1507,6 +1767,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::from([chrono_id, cpufeatures_id, serde_derive_id,]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
             ]),
@@ -1590,6 +1851,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1597,6 +1859,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "std".to_owned()]),
                         deps: BTreeSet::from([autocfg_id.clone(),]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1608,6 +1871,7 @@ mod test {
                             quote_id.clone(),
                             version_check_id.clone(),
                         ]),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1615,6 +1879,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "proc-macro".to_owned()]),
                         deps: BTreeSet::from([unicode_ident_id.clone(),]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1622,6 +1887,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::from(["default".to_owned(), "proc-macro".to_owned()]),
                         deps: BTreeSet::from([proc_macro2_id.clone(),]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1640,6 +1906,7 @@ mod test {
                             quote_id.clone(),
                             unicode_ident_id.clone()
                         ]),
+                        enabled_by: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1647,6 +1914,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
                 (
@@ -1654,6 +1922,7 @@ mod test {
                     CargoTreeEntry {
                         features: BTreeSet::new(),
                         deps: BTreeSet::new(),
+                        enabled_by: BTreeMap::new(),
                     }
                 ),
             ]),
@@ -1671,6 +1940,7 @@ mod test {
                         syn_id.clone(),
                         proc_macro_error_attr_id.clone(),
                     ]),
+                    enabled_by: BTreeMap::new(),
                 }
             )]),
             target_output,
@@ -1714,10 +1984,86 @@ mod test {
                 CargoTreeEntry {
                     features: BTreeSet::new(),
                     deps: BTreeSet::new(),
+                    enabled_by: BTreeMap::new(),
                 },
             ),]),
             host_output,
             "Failed checking host dependencies."
         );
     }
+
+    #[test]
+    fn parse_cargo_tree_feature_edges_test() {
+        let edges = parse_cargo_tree_feature_edges(
+            textwrap::dedent(
+                r#"
+                ;root v0.1.0 (/tmp/ftree/root);;
+                |-- a feature "default" (command-line)
+                |   |-- ;a v0.2.0 (/tmp/ftree/a);default,full,net,std;
+                |   |   `-- ;b v0.3.0 (/tmp/ftree/b);default,extra,std;
+                |   `-- a feature "std"
+                |       `-- ;a v0.2.0 (/tmp/ftree/a);default,full,net,std; (*)
+                |-- a feature "full"
+                |   |-- ;a v0.2.0 (/tmp/ftree/a);default,full,net,std; (*)
+                |   |-- a feature "net"
+                |   |   `-- ;a v0.2.0 (/tmp/ftree/a);default,full,net,std; (*)
+                |   `-- b feature "extra"
+                |       `-- ;b v0.3.0 (/tmp/ftree/b);default,extra,std;
+                `-- b feature "default"
+                    |-- ;b v0.3.0 (/tmp/ftree/b);default,extra,std;
+                    `-- b feature "std"
+                        `-- ;b v0.3.0 (/tmp/ftree/b);default,extra,std;
+                [build-dependencies]
+                |-- b feature "default" (*)
+                `-- b feature "extra" (*)
+                "#,
+            )
+            .lines()
+            .map(Ok::<&str, std::io::Error>),
+        )
+        .unwrap();
+
+        let root = CrateId::new("root".to_owned(), Version::new(0, 1, 0));
+        let a = CrateId::new("a".to_owned(), Version::new(0, 2, 0));
+        let b = CrateId::new("b".to_owned(), Version::new(0, 3, 0));
+        let enabler = |crate_id: &CrateId, feature: Option<&str>| FeatureEnabler {
+            crate_id: crate_id.clone(),
+            feature: feature.map(str::to_owned),
+        };
+
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    a.clone(),
+                    BTreeMap::from([
+                        ("default".to_owned(), BTreeSet::from([enabler(&root, None)])),
+                        ("full".to_owned(), BTreeSet::from([enabler(&root, None)])),
+                        (
+                            "net".to_owned(),
+                            BTreeSet::from([enabler(&a, Some("full"))])
+                        ),
+                        (
+                            "std".to_owned(),
+                            BTreeSet::from([enabler(&a, Some("default"))])
+                        ),
+                    ])
+                ),
+                (
+                    b.clone(),
+                    BTreeMap::from([
+                        ("default".to_owned(), BTreeSet::from([enabler(&root, None)])),
+                        (
+                            "extra".to_owned(),
+                            BTreeSet::from([enabler(&a, Some("full")), enabler(&root, None)])
+                        ),
+                        (
+                            "std".to_owned(),
+                            BTreeSet::from([enabler(&b, Some("default"))])
+                        ),
+                    ])
+                ),
+            ]),
+            edges
+        );
+    }
 }
@@ -817,6 +817,7 @@ mod test {
                     CrateId::new("is-terminal".to_owned(), Version::new(0, 4, 5)),
                     CrateId::new("termcolor".to_owned(), Version::new(1, 2, 0)),
                 ]),
+                enabled_by: BTreeMap::new(),
             },
             None,
         );
@@ -898,6 +899,7 @@ mod test {
             CargoTreeEntry {
                 features: BTreeSet::new(),
                 deps: BTreeSet::from([CrateId::new("ecdsa".to_owned(), Version::new(0, 16, 8))]),
+                enabled_by: BTreeMap::new(),
             },
             None,
         );
@@ -941,6 +943,7 @@ mod test {
                     "sync".to_owned(),
                     "time".to_owned(),
                 ]),
+                enabled_by: BTreeMap::new(),
             },
             Some("x86_64-unknown-linux-gnu".to_owned()),
         );
@@ -955,6 +958,7 @@ mod test {
                     "default".to_owned(),
                     "io-util".to_owned(),
                 ]),
+                enabled_by: BTreeMap::new(),
             },
             None,
         );
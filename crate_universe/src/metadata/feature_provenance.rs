@@ -0,0 +1,287 @@
+//! Explanations of why a feature is enabled on a crate.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use anyhow::{bail, Result};
+
+use crate::config::CrateId;
+use crate::metadata::{CargoTreeEntry, FeatureEnabler, TreeResolverMetadata};
+use crate::utils::target_triple::TargetTriple;
+
+/// The chains of dependents and features which enable a feature of a crate on one platform,
+/// rendered like `cargo tree --invert --edges features`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FeatureProvenance {
+    roots: Vec<ProvenanceNode>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ProvenanceNode {
+    enabler: FeatureEnabler,
+
+    /// Whether or not this node was already expanded elsewhere in the tree.
+    repeated: bool,
+
+    children: Vec<ProvenanceNode>,
+}
+
+impl FeatureProvenance {
+    /// Explain why `feature` is enabled on every version of the crate named `crate_name`
+    /// when building for `platform`. The `metadata` must be generated with
+    /// [crate::metadata::TreeResolver::with_feature_provenance].
+    pub(crate) fn new(
+        metadata: &TreeResolverMetadata,
+        platform: &TargetTriple,
+        crate_name: &str,
+        feature: &str,
+    ) -> Result<Self> {
+        let entries: BTreeMap<&CrateId, CargoTreeEntry> = metadata
+            .iter()
+            .map(|(crate_id, select)| (crate_id, entry_for_platform(select.items(), platform)))
+            .collect();
+
+        let mut dependents: BTreeMap<&CrateId, BTreeSet<&CrateId>> = BTreeMap::new();
+        for (crate_id, entry) in entries.iter() {
+            for dep in entry.deps.iter() {
+                dependents.entry(dep).or_default().insert(crate_id);
+            }
+        }
+
+        if !entries.keys().any(|crate_id| crate_id.name == crate_name) {
+            bail!("No crate named `{}` was found", crate_name);
+        }
+
+        let mut visited = BTreeSet::new();
+        let roots: Vec<ProvenanceNode> = entries
+            .iter()
+            .filter(|(crate_id, entry)| {
+                crate_id.name == crate_name && entry.features.contains(feature)
+            })
+            .map(|(crate_id, _)| {
+                expand(
+                    FeatureEnabler {
+                        crate_id: (*crate_id).clone(),
+                        feature: Some(feature.to_owned()),
+                    },
+                    &entries,
+                    &dependents,
+                    &mut visited,
+                )
+            })
+            .collect();
+
+        if roots.is_empty() {
+            bail!(
+                "`{}/{}` is not enabled on platform `{}`",
+                crate_name,
+                feature,
+                platform
+            );
+        }
+
+        Ok(Self { roots })
+    }
+}
+
+/// Combine the common and platform specific values of a crate's tree data.
+fn entry_for_platform(
+    items: Vec<(Option<String>, CargoTreeEntry)>,
+    platform: &TargetTriple,
+) -> CargoTreeEntry {
+    let mut result = CargoTreeEntry::new();
+    for (configuration, entry) in items {
+        if configuration.is_none() || configuration == Some(platform.to_bazel()) {
+            result.consume(entry);
+        }
+    }
+    result
+}
+
+/// Build the tree of everything enabling `enabler`. Features are enabled by the entries recorded
+/// for them and crates are enabled by the crates which depend on them.
+fn expand(
+    enabler: FeatureEnabler,
+    entries: &BTreeMap<&CrateId, CargoTreeEntry>,
+    dependents: &BTreeMap<&CrateId, BTreeSet<&CrateId>>,
+    visited: &mut BTreeSet<FeatureEnabler>,
+) -> ProvenanceNode {
+    if !visited.insert(enabler.clone()) {
+        return ProvenanceNode {
+            enabler,
+            repeated: true,
+            children: Vec::new(),
+        };
+    }
+
+    let parents: Vec<FeatureEnabler> = match &enabler.feature {
+        Some(feature) => entries
+            .get(&enabler.crate_id)
+            .and_then(|entry| entry.enabled_by.get(feature))
+            .map(|enablers| enablers.iter().cloned().collect())
+            .unwrap_or_default(),
+        None => dependents
+            .get(&enabler.crate_id)
+            .map(|crate_ids| {
+                crate_ids
+                    .iter()
+                    .map(|crate_id| FeatureEnabler {
+                        crate_id: (*crate_id).clone(),
+                        feature: None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    let children = parents
+        .into_iter()
+        .map(|parent| expand(parent, entries, dependents, visited))
+        .collect();
+
+    ProvenanceNode {
+        enabler,
+        repeated: false,
+        children,
+    }
+}
+
+impl fmt::Display for FeatureProvenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_node(
+            f: &mut fmt::Formatter<'_>,
+            node: &ProvenanceNode,
+            prefix: &str,
+            is_last: bool,
+            is_root: bool,
+        ) -> fmt::Result {
+            let (branch, indent) = match (is_root, is_last) {
+                (true, _) => ("", ""),
+                (false, true) => ("`-- ", "    "),
+                (false, false) => ("|-- ", "|   "),
+            };
+            write!(f, "{}{}{}", prefix, branch, node.enabler)?;
+            if node.repeated {
+                write!(f, " (*)")?;
+            }
+            writeln!(f)?;
+
+            let prefix = format!("{}{}", prefix, indent);
+            for (i, child) in node.children.iter().enumerate() {
+                write_node(f, child, &prefix, i + 1 == node.children.len(), false)?;
+            }
+            Ok(())
+        }
+
+        for (i, root) in self.roots.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write_node(f, root, "", true, true)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+    use semver::Version;
+
+    use super::*;
+
+    use crate::select::Select;
+
+    fn enabler(name: &str, version: Version, feature: Option<&str>) -> FeatureEnabler {
+        FeatureEnabler {
+            crate_id: CrateId::new(name.to_owned(), version),
+            feature: feature.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn explain_platform_specific_feature() {
+        let root = CrateId::new("root".to_owned(), Version::new(0, 1, 0));
+        let hyper = CrateId::new("hyper".to_owned(), Version::new(0, 14, 0));
+        let tokio = CrateId::new("tokio".to_owned(), Version::new(1, 38, 0));
+        let linux = TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned());
+
+        let mut root_select = Select::new();
+        root_select.insert(
+            CargoTreeEntry {
+                features: BTreeSet::new(),
+                deps: BTreeSet::from([hyper.clone(), tokio.clone()]),
+                enabled_by: BTreeMap::new(),
+            },
+            None,
+        );
+
+        let mut hyper_select = Select::new();
+        hyper_select.insert(
+            CargoTreeEntry {
+                features: BTreeSet::from(["runtime".to_owned()]),
+                deps: BTreeSet::from([tokio.clone()]),
+                enabled_by: BTreeMap::from([(
+                    "runtime".to_owned(),
+                    BTreeSet::from([enabler("root", Version::new(0, 1, 0), None)]),
+                )]),
+            },
+            None,
+        );
+
+        let mut tokio_select = Select::new();
+        tokio_select.insert(
+            CargoTreeEntry {
+                features: BTreeSet::from(["rt".to_owned()]),
+                deps: BTreeSet::new(),
+                enabled_by: BTreeMap::from([(
+                    "rt".to_owned(),
+                    BTreeSet::from([enabler("hyper", Version::new(0, 14, 0), Some("runtime"))]),
+                )]),
+            },
+            None,
+        );
+        tokio_select.insert(
+            CargoTreeEntry {
+                features: BTreeSet::from(["full".to_owned(), "rt".to_owned()]),
+                deps: BTreeSet::new(),
+                enabled_by: BTreeMap::from([
+                    (
+                        "full".to_owned(),
+                        BTreeSet::from([enabler("root", Version::new(0, 1, 0), None)]),
+                    ),
+                    (
+                        "rt".to_owned(),
+                        BTreeSet::from([
+                            enabler("hyper", Version::new(0, 14, 0), Some("runtime")),
+                            enabler("tokio", Version::new(1, 38, 0), Some("full")),
+                        ]),
+                    ),
+                ]),
+            },
+            Some(linux.to_bazel()),
+        );
+
+        let metadata = TreeResolverMetadata::from([
+            (root, root_select),
+            (hyper, hyper_select),
+            (tokio, tokio_select),
+        ]);
+
+        let provenance = FeatureProvenance::new(&metadata, &linux, "tokio", "rt").unwrap();
+        assert_eq!(
+            provenance.to_string(),
+            indoc! {r#"
+                tokio 1.38.0 feature "rt"
+                |-- hyper 0.14.0 feature "runtime"
+                |   `-- root 0.1.0
+                `-- tokio 1.38.0 feature "full"
+                    `-- root 0.1.0 (*)
+            "#}
+        );
+
+        let macos = TargetTriple::from_bazel("aarch64-apple-darwin".to_owned());
+        assert!(FeatureProvenance::new(&metadata, &macos, "tokio", "full").is_err());
+        assert!(FeatureProvenance::new(&metadata, &macos, "serde", "std").is_err());
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use anyhow::{bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
@@ -9,10 +10,12 @@ use cargo_metadata::{Node, Package, PackageId};
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{Commitish, Config, CrateAnnotations, CrateId};
+use crate::config::{AnnotationsProvidedByPackage, Commitish, Config, CrateAnnotations, CrateId};
 use crate::metadata::dependency::DependencySet;
 use crate::metadata::TreeResolverMetadata;
+use crate::rendering::render_crate_bazel_label;
 use crate::splicing::{SourceInfo, WorkspaceMetadata};
+use crate::utils::starlark::Label;
 
 pub(crate) type CargoMetadata = cargo_metadata::Metadata;
 pub(crate) type CargoLockfile = cargo_lock::Lockfile;
@@ -162,6 +165,10 @@ pub(crate) enum SourceAnnotation {
         /// See [http_archive::patches](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patches)
         #[serde(default, skip_serializing_if = "Option::is_none")]
         patches: Option<BTreeSet<String>>,
+
+        /// See [http_archive::auth_patterns](https://bazel.build/rules/lib/repo/http#http_archive-auth_patterns)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auth_patterns: Option<BTreeMap<String, String>>,
     },
     Path {
         /// Local path to crate's source, relative to Bazel workspace root.
@@ -253,6 +260,7 @@ impl LockfileAnnotation {
             None => match spliced_source_info {
                 Some(info) => {
                     return Ok(SourceAnnotation::Http {
+                        auth_patterns: info.auth_patterns(),
                         url: info.url,
                         sha256: Some(info.sha256),
                         patch_args: None,
@@ -340,6 +348,7 @@ impl LockfileAnnotation {
         // other sources may more accurately represent where a crate should be downloaded.
         if let Some(info) = spliced_source_info {
             return Ok(SourceAnnotation::Http {
+                auth_patterns: info.auth_patterns(),
                 url: info.url,
                 sha256: Some(info.sha256),
                 patch_args: None,
@@ -372,6 +381,7 @@ impl LockfileAnnotation {
                 patch_args: None,
                 patch_tool: None,
                 patches: None,
+                auth_patterns: None,
             });
         }
 
@@ -465,6 +475,12 @@ impl Annotations {
 
         let mut unused_extra_annotations = config.annotations.clone();
 
+        // Default annotations are applied wherever they match and are never reported as unused.
+        let default_annotations = match &config.default_annotations {
+            Some(default_annotations) => default_annotations.annotations()?,
+            None => BTreeMap::new(),
+        };
+
         // Ensure each override matches a particular package
         let pairred_extras = metadata_annotation
             .packages
@@ -484,7 +500,28 @@ impl Annotations {
                     .cloned()
                     .sum();
 
-                crate_extra.apply_defaults_from_package_metadata(&pkg.metadata);
+                // Workspace annotations take precedence over those the package provides for
+                // itself, which take precedence over the built-in defaults.
+                let package_root = Label::from_str(&render_crate_bazel_label(
+                    &config.rendering.crate_label_template,
+                    &config.rendering.repository_name,
+                    &pkg.name,
+                    &pkg.version.to_string(),
+                    &pkg.name,
+                ))
+                .unwrap_or(Label::Relative {
+                    target: pkg.name.clone(),
+                });
+                crate_extra.apply_defaults_from_package_metadata(
+                    AnnotationsProvidedByPackage::parse(pkg, &package_root),
+                );
+                crate_extra = crate_extra
+                    + default_annotations
+                        .iter()
+                        .filter(|(id, _)| id.matches(pkg))
+                        .map(|(_, extra)| extra)
+                        .cloned()
+                        .sum();
 
                 if crate_extra == CrateAnnotations::default() {
                     None
@@ -557,7 +594,7 @@ mod test {
     use semver::Version;
     use serde_json::json;
 
-    use crate::config::CrateNameAndVersionReq;
+    use crate::config::{CrateNameAndVersionReq, DefaultAnnotations, DefaultAnnotationsVersion};
     use crate::metadata::CargoTreeEntry;
     use crate::select::Select;
     use crate::test::*;
@@ -722,6 +759,42 @@ mod test {
         assert!(result_str.contains("mock-crate"));
     }
 
+    #[test]
+    fn default_annotations_beneath_user_annotations() {
+        let mut config = Config {
+            default_annotations: Some(DefaultAnnotations {
+                version: DefaultAnnotationsVersion::V1,
+                disabled: BTreeSet::from(["libz-sys".to_owned()]),
+            }),
+            ..Config::default()
+        };
+        config.annotations.insert(
+            CrateNameAndVersionReq::new("openssl-sys".to_owned(), "*".parse().unwrap()),
+            CrateAnnotations {
+                build_script_use_default_shell_env: Some(0),
+                data_glob: Some(BTreeSet::from(["*.txt".to_owned()])),
+                ..CrateAnnotations::default()
+            },
+        );
+
+        let annotations = Annotations::new(
+            test::metadata::build_scripts(),
+            &None,
+            test::lockfile::build_scripts(),
+            config,
+            Utf8Path::new("/tmp/bazelworkspace"),
+        )
+        .unwrap();
+
+        let openssl_sys = CrateId::new("openssl-sys".to_owned(), Version::new(0, 9, 87));
+        let extras = &annotations.pairred_extras[&openssl_sys].crate_extra;
+        assert_eq!(extras.build_script_use_default_shell_env, Some(0));
+        assert_eq!(extras.data_glob, Some(BTreeSet::from(["*.txt".to_owned()])));
+
+        let libz_sys = CrateId::new("libz-sys".to_owned(), Version::new(1, 1, 8));
+        assert!(!annotations.pairred_extras.contains_key(&libz_sys));
+    }
+
     #[test]
     fn defaults_from_package_metadata() {
         let crate_id = CrateId::new(
@@ -802,6 +875,7 @@ mod test {
             CargoTreeEntry {
                 features: BTreeSet::from(["default".to_owned()]),
                 deps: BTreeSet::new(),
+                enabled_by: BTreeMap::new(),
             },
             None,
         );
@@ -809,6 +883,7 @@ mod test {
             CargoTreeEntry {
                 features: BTreeSet::from(["std".to_owned()]),
                 deps: BTreeSet::from([CrateId::new("libc".to_owned(), Version::new(1, 2, 3))]),
+                enabled_by: BTreeMap::new(),
             },
             Some("x86_64-unknown-linux-gnu".to_owned()),
         );
@@ -0,0 +1,306 @@
+//! Checks of the minimum supported Rust versions (`rust-version`) of resolved crates.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::Metadata as CargoMetadata;
+use semver::Version;
+
+use crate::context;
+use crate::lockfile::Digest;
+
+/// A resolved crate whose `rust-version` is newer than the rustc in use.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct IncompatibleRustVersion {
+    /// The name of the crate.
+    pub(crate) name: String,
+
+    /// The version of the crate.
+    pub(crate) version: Version,
+
+    /// The `rust-version` the crate declares.
+    pub(crate) rust_version: Version,
+
+    /// The version of rustc the crate was checked against.
+    pub(crate) rustc_version: Version,
+
+    /// The `{name} {version}` of every package which directly depends on the crate.
+    pub(crate) dependents: BTreeSet<String>,
+}
+
+impl fmt::Display for IncompatibleRustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{} {}` requires rustc {} or newer but rustc {} is in use.",
+            self.name, self.version, self.rust_version, self.rustc_version
+        )?;
+        if !self.dependents.is_empty() {
+            write!(
+                f,
+                " It is required by: {}",
+                self.dependents
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the version from the output of `rustc --version` (e.g. `rustc 1.57.0 (f1edd0429 2021-11-29)`).
+///
+/// Pre-release identifiers are dropped so nightly and beta toolchains are treated like the release
+/// they precede, matching how Cargo compares them against `rust-version`.
+pub(crate) fn parse_rustc_version(version_output: &str) -> Result<Version> {
+    let version_str = match version_output.split(' ').nth(1) {
+        Some(version_str) => version_str,
+        None => bail!("Couldn't parse rustc version from `{}`", version_output),
+    };
+    let version = Version::parse(version_str)
+        .with_context(|| format!("Failed to parse rustc version `{}`", version_str))?;
+    Ok(Version::new(version.major, version.minor, version.patch))
+}
+
+/// Find all resolved packages which declare a `rust-version` newer than `rustc_version`.
+pub(crate) fn find_incompatible_rust_versions(
+    metadata: &CargoMetadata,
+    rustc_version: &Version,
+) -> Vec<IncompatibleRustVersion> {
+    let packages: BTreeMap<_, _> = metadata.packages.iter().map(|pkg| (&pkg.id, pkg)).collect();
+
+    let Some(resolve) = &metadata.resolve else {
+        return Vec::new();
+    };
+
+    let mut dependents: BTreeMap<_, BTreeSet<String>> = BTreeMap::new();
+    for node in &resolve.nodes {
+        let Some(dependent) = packages.get(&node.id) else {
+            continue;
+        };
+        for dep in &node.deps {
+            dependents
+                .entry(&dep.pkg)
+                .or_default()
+                .insert(format!("{} {}", dependent.name, dependent.version));
+        }
+    }
+
+    resolve
+        .nodes
+        .iter()
+        .filter_map(|node| packages.get(&node.id))
+        .filter_map(|pkg| {
+            let rust_version = pkg.rust_version.as_ref()?;
+            if rust_version <= rustc_version {
+                return None;
+            }
+            Some(IncompatibleRustVersion {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                rust_version: rust_version.clone(),
+                rustc_version: rustc_version.clone(),
+                dependents: dependents.remove(&pkg.id).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Find all crates of a rendering context which declare a `rust-version` newer than
+/// `rustc_version`. This allows checking a lockfile without resolving the workspace again.
+pub(crate) fn find_incompatible_rust_versions_in_context(
+    context: &context::Context,
+    rustc_version: &Version,
+) -> Vec<IncompatibleRustVersion> {
+    let mut dependents: BTreeMap<_, BTreeSet<String>> = BTreeMap::new();
+    for (id, crate_context) in &context.crates {
+        let common_attrs = &crate_context.common_attrs;
+        let mut deps = [
+            &common_attrs.deps,
+            &common_attrs.deps_dev,
+            &common_attrs.proc_macro_deps,
+            &common_attrs.proc_macro_deps_dev,
+        ]
+        .into_iter()
+        .flat_map(|deps| deps.values())
+        .collect::<Vec<_>>();
+        if let Some(build_script_attrs) = &crate_context.build_script_attrs {
+            deps.extend(build_script_attrs.deps.values());
+            deps.extend(build_script_attrs.proc_macro_deps.values());
+        }
+        for dep in deps {
+            dependents
+                .entry(dep.id)
+                .or_default()
+                .insert(format!("{} {}", id.name, id.version));
+        }
+    }
+
+    context
+        .crates
+        .iter()
+        .filter_map(|(id, crate_context)| {
+            let rust_version = crate_context.rust_version.as_ref()?;
+            if rust_version <= rustc_version {
+                return None;
+            }
+            Some(IncompatibleRustVersion {
+                name: id.name.clone(),
+                version: id.version.clone(),
+                rust_version: rust_version.clone(),
+                rustc_version: rustc_version.clone(),
+                dependents: dependents.remove(id).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Compare the `rust-version` of every resolved crate against the version of `rustc_bin`.
+///
+/// Returns a warning for every incompatible crate, or an error listing all of them if `enforce`
+/// is set.
+pub(crate) fn check_rust_versions(
+    metadata: &CargoMetadata,
+    rustc_bin: &Path,
+    enforce: bool,
+) -> Result<Vec<String>> {
+    let rustc_version = parse_rustc_version(&Digest::bin_version(rustc_bin)?)?;
+    let incompatible = find_incompatible_rust_versions(metadata, &rustc_version);
+    report_incompatible_rust_versions(&incompatible, &rustc_version, enforce)
+}
+
+/// Like [check_rust_versions], but for the crates of a rendering context read from a lockfile.
+pub(crate) fn check_context_rust_versions(
+    context: &context::Context,
+    rustc_bin: &Path,
+    enforce: bool,
+) -> Result<Vec<String>> {
+    let rustc_version = parse_rustc_version(&Digest::bin_version(rustc_bin)?)?;
+    let incompatible = find_incompatible_rust_versions_in_context(context, &rustc_version);
+    report_incompatible_rust_versions(&incompatible, &rustc_version, enforce)
+}
+
+fn report_incompatible_rust_versions(
+    incompatible: &[IncompatibleRustVersion],
+    rustc_version: &Version,
+    enforce: bool,
+) -> Result<Vec<String>> {
+    if enforce && !incompatible.is_empty() {
+        bail!(
+            "The following crates are incompatible with rustc {}:\n{}",
+            rustc_version,
+            incompatible
+                .iter()
+                .map(|crate_| format!("  {crate_}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    Ok(incompatible.iter().map(ToString::to_string).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use camino::Utf8Path;
+
+    use crate::config::Config;
+    use crate::metadata::Annotations;
+    use crate::test;
+
+    #[test]
+    fn parse_rustc_versions() {
+        assert_eq!(
+            parse_rustc_version("rustc 1.57.0 (f1edd0429 2021-11-29)").unwrap(),
+            Version::new(1, 57, 0)
+        );
+        assert_eq!(
+            parse_rustc_version("rustc 1.81.0-nightly (8337ba918 2024-06-12)").unwrap(),
+            Version::new(1, 81, 0)
+        );
+        assert!(parse_rustc_version("rustc").is_err());
+    }
+
+    #[test]
+    fn incompatible_rust_versions() {
+        let metadata = test::metadata::workspace_build_scripts_deps();
+
+        let incompatible = find_incompatible_rust_versions(&metadata, &Version::new(1, 80, 0));
+        assert_eq!(
+            incompatible,
+            vec![IncompatibleRustVersion {
+                name: "home".to_owned(),
+                version: Version::new(0, 5, 11),
+                rust_version: Version::new(1, 81, 0),
+                rustc_version: Version::new(1, 80, 0),
+                dependents: BTreeSet::from(["which 4.4.2".to_owned()]),
+            }]
+        );
+        assert_eq!(
+            incompatible[0].to_string(),
+            "`home 0.5.11` requires rustc 1.81.0 or newer but rustc 1.80.0 is in use. It is required by: which 4.4.2"
+        );
+
+        let incompatible = find_incompatible_rust_versions(&metadata, &Version::new(1, 69, 0));
+        let tokio = incompatible
+            .iter()
+            .find(|crate_| crate_.name == "tokio")
+            .unwrap();
+        assert!(tokio.dependents.contains("hyper 0.14.32"));
+        assert!(tokio.dependents.contains("tonic 0.4.3"));
+
+        assert!(find_incompatible_rust_versions(&metadata, &Version::new(1, 81, 0)).is_empty());
+    }
+
+    #[test]
+    fn incompatible_rust_versions_in_context() {
+        let metadata = test::metadata::workspace_build_scripts_deps();
+        let annotations = Annotations::new(
+            metadata.clone(),
+            &None,
+            test::lockfile::workspace_build_scripts_deps(),
+            Config {
+                generate_build_scripts: true,
+                ..Config::default()
+            },
+            Utf8Path::new("/tmp/bazelworkspace"),
+        )
+        .unwrap();
+        let context = context::Context::new(annotations, false).unwrap();
+
+        // The lockfile reports the same crates as the metadata it was generated from.
+        let crates = |incompatible: Vec<IncompatibleRustVersion>| {
+            incompatible
+                .into_iter()
+                .map(|crate_| (crate_.name, crate_.version, crate_.rust_version))
+                .collect::<Vec<_>>()
+        };
+        for rustc_version in [
+            Version::new(1, 69, 0),
+            Version::new(1, 80, 0),
+            Version::new(1, 81, 0),
+        ] {
+            assert_eq!(
+                crates(find_incompatible_rust_versions_in_context(
+                    &context,
+                    &rustc_version
+                )),
+                crates(find_incompatible_rust_versions(&metadata, &rustc_version)),
+                "{rustc_version}"
+            );
+        }
+
+        let incompatible =
+            find_incompatible_rust_versions_in_context(&context, &Version::new(1, 80, 0));
+        assert_eq!(
+            incompatible[0].to_string(),
+            "`home 0.5.11` requires rustc 1.81.0 or newer but rustc 1.80.0 is in use. It is required by: which 4.4.2"
+        );
+    }
+}
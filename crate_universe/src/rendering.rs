@@ -11,7 +11,7 @@ use std::sync::Arc;
 use anyhow::{bail, Context as AnyhowContext, Result};
 use itertools::Itertools;
 
-use crate::config::{AliasRule, RenderConfig, VendorMode};
+use crate::config::{AliasRule, CrateId, RenderConfig, VendorMode};
 use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
 use crate::context::{Context, TargetAttributes};
 use crate::metadata::SourceAnnotation;
@@ -19,9 +19,10 @@ use crate::rendering::template_engine::TemplateEngine;
 use crate::select::Select;
 use crate::splicing::default_splicing_package_crate_id;
 use crate::utils::starlark::{
-    self, Alias, CargoBuildScript, CargoTomlEnvVars, CommonAttrs, Data, ExportsFiles, Filegroup,
-    Glob, Label, Load, Package, RustBinary, RustLibrary, RustProcMacro, SelectDict, SelectList,
-    SelectScalar, SelectSet, Starlark, TargetCompatibleWith,
+    self, Alias, CargoBuildScript, CargoTomlEnvVars, CommonAttrs, CratesDocIndex, Data,
+    ExportsFiles, Filegroup, Glob, Label, Load, Package, RustBinary, RustDoc, RustLibrary,
+    RustProcMacro, SelectCompilationMode, SelectDict, SelectList, SelectScalar, SelectSet,
+    Starlark, TargetCompatibleWith,
 };
 use crate::utils::target_triple::TargetTriple;
 use crate::utils::{self, sanitize_repository_name};
@@ -30,6 +31,17 @@ use crate::utils::{self, sanitize_repository_name};
 // to platform labels like "@rules_rust//rust/platform:x86_64-unknown-linux-gnu".
 pub(crate) type Platforms = BTreeMap<String, BTreeSet<String>>;
 
+/// The `visibility` of the aliases of each direct dependency of the workspace members.
+/// Empty sets leave an alias public.
+#[derive(Debug, Default)]
+struct DependencyVisibility {
+    /// The visibility of aliases naming a crate by its package name.
+    by_crate: BTreeMap<CrateId, BTreeSet<String>>,
+
+    /// The visibility of aliases naming a crate by the name it's depended on as.
+    by_alias: BTreeMap<(CrateId, Option<String>), BTreeSet<String>>,
+}
+
 pub(crate) struct Renderer {
     config: Arc<RenderConfig>,
     supported_platform_triples: Arc<BTreeSet<TargetTriple>>,
@@ -125,10 +137,38 @@ impl Renderer {
                 .context("Failed to resolve string to module file label")?;
 
         let mut map = BTreeMap::new();
-        map.insert(
-            Renderer::label_to_path(&module_label),
-            engine.render_module_bzl(context, platforms, generator)?,
-        );
+        match self.config.crates_module_sharding {
+            Some(sharding) if !context.workspace_members.is_empty() => {
+                let mut shards: BTreeMap<String, BTreeMap<&CrateId, &String>> = BTreeMap::new();
+                let mut package_shards = BTreeMap::new();
+                for (id, package) in context.workspace_members.iter() {
+                    let file = format!("shards/{}.bzl", sharding.shard_name(package));
+                    shards.entry(file.clone()).or_default().insert(id, package);
+                    package_shards.insert(package, file);
+                }
+
+                // Each shard is an entry point for the BUILD files of its packages and is
+                // not loaded by the crates module itself.
+                for (file, content) in
+                    engine.render_module_shard_bzls(context, &shards, platforms)?
+                {
+                    let label = render_module_label(&self.config.crates_module_template, &file)
+                        .context("Failed to resolve string to module file label")?;
+                    map.insert(Renderer::label_to_path(&label), content);
+                }
+
+                map.insert(
+                    Renderer::label_to_path(&module_label),
+                    engine.render_sharded_module_bzl(context, &package_shards, generator)?,
+                );
+            }
+            _ => {
+                map.insert(
+                    Renderer::label_to_path(&module_label),
+                    engine.render_module_bzl(context, platforms, generator)?,
+                );
+            }
+        }
         map.insert(
             Renderer::label_to_path(&module_build_label),
             self.render_module_build_file(engine, context)?,
@@ -173,6 +213,16 @@ impl Renderer {
             starlark.push(Starlark::Load(Load { bzl, items }))
         }
 
+        let docs_index = self.make_crates_doc_index(context);
+        if docs_index.is_some() {
+            let disable_visibility = "# buildifier: disable=bzl-visibility".to_owned();
+            starlark.push(Starlark::Verbatim(disable_visibility));
+            starlark.push(Starlark::Load(Load {
+                bzl: "@rules_rust//crate_universe/private:crates_doc_index.bzl".to_owned(),
+                items: BTreeSet::from(["crates_doc_index".to_owned()]),
+            }));
+        }
+
         // Package visibility, exported bzl files.
         let package = Package::default_visibility_public(BTreeSet::new());
         starlark.push(Starlark::Package(package));
@@ -190,7 +240,7 @@ impl Renderer {
         }
         starlark.push(Starlark::ExportsFiles(exports_files));
 
-        let filegroup = Filegroup {
+        let mut filegroup = Filegroup {
             name: "srcs".to_owned(),
             srcs: Glob {
                 allow_empty: true,
@@ -198,9 +248,13 @@ impl Renderer {
                 exclude: BTreeSet::new(),
             },
         };
+        if self.config.crates_module_sharding.is_some() {
+            filegroup.srcs.include.insert("shards/**/*.bzl".to_owned());
+        }
         starlark.push(Starlark::Filegroup(filegroup));
 
         // An `alias` for each direct dependency of a workspace member crate.
+        let visibility = self.workspace_member_dep_visibility(context);
         let mut dependencies = Vec::new();
         for dep in context.workspace_member_deps() {
             let krate = &context.crates[&dep.id];
@@ -208,6 +262,11 @@ impl Renderer {
                 .alias_rule
                 .as_ref()
                 .unwrap_or(&self.config.default_alias_rule);
+            let crate_visibility = visibility
+                .by_crate
+                .get(&dep.id)
+                .cloned()
+                .unwrap_or_default();
 
             if let Some(library_target_name) = &krate.library_target_name {
                 // Avoid adding the <crate_name>-<version> alias if there are
@@ -233,9 +292,16 @@ impl Renderer {
                             library_target_name,
                         ),
                         tags: BTreeSet::from(["manual".to_owned()]),
+                        visibility: crate_visibility.clone(),
                     });
                 }
 
+                let dep_visibility = visibility
+                    .by_alias
+                    .get(&(dep.id.clone(), dep.alias.clone()))
+                    .cloned()
+                    .unwrap_or_default();
+
                 let shorthand = if let Some(rename) = dep.alias.as_ref() {
                     // when the alias is the same as the crate name, don't create the alias
                     if krate.name != *rename {
@@ -248,6 +314,7 @@ impl Renderer {
                                 library_target_name,
                             ),
                             tags: BTreeSet::from(["manual".to_owned()]),
+                            visibility: dep_visibility.clone(),
                         });
                     }
                     rename
@@ -268,6 +335,7 @@ impl Renderer {
                             library_target_name,
                         ),
                         tags: BTreeSet::from(["manual".to_owned()]),
+                        visibility: dep_visibility,
                     });
                 }
             }
@@ -278,6 +346,7 @@ impl Renderer {
                     name: alias.clone(),
                     actual: self.crate_label(&krate.name, &krate.version.to_string(), target),
                     tags: BTreeSet::from(["manual".to_owned()]),
+                    visibility: crate_visibility.clone(),
                 });
             }
         }
@@ -285,6 +354,7 @@ impl Renderer {
         let duplicates: Vec<_> = dependencies
             .iter()
             .map(|alias| &alias.name)
+            .chain(docs_index.as_ref().map(|index| &index.name))
             .duplicates()
             .sorted()
             .collect();
@@ -321,6 +391,7 @@ impl Renderer {
                             &format!("{}__bin", bin.crate_name),
                         ),
                         tags: BTreeSet::from(["manual".to_owned()]),
+                        visibility: BTreeSet::new(),
                     });
                 }
             }
@@ -331,6 +402,12 @@ impl Renderer {
             starlark.extend(binaries.into_iter().map(Starlark::Alias));
         }
 
+        if let Some(docs_index) = docs_index {
+            let comment = "# Documentation".to_owned();
+            starlark.push(Starlark::Verbatim(comment));
+            starlark.push(Starlark::CratesDocIndex(docs_index));
+        }
+
         let starlark = starlark::serialize(&starlark)?;
         Ok(starlark)
     }
@@ -477,6 +554,7 @@ impl Renderer {
                     name: rule.crate_name().to_owned(),
                     actual: override_target.clone(),
                     tags: BTreeSet::from(["manual".to_owned()]),
+                    visibility: BTreeSet::new(),
                 }));
             } else {
                 match rule {
@@ -490,6 +568,7 @@ impl Renderer {
                             name: target.crate_name.clone(),
                             actual: Label::from_str("_bs").unwrap(),
                             tags: BTreeSet::from(["manual".to_owned()]),
+                            visibility: BTreeSet::new(),
                         }));
                     }
                     Rule::ProcMacro(target) => {
@@ -512,6 +591,13 @@ impl Renderer {
             }
         }
 
+        if self.generates_rust_doc(&krate) {
+            if let Some(target) = rust_doc_target(&krate) {
+                load("@rules_rust//rust:defs.bzl", "rust_doc");
+                starlark.push(Starlark::RustDoc(self.make_rust_doc(target)));
+            }
+        }
+
         if let Some(additive_build_file_content) = &krate.additive_build_file_content {
             let comment = "# Additive BUILD file content".to_owned();
             starlark.push(Starlark::Verbatim(comment));
@@ -720,6 +806,83 @@ impl Renderer {
         })
     }
 
+    fn make_rust_doc(&self, target: &TargetAttributes) -> RustDoc {
+        // `rust_doc` already enables the features the crate is compiled with.
+        RustDoc {
+            name: rust_doc_name(target),
+            krate: Label::Relative {
+                target: target.crate_name.clone(),
+            },
+            rustdoc_flags: BTreeSet::from(["--cap-lints=allow".to_owned()]),
+            tags: BTreeSet::from(["manual".to_owned()]),
+        }
+    }
+
+    /// Whether a `rust_doc` target is generated for the library of a crate.
+    fn generates_rust_doc(&self, krate: &CrateContext) -> bool {
+        krate.gen_rust_doc.unwrap_or(self.config.generate_rust_docs)
+    }
+
+    /// The target combining the `rust_doc` targets of the third-party crates into trees of
+    /// documentation, in which each crate links to the documentation of its dependencies.
+    /// Crates are identified as `<crate name>-<version>`.
+    fn make_crates_doc_index(&self, context: &Context) -> Option<CratesDocIndex> {
+        let default_splicing_package_id = default_splicing_package_crate_id();
+        let mut documented: BTreeMap<&CrateId, (String, &CrateContext, &TargetAttributes)> =
+            BTreeMap::new();
+        for (id, krate) in &context.crates {
+            if *id == default_splicing_package_id
+                || context.workspace_members.contains_key(id)
+                || !self.generates_rust_doc(krate)
+            {
+                continue;
+            }
+            let Some(target) = rust_doc_target(krate) else {
+                continue;
+            };
+            let key = format!(
+                "{}-{}",
+                utils::sanitize_module_name(&target.crate_name),
+                id.version
+            );
+            documented.insert(id, (key, krate, target));
+        }
+        if documented.is_empty() {
+            return None;
+        }
+
+        let mut docs = BTreeMap::new();
+        let mut links = BTreeMap::new();
+        for (key, krate, target) in documented.values() {
+            docs.insert(
+                self.crate_label(
+                    &krate.name,
+                    &krate.version.to_string(),
+                    &rust_doc_name(target),
+                ),
+                krate.version.to_string(),
+            );
+
+            // Dependencies on any platform, as only those which are built end up being linked to.
+            let dependencies: BTreeSet<String> = Iterator::chain(
+                krate.common_attrs.deps.values().into_iter(),
+                krate.common_attrs.proc_macro_deps.values(),
+            )
+            .filter_map(|dep| documented.get(&dep.id).map(|(key, ..)| key.clone()))
+            .collect();
+            if !dependencies.is_empty() {
+                links.insert(key.clone(), dependencies);
+            }
+        }
+
+        Some(CratesDocIndex {
+            name: "docs".to_owned(),
+            docs,
+            links,
+            tags: BTreeSet::from(["manual".to_owned()]),
+        })
+    }
+
     fn make_rust_binary(
         &self,
         platforms: &Platforms,
@@ -778,16 +941,19 @@ impl Renderer {
             linker_script: krate.common_attrs.linker_script.clone(),
             rustc_env: SelectDict::new(krate.common_attrs.rustc_env.clone(), platforms),
             rustc_env_files: SelectSet::new(krate.common_attrs.rustc_env_files.clone(), platforms),
-            rustc_flags: SelectList::new(
-                // In most cases, warnings in 3rd party crates are not
-                // interesting as they're out of the control of consumers. The
-                // flag here silences warnings. For more details see:
-                // https://doc.rust-lang.org/rustc/lints/levels.html
-                Select::merge(
-                    Select::from_value(Vec::from(["--cap-lints=allow".to_owned()])),
-                    krate.common_attrs.rustc_flags.clone(),
+            rustc_flags: SelectCompilationMode::new(
+                krate.common_attrs.profile_rustc_flags.clone(),
+                SelectList::new(
+                    // In most cases, warnings in 3rd party crates are not
+                    // interesting as they're out of the control of consumers. The
+                    // flag here silences warnings. For more details see:
+                    // https://doc.rust-lang.org/rustc/lints/levels.html
+                    Select::merge(
+                        Select::from_value(Vec::from(["--cap-lints=allow".to_owned()])),
+                        krate.common_attrs.rustc_flags.clone(),
+                    ),
+                    platforms,
                 ),
-                platforms,
             ),
             srcs: target.srcs.clone(),
             tags: {
@@ -894,6 +1060,50 @@ impl Renderer {
         }
     }
 
+    /// Restrict the aliases of each direct dependency to the packages of the workspace
+    /// members which declare it when [RenderConfig::strict_visibility] is set. Dependencies
+    /// declared outside of a `Cargo.toml` (e.g. with `crate.spec`) remain public.
+    fn workspace_member_dep_visibility(&self, context: &Context) -> DependencyVisibility {
+        if !self.config.strict_visibility {
+            return DependencyVisibility::default();
+        }
+
+        let splicing_package = default_splicing_package_crate_id();
+        let package_groups = |members: &BTreeSet<&CrateId>| -> BTreeSet<String> {
+            if members.contains(&splicing_package) {
+                return BTreeSet::new();
+            }
+            members
+                .iter()
+                .map(|id| format!("@//{}:__pkg__", context.workspace_members[*id]))
+                .collect()
+        };
+
+        let mut by_crate: BTreeMap<CrateId, BTreeSet<&CrateId>> = BTreeMap::new();
+        let mut by_alias: BTreeMap<(CrateId, Option<String>), BTreeSet<&CrateId>> = BTreeMap::new();
+        for (dep, members) in context.workspace_member_dependents() {
+            by_crate
+                .entry(dep.id.clone())
+                .or_default()
+                .extend(members.iter().copied());
+            by_alias
+                .entry((dep.id, dep.alias))
+                .or_default()
+                .extend(members);
+        }
+
+        DependencyVisibility {
+            by_crate: by_crate
+                .into_iter()
+                .map(|(id, members)| (id, package_groups(&members)))
+                .collect(),
+            by_alias: by_alias
+                .into_iter()
+                .map(|(key, members)| (key, package_groups(&members)))
+                .collect(),
+        }
+    }
+
     fn crate_label(&self, name: &str, version: &str, target: &str) -> Label {
         Label::from_str(&sanitize_repository_name(&render_crate_bazel_label(
             &self.config.crate_label_template,
@@ -987,21 +1197,41 @@ fn render_build_file_template(template: &str, name: &str, version: &str) -> Resu
     )
 }
 
+/// Glob patterns excluded from every rendered `data` and `compile_data` glob.
+pub(crate) const COMMON_GLOB_EXCLUDES: &[&str] = &[
+    "**/* *",
+    "BUILD.bazel",
+    "BUILD",
+    "WORKSPACE.bazel",
+    "WORKSPACE",
+    ".tmp_git_root/**/*",
+];
+
+/// The library or procedural macro target of a crate to generate documentation for, unless it's
+/// overridden by another target.
+fn rust_doc_target(krate: &CrateContext) -> Option<&TargetAttributes> {
+    krate.targets.iter().find_map(|rule| match rule {
+        Rule::Library(target) | Rule::ProcMacro(target)
+            if !krate
+                .override_targets
+                .contains_key(rule.override_target_key()) =>
+        {
+            Some(target)
+        }
+        _ => None,
+    })
+}
+
+fn rust_doc_name(target: &TargetAttributes) -> String {
+    format!("{}_doc", target.crate_name)
+}
+
 fn make_data_with_exclude(
     platforms: &Platforms,
     include: BTreeSet<String>,
     exclude: BTreeSet<String>,
     select: Select<BTreeSet<Label>>,
 ) -> Data {
-    const COMMON_GLOB_EXCLUDES: &[&str] = &[
-        "**/* *",
-        "BUILD.bazel",
-        "BUILD",
-        "WORKSPACE.bazel",
-        "WORKSPACE",
-        ".tmp_git_root/**/*",
-    ];
-
     Data {
         glob: Glob {
             allow_empty: true,
@@ -1032,7 +1262,9 @@ mod test {
     use camino::Utf8Path;
     use indoc::indoc;
 
-    use crate::config::{Config, CrateId};
+    use crate::config::{
+        Config, CrateId, CratesModuleSharding, DefaultAnnotations, DefaultAnnotationsVersion,
+    };
     use crate::context::{BuildScriptAttributes, CommonAttributes};
     use crate::metadata::Annotations;
     use crate::test;
@@ -1084,6 +1316,83 @@ mod test {
         ]))
     }
 
+    /// A context with a `my_dependency` crate used by the workspace members `app`, which depends
+    /// on it by its name, and `tools/lib`, which renames it.
+    fn mock_context_with_workspace_members() -> Context {
+        let mut context = Context::default();
+        let dependency_id = CrateId::new("my_dependency".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            dependency_id.clone(),
+            CrateContext {
+                name: dependency_id.name.clone(),
+                version: dependency_id.version.clone(),
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                library_target_name: Some("my_dependency".into()),
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                gen_rust_doc: None,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
+            },
+        );
+
+        for (name, package, alias) in [
+            ("app", "app", None),
+            ("lib", "tools/lib", Some("my_dependency_other")),
+        ] {
+            let member_id = CrateId::new(name.to_owned(), VERSION_ZERO_ONE_ZERO);
+            context
+                .workspace_members
+                .insert(member_id.clone(), package.to_owned());
+            context.crates.insert(
+                member_id.clone(),
+                CrateContext {
+                    name: member_id.name,
+                    version: member_id.version,
+                    package_url: None,
+                    repository: None,
+                    targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                    library_target_name: None,
+                    common_attrs: CommonAttributes {
+                        deps: Select::from_value(BTreeSet::from([CrateDependency {
+                            id: dependency_id.clone(),
+                            target: "my_dependency".into(),
+                            alias: alias.map(str::to_owned),
+                            local_path: None,
+                        }])),
+                        ..Default::default()
+                    },
+                    build_script_attrs: None,
+                    license: None,
+                    license_ids: BTreeSet::default(),
+                    license_file: None,
+                    additive_build_file_content: None,
+                    disable_pipelining: false,
+                    gen_rust_doc: None,
+                    extra_aliased_targets: BTreeMap::default(),
+                    alias_rule: None,
+                    override_targets: BTreeMap::default(),
+                    vendor_keep_glob: BTreeSet::default(),
+                    vendor_exclude_glob: BTreeSet::default(),
+                    rust_version: None,
+                },
+            );
+        }
+
+        context
+    }
+
     #[test]
     fn render_rust_library() {
         let mut context = Context::default();
@@ -1104,9 +1413,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1142,9 +1455,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: true,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1184,9 +1501,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1258,9 +1579,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1321,9 +1646,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1359,9 +1688,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1399,9 +1732,13 @@ mod test {
                     "# Hello World from additive section!".to_owned(),
                 ),
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1440,6 +1777,50 @@ mod test {
         assert!(build_file_content.contains(r#"name = "names-0.13.0__names","#));
     }
 
+    #[test]
+    fn render_default_annotations() {
+        let render = |default_annotations| {
+            let config = Config {
+                generate_build_scripts: true,
+                default_annotations,
+                ..Config::default()
+            };
+            let annotations = Annotations::new(
+                test::metadata::build_scripts(),
+                &None,
+                test::lockfile::build_scripts(),
+                config,
+                Utf8Path::new("/tmp/bazelworkspace"),
+            )
+            .unwrap();
+            let context = Context::new(annotations, false).unwrap();
+
+            let renderer =
+                Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+            renderer.render(&context, None).unwrap()
+        };
+
+        let without_defaults = render(None);
+        let with_defaults = render(Some(DefaultAnnotations {
+            version: DefaultAnnotationsVersion::V1,
+            disabled: BTreeSet::new(),
+        }));
+
+        let libz_sys = PathBuf::from("BUILD.libz-sys-1.1.8.bazel");
+        assert_ne!(without_defaults[&libz_sys], with_defaults[&libz_sys]);
+        assert!(!without_defaults[&libz_sys].contains("LIBZ_SYS_STATIC"));
+        assert!(with_defaults[&libz_sys].contains(r#""LIBZ_SYS_STATIC": "1","#));
+
+        let openssl_sys = PathBuf::from("BUILD.openssl-sys-0.9.87.bazel");
+        assert_ne!(without_defaults[&openssl_sys], with_defaults[&openssl_sys]);
+        assert!(!without_defaults[&openssl_sys].contains("use_default_shell_env ="));
+        assert!(with_defaults[&openssl_sys].contains("use_default_shell_env = 1"));
+
+        // Crates without default annotations render the same either way.
+        let openssl = PathBuf::from("BUILD.openssl-0.10.52.bazel");
+        assert_eq!(without_defaults[&openssl], with_defaults[&openssl]);
+    }
+
     #[test]
     fn render_crate_repositories() {
         let mut context = Context::default();
@@ -1460,9 +1841,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1474,6 +1859,61 @@ mod test {
         assert!(defs_module.contains("def crate_repositories():"));
     }
 
+    #[test]
+    fn render_authenticated_crate_repositories() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: Some(SourceAnnotation::Http {
+                    url: "https://private.example.com/api/v1/crates/mock_crate/0.1.0/download"
+                        .to_owned(),
+                    sha256: None,
+                    patch_args: None,
+                    patch_tool: None,
+                    patches: None,
+                    auth_patterns: Some(BTreeMap::from([(
+                        "private.example.com".to_owned(),
+                        "<password>".to_owned(),
+                    )])),
+                }),
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                library_target_name: None,
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                gen_rust_doc: None,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+
+        assert!(defs_module.contains(
+            r#"
+        auth_patterns = {
+            "private.example.com": "<password>",
+        },
+"#
+        ));
+    }
+
     #[test]
     fn remote_remote_vendor_mode() {
         let mut context = Context::default();
@@ -1494,9 +1934,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1534,9 +1978,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1586,9 +2034,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1708,9 +2160,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1755,9 +2211,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1804,6 +2264,7 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
                 library_target_name: None,
@@ -1813,6 +2274,9 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1870,6 +2334,7 @@ mod test {
                 license_file: Some("LICENSE.txt".to_owned()),
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
                 library_target_name: None,
@@ -1879,6 +2344,9 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -1949,9 +2417,13 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -2016,6 +2488,7 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
                 library_target_name: Some("library_name".into()),
@@ -2035,6 +2508,9 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -2120,6 +2596,7 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
                 library_target_name: Some("my_dependency".into()),
@@ -2129,6 +2606,9 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -2142,6 +2622,7 @@ mod test {
                 license_file: None,
                 additive_build_file_content: None,
                 disable_pipelining: false,
+                gen_rust_doc: None,
                 extra_aliased_targets: BTreeMap::default(),
                 targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
                 library_target_name: Some("library_name".into()),
@@ -2168,6 +2649,9 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
             },
         );
 
@@ -2242,4 +2726,198 @@ mod test {
             .replace(' ', "")
             .contains(&expected.replace(' ', "")));
     }
+
+    #[test]
+    fn render_strict_workspace_member_dep_visibility() {
+        let context = mock_context_with_workspace_members();
+
+        let mut render_config = mock_render_config(None);
+        Arc::get_mut(&mut render_config).unwrap().strict_visibility = true;
+        let renderer = Renderer::new(render_config, mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
+
+        println!("{build_file_content}");
+        let expected = indoc! {r#"
+            # Workspace Member Dependencies
+            alias(
+                name = "my_dependency-0.1.0",
+                actual = "@test_rendering__my_dependency-0.1.0//:my_dependency",
+                tags = ["manual"],
+                visibility = [
+                    "@//app:__pkg__",
+                    "@//tools/lib:__pkg__",
+                ],
+            )
+
+            alias(
+                name = "my_dependency",
+                actual = "@test_rendering__my_dependency-0.1.0//:my_dependency",
+                tags = ["manual"],
+                visibility = ["@//app:__pkg__"],
+            )
+
+            alias(
+                name = "my_dependency_other-0.1.0",
+                actual = "@test_rendering__my_dependency-0.1.0//:my_dependency",
+                tags = ["manual"],
+                visibility = ["@//tools/lib:__pkg__"],
+            )
+
+            alias(
+                name = "my_dependency_other",
+                actual = "@test_rendering__my_dependency-0.1.0//:my_dependency",
+                tags = ["manual"],
+                visibility = ["@//tools/lib:__pkg__"],
+            )
+        "#};
+        assert!(build_file_content
+            .replace(' ', "")
+            .contains(&expected.replace(' ', "")));
+    }
+
+    #[test]
+    fn render_sharded_crates_module() {
+        let context = mock_context_with_workspace_members();
+
+        let mut render_config = mock_render_config(None);
+        Arc::get_mut(&mut render_config)
+            .unwrap()
+            .crates_module_sharding = Some(CratesModuleSharding::Directory);
+        let renderer = Renderer::new(render_config, mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        // The crates module doesn't load the shards but points BUILD files at them.
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+        assert!(defs_module
+            .lines()
+            .filter(|line| line.starts_with("load("))
+            .all(|line| line.starts_with("load(\"@")));
+        assert!(defs_module.contains(indoc! {r#"
+            _PACKAGE_SHARDS = {
+                "app": "//:shards/app.bzl",
+                "tools/lib": "//:shards/tools.bzl",
+            }
+        "#}));
+        assert!(defs_module.contains(r#"def all_crate_deps("#));
+        assert!(!defs_module.contains("_NORMAL_DEPENDENCIES"));
+        assert!(defs_module.contains("def crate_repositories():"));
+
+        let app_shard = output.get(&PathBuf::from("shards/app.bzl")).unwrap();
+        assert!(app_shard.contains(r#""app": {"#));
+        assert!(!app_shard.contains(r#""tools/lib": {"#));
+        assert!(!app_shard.contains("def crate_repositories():"));
+
+        let tools_shard = output.get(&PathBuf::from("shards/tools.bzl")).unwrap();
+        assert!(tools_shard.contains(r#""tools/lib": {"#));
+        assert!(!tools_shard.contains(r#""app": {"#));
+
+        let build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
+        assert!(build_file_content.contains(r#""shards/**/*.bzl","#));
+    }
+
+    #[test]
+    fn render_rust_docs() {
+        let mock_library = |name: &str, version: semver::Version| -> (CrateId, CrateContext) {
+            let id = CrateId::new(name.to_owned(), version);
+            let krate = CrateContext {
+                name: id.name.clone(),
+                version: id.version.clone(),
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([Rule::Library(TargetAttributes {
+                    crate_name: name.to_owned(),
+                    crate_root: Some("src/lib.rs".to_owned()),
+                    ..TargetAttributes::default()
+                })]),
+                library_target_name: Some(name.to_owned()),
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                gen_rust_doc: None,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                vendor_keep_glob: BTreeSet::default(),
+                vendor_exclude_glob: BTreeSet::default(),
+                rust_version: None,
+            };
+            (id, krate)
+        };
+
+        let mut context = Context::default();
+        let (old_itoa_id, old_itoa) = mock_library("itoa", semver::Version::new(0, 4, 8));
+        let (itoa_id, itoa) = mock_library("itoa", semver::Version::new(1, 0, 0));
+        let (json_id, mut json) = mock_library("serde_json", semver::Version::new(1, 0, 0));
+        for id in [&old_itoa_id, &itoa_id] {
+            json.common_attrs.deps.insert(
+                CrateDependency {
+                    id: id.clone(),
+                    target: "itoa".to_owned(),
+                    alias: None,
+                    local_path: None,
+                },
+                None,
+            );
+        }
+        json.common_attrs
+            .crate_features
+            .insert("std".to_owned(), None);
+        let (undocumented_id, mut undocumented) =
+            mock_library("undocumented", VERSION_ZERO_ONE_ZERO);
+        undocumented.gen_rust_doc = Some(false);
+        context.crates.extend([
+            (old_itoa_id, old_itoa),
+            (itoa_id, itoa),
+            (json_id, json),
+            (undocumented_id, undocumented),
+        ]);
+
+        let mut render_config = mock_render_config(None);
+        Arc::get_mut(&mut render_config).unwrap().generate_rust_docs = true;
+        let renderer = Renderer::new(render_config, mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.serde_json-1.0.0.bazel"))
+            .unwrap();
+        assert!(build_file_content.contains(indoc! {r#"
+            rust_doc(
+                name = "serde_json_doc",
+                crate = ":serde_json",
+                rustdoc_flags = ["--cap-lints=allow"],
+                tags = ["manual"],
+            )
+        "#}));
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.undocumented-0.1.0.bazel"))
+            .unwrap();
+        assert!(!build_file_content.contains("rust_doc("));
+
+        // Every version of a crate is included in the index.
+        let build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
+        assert!(build_file_content.contains(indoc! {r#"
+            crates_doc_index(
+                name = "docs",
+                docs = {
+                    "@test_rendering__itoa-0.4.8//:itoa_doc": "0.4.8",
+                    "@test_rendering__itoa-1.0.0//:itoa_doc": "1.0.0",
+                    "@test_rendering__serde_json-1.0.0//:serde_json_doc": "1.0.0",
+                },
+                links = {
+                    "serde_json-1.0.0": [
+                        "itoa-0.4.8",
+                        "itoa-1.0.0",
+                    ],
+                },
+                tags = ["manual"],
+            )
+        "#}));
+    }
 }
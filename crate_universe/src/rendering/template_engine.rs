@@ -6,7 +6,7 @@ use std::sync::Arc;
 use anyhow::{Context as AnyhowContext, Result};
 use serde_json::{from_value, to_value, Value};
 
-use crate::config::RenderConfig;
+use crate::config::{CrateId, RenderConfig};
 use crate::context::{Context, SingleBuildFileRenderContext};
 use crate::rendering::{
     render_crate_bazel_label, render_crate_bazel_repository, render_crate_build_file,
@@ -58,6 +58,13 @@ impl TemplateEngine {
                     "/src/rendering/templates/partials/module/repo_http.j2"
                 )),
             ),
+            (
+                "partials/module/sharded_macros.j2",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/src/rendering/templates/partials/module/sharded_macros.j2"
+                )),
+            ),
             (
                 "partials/header.j2",
                 include_str!(concat!(
@@ -162,14 +169,67 @@ impl TemplateEngine {
     ) -> Result<String> {
         let mut context = self.new_tera_ctx();
         context.insert("context", data);
+        context.insert("workspace_members", &data.workspace_members);
         context.insert("platforms", platforms);
         context.insert("generator", &generator);
+        context.insert("shard", &false);
+        context.insert("package_shards", &BTreeMap::<String, String>::new());
 
         self.engine
             .render("module_bzl.j2", &context)
             .context("Failed to render crates module")
     }
 
+    /// Render a crates module whose dependency data is split into shards. The shards are
+    /// not loaded by this module, BUILD files load the macros from the shard of their package.
+    ///
+    /// `package_shards` maps each workspace member package to the file of its shard.
+    pub(crate) fn render_sharded_module_bzl(
+        &self,
+        data: &Context,
+        package_shards: &BTreeMap<&String, String>,
+        generator: Option<Label>,
+    ) -> Result<String> {
+        let mut context = self.new_tera_ctx();
+        context.insert("context", data);
+        context.insert("generator", &generator);
+        context.insert("shard", &false);
+        context.insert("package_shards", package_shards);
+
+        self.engine
+            .render("module_bzl.j2", &context)
+            .context("Failed to render sharded crates module")
+    }
+
+    /// Render a crates module for each shard of workspace members, keyed by shard file.
+    pub(crate) fn render_module_shard_bzls(
+        &self,
+        data: &Context,
+        shards: &BTreeMap<String, BTreeMap<&CrateId, &String>>,
+        platforms: &Platforms,
+    ) -> Result<BTreeMap<String, String>> {
+        // The context is shared by every shard so it's only serialized once.
+        let mut context = self.new_tera_ctx();
+        context.insert("context", data);
+        context.insert("platforms", platforms);
+        context.insert("generator", &None::<Label>);
+        context.insert("shard", &true);
+        context.insert("package_shards", &BTreeMap::<String, String>::new());
+
+        shards
+            .iter()
+            .map(|(file, workspace_members)| {
+                let mut context = context.clone();
+                context.insert("workspace_members", workspace_members);
+                let content = self
+                    .engine
+                    .render("module_bzl.j2", &context)
+                    .with_context(|| format!("Failed to render crates module shard `{}`", file))?;
+                Ok((file.clone(), content))
+            })
+            .collect()
+    }
+
     pub(crate) fn render_vendor_module_file(&self, data: &Context) -> Result<String> {
         let mut context = self.new_tera_ctx();
         context.insert("context", data);
@@ -2,7 +2,13 @@
 
 pub(crate) mod cargo_config;
 mod crate_index_lookup;
+mod credentials;
+mod local_overrides;
+mod outdated;
+mod profiles;
 mod splicer;
+mod upgrader;
+mod yanked;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
@@ -15,14 +21,21 @@ use cargo_lock::package::SourceKind;
 use cargo_toml::Manifest;
 use serde::{Deserialize, Serialize};
 
-use crate::config::CrateId;
+use crate::config::{CrateId, VersionResolution};
 use crate::metadata::{Cargo, CargoUpdateRequest, LockGenerator, TreeResolverMetadata};
 use crate::utils;
 use crate::utils::starlark::Label;
 
 use self::cargo_config::CargoConfig;
 use self::crate_index_lookup::CrateIndexLookup;
+pub(crate) use self::credentials::RegistryCredentials;
+pub(crate) use self::local_overrides::LocalOverrides;
+pub(crate) use self::outdated::{find_outdated_crates, update_crate_indexes};
+pub(crate) use self::profiles::{CargoProfiles, CompilationModeFlags};
 pub(crate) use self::splicer::*;
+pub(crate) use self::upgrader::*;
+use self::yanked::find_yanked_crates;
+pub(crate) use self::yanked::{check_yanked_crates, YankedCrates};
 
 type DirectPackageManifest = BTreeMap<String, cargo_toml::DependencyDetail>;
 
@@ -94,6 +107,16 @@ impl SplicingManifest {
             ..self
         }
     }
+    /// Find the private registries in the Cargo config.
+    pub(crate) fn registry_credentials(&self) -> Result<RegistryCredentials> {
+        match &self.cargo_config {
+            Some(path) => {
+                let cargo_config = CargoConfig::try_from_path(path.as_std_path())?;
+                Ok(RegistryCredentials::load(&cargo_config))
+            }
+            None => Ok(RegistryCredentials::default()),
+        }
+    }
 }
 
 /// The result of fully resolving a [SplicingManifest] in preparation for splicing.
@@ -156,6 +179,23 @@ pub(crate) struct SourceInfo {
 
     /// The `.crate` file's sha256 checksum.
     pub(crate) sha256: String,
+
+    /// Whether or not the registry requires a token to download the `.crate` file.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) authenticated: bool,
+}
+
+impl SourceInfo {
+    /// The `http_archive.auth_patterns` which send the registry token, read from
+    /// the user's `.netrc` file, as the `Authorization` header Cargo registries expect.
+    pub(crate) fn auth_patterns(&self) -> Option<BTreeMap<String, String>> {
+        if !self.authenticated {
+            return None;
+        }
+        let url = url::Url::parse(&self.url).ok()?;
+        let host = url.host_str()?;
+        Some(BTreeMap::from([(host.to_owned(), "<password>".to_owned())]))
+    }
 }
 
 /// Information about the Cargo workspace relative to the Bazel workspace
@@ -175,6 +215,16 @@ pub(crate) struct WorkspaceMetadata {
     /// We store this here because it's computed during the splicing phase via
     /// calls to "cargo tree" which need the full spliced workspace.
     pub(crate) tree_metadata: TreeResolverMetadata,
+
+    /// Resolved crates which have been yanked from their registry.
+    ///
+    /// Like `sources`, this is found in the crate indexes while splicing.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) yanked: YankedCrates,
+
+    /// The `rustc` flags equivalent to the `[profile]` tables of the root manifest.
+    #[serde(default, skip_serializing_if = "CargoProfiles::is_empty")]
+    pub(crate) profiles: CargoProfiles,
 }
 
 impl TryFrom<toml::Value> for WorkspaceMetadata {
@@ -208,6 +258,7 @@ impl WorkspaceMetadata {
     fn new(
         splicing_manifest: &SplicingManifest,
         member_manifests: BTreeMap<&Utf8PathBuf, String>,
+        profiles: &toml::Table,
     ) -> Result<Self> {
         let mut package_prefixes: BTreeMap<String, String> = member_manifests
             .iter()
@@ -248,6 +299,9 @@ impl WorkspaceMetadata {
             workspace_prefix,
             package_prefixes,
             tree_metadata: TreeResolverMetadata::new(),
+            yanked: BTreeMap::new(),
+            profiles: CargoProfiles::new(profiles)
+                .context("Failed to parse the profiles of the root manifest")?,
         })
     }
 
@@ -282,100 +336,7 @@ impl WorkspaceMetadata {
             .filter(|pkg| pkg.source.as_ref().unwrap().is_registry())
             .collect();
 
-        // Collect a unique set of index urls
-        let index_urls: BTreeSet<(SourceKind, String)> = pkg_sources
-            .iter()
-            .map(|pkg| {
-                let source = pkg.source.as_ref().unwrap();
-                (source.kind().clone(), source.url().to_string())
-            })
-            .collect();
-
-        // Load the cargo config
-        let cargo_config = {
-            // Note that this path must match the one defined in `splicing::setup_cargo_config`
-            let config_path = input_manifest_path
-                .parent()
-                .unwrap()
-                .join(".cargo")
-                .join("config.toml");
-
-            if config_path.exists() {
-                Some(CargoConfig::try_from_path(config_path.as_std_path())?)
-            } else {
-                None
-            }
-        };
-
-        let crate_index_hash_kind = if cargo.uses_stable_registry_hash()? {
-            crates_index::HashKind::Stable
-        } else {
-            crates_index::HashKind::Legacy
-        };
-
-        // Load each index for easy access
-        let crate_indexes = index_urls
-            .into_iter()
-            .map(|(source_kind, url)| {
-                // Ensure the correct registry is mapped based on the give Cargo config.
-                let index_url = if let Some(config) = &cargo_config {
-                    config.resolve_replacement_url(&url)?
-                } else {
-                    &url
-                };
-                let index = if cargo.use_sparse_registries_for_crates_io()?
-                    && index_url == utils::CRATES_IO_INDEX_URL
-                {
-                    CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
-                        "sparse+https://index.crates.io/",
-                        &crate_index_hash_kind,
-                    )?)
-                } else if index_url.starts_with("sparse+") {
-                    CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
-                        index_url,
-                        &crate_index_hash_kind,
-                    )?)
-                } else {
-                    match source_kind {
-                        SourceKind::Registry => {
-                            let index = {
-                                // Load the index for the current url
-                                let index = crates_index::GitIndex::from_url_with_hash_kind(
-                                    index_url,
-                                    &crate_index_hash_kind,
-                                )
-                                .with_context(|| {
-                                    format!("Failed to load index for url: {index_url}")
-                                })?;
-
-                                // Ensure each index has a valid index config
-                                index.index_config().with_context(|| {
-                                    format!("`config.json` not found in index: {index_url}")
-                                })?;
-
-                                index
-                            };
-                            CrateIndexLookup::Git(index)
-                        }
-                        SourceKind::SparseRegistry => CrateIndexLookup::Http(
-                            crates_index::SparseIndex::from_url_with_hash_kind(
-                                format!("sparse+{}", index_url).as_str(),
-                                &crate_index_hash_kind,
-                            )?,
-                        ),
-                        unknown => {
-                            return Err(anyhow!(
-                                "'{:?}' crate index type is not supported (caused by '{}')",
-                                &unknown,
-                                url
-                            ));
-                        }
-                    }
-                };
-                Ok((url, index))
-            })
-            .collect::<Result<BTreeMap<String, _>>>()
-            .context("Failed to locate crate indexes")?;
+        let crate_indexes = load_crate_indexes(cargo, lockfile, input_manifest_path)?;
 
         // Get the download URL of each package based on it's registry url.
         let additional_sources = pkg_sources
@@ -389,10 +350,14 @@ impl WorkspaceMetadata {
                         source_id
                     )
                 })?;
+                let authenticated = cargo.registry_credentials().is_authenticated(&source_url);
                 lookup.get_source_info(pkg).map(|source_info| {
                     (
                         CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone()),
-                        source_info,
+                        source_info.map(|source_info| SourceInfo {
+                            authenticated,
+                            ..source_info
+                        }),
                     )
                 })
             })
@@ -407,6 +372,8 @@ impl WorkspaceMetadata {
                         source_info.map(|source_info| (crate_id, source_info))
                     }),
             );
+        workspace_metaata.yanked = find_yanked_crates(lockfile, &crate_indexes)
+            .context("Failed to check for yanked crates")?;
         workspace_metaata.tree_metadata = resolver_data;
         workspace_metaata.inject_into(&mut manifest)?;
 
@@ -437,6 +404,129 @@ impl WorkspaceMetadata {
     }
 }
 
+/// Load the crate index of every registry a package in `lockfile` is sourced from, keyed by the
+/// registry's source url. `manifest_path` is the spliced manifest the lockfile was generated for.
+pub(crate) fn load_crate_indexes(
+    cargo: &Cargo,
+    lockfile: &cargo_lock::Lockfile,
+    manifest_path: &Utf8Path,
+) -> Result<BTreeMap<String, CrateIndexLookup>> {
+    // Load the cargo config
+    let cargo_config = {
+        // Note that this path must match the one defined in `splicing::setup_cargo_config`
+        let config_path = manifest_path
+            .parent()
+            .unwrap()
+            .join(".cargo")
+            .join("config.toml");
+
+        if config_path.exists() {
+            Some(CargoConfig::try_from_path(config_path.as_std_path())?)
+        } else {
+            None
+        }
+    };
+
+    load_crate_indexes_with_config(cargo, lockfile, cargo_config.as_ref())
+}
+
+/// Like [load_crate_indexes] but with registry replacements taken from an already loaded
+/// `cargo_config`.
+pub(crate) fn load_crate_indexes_with_config(
+    cargo: &Cargo,
+    lockfile: &cargo_lock::Lockfile,
+    cargo_config: Option<&CargoConfig>,
+) -> Result<BTreeMap<String, CrateIndexLookup>> {
+    // Collect a unique set of index urls
+    let index_urls: BTreeSet<(SourceKind, String)> = lockfile
+        .packages
+        .iter()
+        .filter(|pkg| pkg.source.is_some())
+        .filter(|pkg| pkg.source.as_ref().unwrap().is_registry())
+        .map(|pkg| {
+            let source = pkg.source.as_ref().unwrap();
+            (source.kind().clone(), source.url().to_string())
+        })
+        .collect();
+
+    let crate_index_hash_kind = if cargo.uses_stable_registry_hash()? {
+        crates_index::HashKind::Stable
+    } else {
+        crates_index::HashKind::Legacy
+    };
+
+    // Load each index for easy access
+    index_urls
+        .into_iter()
+        .map(|(source_kind, url)| {
+            // Ensure the correct registry is mapped based on the give Cargo config.
+            let index_url = if let Some(config) = cargo_config {
+                config.resolve_replacement_url(&url)?
+            } else {
+                &url
+            };
+            let index = if cargo.use_sparse_registries_for_crates_io()?
+                && index_url == utils::CRATES_IO_INDEX_URL
+            {
+                CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
+                    "sparse+https://index.crates.io/",
+                    &crate_index_hash_kind,
+                )?)
+            } else if index_url.starts_with("sparse+") {
+                CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
+                    index_url,
+                    &crate_index_hash_kind,
+                )?)
+            } else {
+                match source_kind {
+                    SourceKind::Registry => {
+                        let index = {
+                            // Load the index for the current url
+                            let index = crates_index::GitIndex::from_url_with_hash_kind(
+                                index_url,
+                                &crate_index_hash_kind,
+                            )
+                            .with_context(|| {
+                                format!("Failed to load index for url: {index_url}")
+                            })?;
+
+                            // Ensure each index has a valid index config
+                            index.index_config().with_context(|| {
+                                format!("`config.json` not found in index: {index_url}")
+                            })?;
+
+                            index
+                        };
+                        CrateIndexLookup::Git(index)
+                    }
+                    SourceKind::SparseRegistry => {
+                        CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
+                            format!("sparse+{}", index_url).as_str(),
+                            &crate_index_hash_kind,
+                        )?)
+                    }
+                    SourceKind::LocalRegistry => {
+                        let path = url::Url::parse(index_url)
+                            .ok()
+                            .and_then(|url| url.to_file_path().ok())
+                            .ok_or_else(|| anyhow!("Invalid local registry url: {index_url}"))?;
+                        CrateIndexLookup::Local(path.join("index"))
+                    }
+                    unknown => {
+                        return Err(anyhow!(
+                            "'{:?}' crate index type is not supported (caused by '{}')",
+                            &unknown,
+                            url
+                        ));
+                    }
+                }
+            };
+            Ok((url, index))
+        })
+        .collect::<Result<BTreeMap<String, _>>>()
+        .context("Failed to locate crate indexes")
+}
+
 #[derive(Debug)]
 pub(crate) enum SplicedManifest {
     Workspace(Utf8PathBuf),
@@ -459,11 +549,24 @@ pub(crate) fn read_manifest(manifest: &Utf8Path) -> Result<Manifest> {
     cargo_toml::Manifest::from_str(content.as_str()).context("Failed to deserialize manifest")
 }
 
+/// Read the raw `[profile]` table of a manifest for [CargoProfiles::new].
+pub(crate) fn read_manifest_profiles(manifest: &Utf8Path) -> Result<toml::Table> {
+    let content = fs::read_to_string(manifest.as_std_path())?;
+    let mut manifest: toml::Table =
+        toml::from_str(content.as_str()).context("Failed to deserialize manifest")?;
+    match manifest.remove("profile") {
+        Some(toml::Value::Table(profiles)) => Ok(profiles),
+        Some(_) => bail!("`profile` must be a table"),
+        None => Ok(toml::Table::new()),
+    }
+}
+
 pub(crate) fn generate_lockfile(
     manifest_path: &SplicedManifest,
     existing_lock: &Option<PathBuf>,
     cargo_bin: Cargo,
     update_request: &Option<CargoUpdateRequest>,
+    version_resolution: VersionResolution,
 ) -> Result<cargo_lock::Lockfile> {
     let manifest_dir = manifest_path
         .as_path_buf()
@@ -478,11 +581,9 @@ pub(crate) fn generate_lockfile(
     }
 
     // Generate the new lockfile
-    let lockfile = LockGenerator::new(cargo_bin).generate(
-        manifest_path.as_path_buf(),
-        existing_lock,
-        update_request,
-    )?;
+    let lockfile = LockGenerator::new(cargo_bin)
+        .with_version_resolution(version_resolution)
+        .generate(manifest_path.as_path_buf(), existing_lock, update_request)?;
 
     // Write the lockfile to disk
     if !root_lockfile_path.exists() {
@@ -46,6 +46,23 @@ pub(crate) struct AdditionalRegistry {
 
     /// authentication token for the registry
     pub(crate) token: Option<String>,
+
+    /// the provider of the registry's authentication token
+    #[serde(
+        default,
+        rename = "credential-provider",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) credential_provider: Option<CredentialProvider>,
+}
+
+/// A [credential provider](https://doc.rust-lang.org/cargo/reference/registry-authentication.html)
+/// and its arguments, written either as a single string or as an array.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub(crate) enum CredentialProvider {
+    Command(String),
+    Args(Vec<String>),
 }
 
 /// A subset of a Cargo configuration file. The schema here is only what
@@ -74,6 +91,7 @@ fn default_registries() -> BTreeMap<String, AdditionalRegistry> {
         AdditionalRegistry {
             index: default_registry_url(),
             token: None,
+            credential_provider: None,
         },
     );
     registries
@@ -197,6 +215,7 @@ mod test {
                             index: "https://artprod.mycompany/artifactory/git/cargo-remote.git"
                                 .to_owned(),
                             token: None,
+                            credential_provider: None,
                         },
                     ),
                     (
@@ -204,6 +223,7 @@ mod test {
                         AdditionalRegistry {
                             index: "https://github.com/rust-lang/crates.io-index".to_owned(),
                             token: None,
+                            credential_provider: None,
                         },
                     ),
                 ]),
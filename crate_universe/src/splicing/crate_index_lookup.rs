@@ -1,27 +1,48 @@
+use std::path::{Path, PathBuf};
+
 use crate::splicing::SourceInfo;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use crates_index::IndexConfig;
 use hex::ToHex;
 
+/// A single published version of a crate, as recorded in a crate index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexVersion {
+    pub(crate) version: semver::Version,
+    pub(crate) yanked: bool,
+}
+
 pub(crate) enum CrateIndexLookup {
     Git(crates_index::GitIndex),
     Http(crates_index::SparseIndex),
+    /// The `index` directory of a [local registry](https://doc.rust-lang.org/cargo/reference/source-replacement.html#local-registry-sources).
+    Local(PathBuf),
 }
 
 impl CrateIndexLookup {
     pub(crate) fn get_source_info(&self, pkg: &cargo_lock::Package) -> Result<Option<SourceInfo>> {
+        if let Self::Local(path) = self {
+            bail!(
+                "Crates from local registries cannot be downloaded. `{} {}` is from {}",
+                pkg.name,
+                pkg.version,
+                path.display()
+            );
+        }
         let index_config = self
             .index_config()
             .context("Failed to get crate index config")?;
         let crate_ = match self {
             // The crates we care about should all be in the cache already,
-            // because `cargo metadata` ran which should have fetched them.
+            // because `cargo metadata` ran which should have fetched them,
+            // authenticating with the registry's credential provider if needed.
             Self::Http(index) => {
                 Some(index.crate_from_cache(pkg.name.as_str()).with_context(|| {
                     format!("Failed to get crate from cache: {:?}\n{:?}", index, pkg)
                 })?)
             }
             Self::Git(index) => index.crate_(pkg.name.as_str()),
+            Self::Local(path) => local_crate(path, pkg.name.as_str())?,
         };
         let source_info = crate_.and_then(|crate_idx| {
             crate_idx
@@ -35,25 +56,83 @@ impl CrateIndexLookup {
                             .as_ref()
                             .and_then(|sum| sum.as_sha256().map(|sum| sum.encode_hex::<String>()))
                             .unwrap_or_else(|| v.checksum().encode_hex::<String>());
-                        SourceInfo { url, sha256 }
+                        SourceInfo {
+                            url,
+                            sha256,
+                            authenticated: false,
+                        }
                     })
                 })
         });
         Ok(source_info)
     }
 
+    /// Get every version of a crate published to the index, or `None` if the crate is unknown.
+    ///
+    /// Like [CrateIndexLookup::get_source_info], sparse indexes are only read from the local
+    /// cache and git indexes from their local checkout, which
+    /// [crate::splicing::update_crate_indexes] brings up to date.
+    pub(crate) fn versions(&self, name: &str) -> Result<Option<Vec<IndexVersion>>> {
+        let crate_ = match self {
+            Self::Http(index) => index.crate_from_cache(name).ok(),
+            Self::Git(index) => index.crate_(name),
+            Self::Local(path) => local_crate(path, name)?,
+        };
+        crate_
+            .map(|crate_idx| {
+                crate_idx
+                    .versions()
+                    .iter()
+                    .map(|v| {
+                        Ok(IndexVersion {
+                            version: semver::Version::parse(v.version()).with_context(|| {
+                                format!("Invalid version in index for {name}: {}", v.version())
+                            })?,
+                            yanked: v.is_yanked(),
+                        })
+                    })
+                    .collect()
+            })
+            .transpose()
+    }
+
     #[allow(clippy::result_large_err)]
     fn index_config(&self) -> Result<IndexConfig, crates_index::Error> {
         match self {
             Self::Git(index) => index.index_config(),
             Self::Http(index) => index.index_config(),
+            Self::Local(_) => unreachable!("Local registries have no index config"),
         }
     }
 }
 
+/// The path of a crate's entry relative to the root of an index.
+fn index_path(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+    }
+}
+
+/// Read a crate from the index of a local registry, or `None` if the crate is unknown.
+fn local_crate(index_dir: &Path, name: &str) -> Result<Option<crates_index::Crate>> {
+    let path = index_dir.join(index_path(name));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    crates_index::Crate::from_slice(&content)
+        .map(Some)
+        .with_context(|| format!("Failed to parse index entry {}", path.display()))
+}
+
 #[cfg(test)]
 mod test {
-    use crate::splicing::crate_index_lookup::CrateIndexLookup;
+    use crate::splicing::crate_index_lookup::{index_path, CrateIndexLookup};
     use semver::Version;
     use std::ffi::OsString;
 
@@ -95,6 +174,12 @@ mod test {
                 source_info.sha256,
                 "e2abad23fbc42b3700f2f279844dc832adb2b2eb069b2df918f455c4e18cc646"
             );
+
+            let versions = index.versions("lazy_static").unwrap().unwrap();
+            assert!(versions
+                .iter()
+                .any(|v| v.version == Version::parse("1.4.0").unwrap() && !v.yanked));
+            assert!(index.versions("does_not_exist").unwrap().is_none());
         }
         {
             let _e = EnvVarResetter::set("CARGO_HOME",
@@ -125,6 +210,36 @@ mod test {
         }
     }
 
+    #[test]
+    fn local_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let entry = temp_dir.path().join("la/zy/lazy_static");
+        std::fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        std::fs::write(
+            &entry,
+            [
+                r#"{"name":"lazy_static","vers":"1.4.0","deps":[],"cksum":"e2abad23fbc42b3700f2f279844dc832adb2b2eb069b2df918f455c4e18cc646","features":{},"yanked":false}"#,
+                r#"{"name":"lazy_static","vers":"1.5.0","deps":[],"cksum":"bbd2bcb4c963f2ddae06a2efc7e9f3591312473c50c6685e1f298068316e66fe","features":{},"yanked":true}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let index = CrateIndexLookup::Local(temp_dir.path().to_path_buf());
+        let versions = index.versions("lazy_static").unwrap().unwrap();
+        assert_eq!(versions.len(), 2);
+        assert!(versions[1].yanked);
+        assert!(index.versions("does_not_exist").unwrap().is_none());
+    }
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), std::path::Path::new("1/a"));
+        assert_eq!(index_path("ab"), std::path::Path::new("2/ab"));
+        assert_eq!(index_path("abc"), std::path::Path::new("3/a/abc"));
+        assert_eq!(index_path("Serde"), std::path::Path::new("se/rd/serde"));
+    }
+
     struct EnvVarResetter {
         key: OsString,
         value: Option<OsString>,
@@ -0,0 +1,245 @@
+//! Support for private registries which require
+//! [authentication](https://doc.rust-lang.org/cargo/reference/registry-authentication.html).
+//!
+//! Cargo authenticates its own reads of a registry's index with the registry's
+//! `credential-provider`, so cargo-bazel never resolves tokens itself. Crates are downloaded
+//! by Bazel, which is told to authenticate them through `http_archive.auth_patterns`.
+
+use std::collections::BTreeSet;
+
+use crate::splicing::cargo_config::CargoConfig;
+
+/// The registries with a `credential-provider`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RegistryCredentials {
+    /// The index urls of every authenticated registry, including those of sources which are
+    /// replaced by one.
+    index_urls: BTreeSet<String>,
+}
+
+impl RegistryCredentials {
+    /// Find the registries which set a `credential-provider`.
+    pub(crate) fn load(cargo_config: &CargoConfig) -> Self {
+        let mut credentials = Self::default();
+        for registry in cargo_config.registries.values() {
+            if registry.credential_provider.is_some() {
+                credentials
+                    .index_urls
+                    .insert(normalize_index_url(&registry.index));
+            }
+        }
+
+        // Crates from a replaced source are downloaded from its replacement.
+        for source in cargo_config.source.values() {
+            if let Some(replace_with) = &source.replace_with {
+                let authenticated = cargo_config
+                    .registries
+                    .get(replace_with)
+                    .is_some_and(|registry| registry.credential_provider.is_some());
+                if authenticated {
+                    credentials
+                        .index_urls
+                        .insert(normalize_index_url(&source.registry));
+                }
+            }
+        }
+
+        credentials
+    }
+
+    /// Whether or not crates from the given index require authentication.
+    pub(crate) fn is_authenticated(&self, index_url: &str) -> bool {
+        self.index_urls.contains(&normalize_index_url(index_url))
+    }
+}
+
+fn normalize_index_url(url: &str) -> String {
+    url.strip_prefix("sparse+")
+        .unwrap_or(url)
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+
+    use crate::metadata::Cargo;
+    use crate::utils;
+
+    #[test]
+    fn authenticated_registries() {
+        let cargo_config = CargoConfig::from_str(indoc::indoc! {r#"
+            [registries.token]
+            index = "sparse+https://token.example.com/index/"
+            credential-provider = "cargo:token"
+
+            [registries.private]
+            index = "https://private.example.com/index"
+            credential-provider = ["cargo:token-from-stdout", "get-token"]
+
+            [registries.anonymous]
+            index = "https://anonymous.example.com/index"
+
+            [source.crates-io]
+            replace-with = "private"
+        "#})
+        .unwrap();
+
+        let credentials = RegistryCredentials::load(&cargo_config);
+        assert!(credentials.is_authenticated("https://token.example.com/index"));
+        assert!(credentials.is_authenticated("sparse+https://private.example.com/index/"));
+        assert!(credentials.is_authenticated(utils::CRATES_IO_INDEX_URL));
+        assert!(!credentials.is_authenticated("https://anonymous.example.com/index"));
+    }
+
+    /// Get the cargo binary the Bazel way
+    #[cfg(not(feature = "cargo"))]
+    fn cargo() -> Cargo {
+        let r = runfiles::Runfiles::create().unwrap();
+        let cargo_path = runfiles::rlocation!(r, concat!("rules_rust/", env!("CARGO"))).unwrap();
+        let rustc_path = runfiles::rlocation!(r, concat!("rules_rust/", env!("RUSTC"))).unwrap();
+        Cargo::new(cargo_path, rustc_path)
+    }
+
+    /// Get the cargo binary the Cargo way
+    #[cfg(feature = "cargo")]
+    fn cargo() -> Cargo {
+        Cargo::new(PathBuf::from(env!("CARGO")), PathBuf::from("rustc"))
+    }
+
+    /// The path and `Authorization` header of each request to a registry.
+    type Requests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+    /// A stand-in for a sparse registry serving a single crate to requests with the given
+    /// token. Returns its index url and the requests it received.
+    fn serve_registry(token: &'static str) -> (String, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let config = format!(r#"{{"dl": "{url}/dl", "auth-required": true}}"#);
+        let entry = format!(
+            r#"{{"name": "foo", "vers": "1.0.0", "deps": [], "cksum": "{}", "features": {{}}, "yanked": false}}"#,
+            "0".repeat(64)
+        );
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_owned();
+                let mut authorization = None;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("authorization") {
+                            authorization = Some(value.trim().to_owned());
+                        }
+                    }
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((path.clone(), authorization.clone()));
+
+                let (status, body) = match (authorization.as_deref(), path.as_str()) {
+                    (Some(auth), "/index/config.json") if auth == token => {
+                        ("200 OK", config.as_str())
+                    }
+                    (Some(auth), "/index/3/f/foo") if auth == token => ("200 OK", entry.as_str()),
+                    (Some(auth), _) if auth == token => ("404 Not Found", ""),
+                    _ => ("401 Unauthorized", ""),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        (format!("sparse+{url}/index/"), requests)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn index_reads_use_credential_provider() {
+        let (index, requests) = serve_registry("secret-token");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = temp_dir.as_ref().join("workspace");
+        fs::create_dir_all(workspace.join("src")).unwrap();
+        fs::create_dir_all(workspace.join(".cargo")).unwrap();
+        fs::write(workspace.join("src/lib.rs"), "").unwrap();
+        fs::write(
+            workspace.join("Cargo.toml"),
+            indoc::indoc! {r#"
+                [package]
+                name = "root"
+                version = "0.1.0"
+                edition = "2021"
+
+                [dependencies]
+                foo = { version = "1.0", registry = "private" }
+            "#},
+        )
+        .unwrap();
+        let cargo_config = format!(
+            indoc::indoc! {r#"
+                [registries.private]
+                index = "{}"
+                credential-provider = ["cargo:token-from-stdout", "sh", "-c", "echo secret-token"]
+            "#},
+            index
+        );
+        fs::write(workspace.join(".cargo/config.toml"), &cargo_config).unwrap();
+
+        // The rendered `http_archive`s of the registry's crates are authenticated.
+        let credentials = RegistryCredentials::load(&CargoConfig::from_str(&cargo_config).unwrap());
+        assert!(credentials.is_authenticated(&index));
+
+        // Cargo reads the index with the token of the credential provider.
+        let output = cargo()
+            .with_registry_credentials(credentials)
+            .command()
+            .unwrap()
+            .arg("generate-lockfile")
+            .current_dir(&workspace)
+            .env("CARGO_HOME", temp_dir.as_ref().join("cargo_home"))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let lockfile = fs::read_to_string(workspace.join("Cargo.lock")).unwrap();
+        assert!(lockfile.contains(r#"name = "foo""#), "{lockfile}");
+        assert!(requests
+            .lock()
+            .unwrap()
+            .contains(&("/index/3/f/foo".to_owned(), Some("secret-token".to_owned()))));
+    }
+}
@@ -0,0 +1,141 @@
+//! Utilities for building against local checkouts of crates without editing any manifests.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use camino::Utf8PathBuf;
+
+/// A mapping of crate names to local checkouts which should be used in place of
+/// the versions resolved from crates.io.
+///
+/// These are parsed from a comma separated list of `name=path` pairs, e.g.
+/// `serde=/home/me/src/serde,log=/home/me/src/log`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LocalOverrides(BTreeMap<String, Utf8PathBuf>);
+
+impl FromStr for LocalOverrides {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut overrides = BTreeMap::new();
+        for entry in s
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (name, path) = entry
+                .split_once('=')
+                .map(|(name, path)| (name.trim(), path.trim()))
+                .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Local overrides must be in the form `name=path`. Got: `{}`",
+                        entry
+                    )
+                })?;
+
+            // Splicing happens in a different directory to the one Bazel was
+            // invoked from so relative paths could not be resolved reliably.
+            let path = Utf8PathBuf::from(path);
+            if !path.is_absolute() {
+                bail!(
+                    "The local override for `{}` must be an absolute path. Got: `{}`",
+                    name,
+                    path
+                );
+            }
+
+            if overrides.insert(name.to_owned(), path).is_some() {
+                bail!("Multiple local overrides were provided for `{}`", name);
+            }
+        }
+
+        Ok(Self(overrides))
+    }
+}
+
+impl LocalOverrides {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The `[patch.crates-io]` entries which redirect each crate to its local checkout.
+    pub(crate) fn patches(&self) -> cargo_toml::PatchSet {
+        if self.is_empty() {
+            return cargo_toml::PatchSet::new();
+        }
+
+        let patches = self
+            .0
+            .iter()
+            .map(|(name, path)| {
+                (
+                    name.clone(),
+                    cargo_toml::Dependency::Detailed(Box::new(cargo_toml::DependencyDetail {
+                        path: Some(path.to_string()),
+                        ..cargo_toml::DependencyDetail::default()
+                    })),
+                )
+            })
+            .collect();
+
+        cargo_toml::PatchSet::from([("crates-io".to_owned(), patches)])
+    }
+
+    /// Ensure each local checkout exists so typos are reported before Cargo runs.
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        for (name, path) in self.0.iter() {
+            let manifest = path.join("Cargo.toml");
+            if !manifest.exists() {
+                bail!(
+                    "The local override for `{}` has no manifest at {}",
+                    name,
+                    manifest
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_local_overrides() {
+        let overrides =
+            LocalOverrides::from_str("serde=/home/me/src/serde, log = /home/me/src/log,").unwrap();
+        assert_eq!(
+            overrides,
+            LocalOverrides(BTreeMap::from([
+                ("log".to_owned(), Utf8PathBuf::from("/home/me/src/log")),
+                ("serde".to_owned(), Utf8PathBuf::from("/home/me/src/serde")),
+            ]))
+        );
+
+        assert!(LocalOverrides::from_str("").unwrap().is_empty());
+        assert!(LocalOverrides::from_str("serde").is_err());
+        assert!(LocalOverrides::from_str("serde=").is_err());
+        assert!(LocalOverrides::from_str("serde=src/serde").is_err());
+        assert!(LocalOverrides::from_str("serde=/a,serde=/b").is_err());
+    }
+
+    #[test]
+    fn local_overrides_patches() {
+        let overrides = LocalOverrides::from_str("serde=/home/me/src/serde").unwrap();
+        let patches = overrides.patches();
+
+        let crates_io = patches.get("crates-io").unwrap();
+        match crates_io.get("serde").unwrap() {
+            cargo_toml::Dependency::Detailed(detail) => {
+                assert_eq!(detail.path.as_deref(), Some("/home/me/src/serde"));
+            }
+            dep => panic!("Unexpected dependency: {:?}", dep),
+        }
+
+        assert!(LocalOverrides::default().patches().is_empty());
+    }
+}
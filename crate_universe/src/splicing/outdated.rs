@@ -0,0 +1,539 @@
+//! Comparison of the crates in a lockfile against the newest versions in their registries.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context as AnyhowContext, Result};
+use cargo_lock::package::SourceKind;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use crate::config::CrateId;
+use crate::context::Context;
+use crate::metadata::Cargo;
+use crate::utils::CRATES_IO_INDEX_URL;
+
+use super::crate_index_lookup::{CrateIndexLookup, IndexVersion};
+
+/// A crate for which a newer version is available.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct OutdatedCrate {
+    /// The name of the crate.
+    pub(crate) name: String,
+
+    /// The version of the crate in the lockfile.
+    pub(crate) version: Version,
+
+    /// The newest version which is semver compatible with `version`, if it is newer.
+    pub(crate) compatible: Option<Version>,
+
+    /// The newest version of the crate, if it is newer.
+    pub(crate) latest: Option<Version>,
+}
+
+/// Outdated crates split by how they are depended on.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct OutdatedReport {
+    /// Crates which are direct dependencies of a workspace member.
+    pub(crate) direct: Vec<OutdatedCrate>,
+
+    /// Crates which are only depended on by other crates.
+    pub(crate) transitive: Vec<OutdatedCrate>,
+
+    /// Registry crates which are missing from their index, so newer versions are unknown.
+    pub(crate) unchecked: Vec<CrateId>,
+}
+
+impl OutdatedReport {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.direct.is_empty() && self.transitive.is_empty()
+    }
+}
+
+fn fmt_section(f: &mut fmt::Formatter<'_>, title: &str, crates: &[OutdatedCrate]) -> fmt::Result {
+    if crates.is_empty() {
+        return Ok(());
+    }
+
+    let or_dash = |version: &Option<Version>| match version {
+        Some(version) => version.to_string(),
+        None => "-".to_owned(),
+    };
+    let rows: Vec<[String; 4]> = crates
+        .iter()
+        .map(|crate_| {
+            [
+                crate_.name.clone(),
+                crate_.version.to_string(),
+                or_dash(&crate_.compatible),
+                or_dash(&crate_.latest),
+            ]
+        })
+        .collect();
+    let header = ["name", "current", "compatible", "latest"].map(str::to_owned);
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            std::iter::once(&header)
+                .chain(&rows)
+                .map(|row| row[column].len())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    writeln!(f, "{title}:")?;
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(f, "  {}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for OutdatedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            if self.unchecked.is_empty() {
+                return writeln!(f, "All crates are up to date.");
+            }
+            writeln!(f, "All checked crates are up to date.")?;
+        }
+        fmt_section(f, "Direct dependencies", &self.direct)?;
+        if !self.direct.is_empty() && !self.transitive.is_empty() {
+            writeln!(f)?;
+        }
+        fmt_section(f, "Transitive dependencies", &self.transitive)?;
+        if !self.unchecked.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Not found in their index:")?;
+            for crate_id in &self.unchecked {
+                writeln!(f, "  {crate_id}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Determine the newer versions of a crate among `versions`, if there are any.
+///
+/// Yanked versions are ignored, as are pre-releases unless the crate is itself a pre-release.
+fn newer_versions(crate_id: &CrateId, versions: &[IndexVersion]) -> Option<OutdatedCrate> {
+    let version = &crate_id.version;
+    let compatible_req = VersionReq::parse(&format!("^{version}")).ok()?;
+    let candidates = versions
+        .iter()
+        .filter(|v| !v.yanked && (v.version.pre.is_empty() || !version.pre.is_empty()))
+        .map(|v| &v.version)
+        .filter(|v| *v > version);
+
+    let mut compatible = None;
+    let mut latest = None;
+    for candidate in candidates {
+        if compatible_req.matches(candidate) && compatible.is_none_or(|v| candidate > v) {
+            compatible = Some(candidate);
+        }
+        if latest.is_none_or(|v| candidate > v) {
+            latest = Some(candidate);
+        }
+    }
+
+    latest.map(|latest| OutdatedCrate {
+        name: crate_id.name.clone(),
+        version: version.clone(),
+        compatible: compatible.cloned(),
+        latest: Some(latest.clone()),
+    })
+}
+
+/// The registry packages of `cargo_lockfile` which are crates of `context`.
+fn registry_packages<'a>(
+    context: &Context,
+    cargo_lockfile: &'a cargo_lock::Lockfile,
+) -> BTreeMap<CrateId, &'a cargo_lock::Package> {
+    cargo_lockfile
+        .packages
+        .iter()
+        .filter(|pkg| {
+            pkg.source
+                .as_ref()
+                .is_some_and(|source| source.is_registry())
+        })
+        .map(|pkg| {
+            (
+                CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone()),
+                pkg,
+            )
+        })
+        .filter(|(crate_id, _)| context.crates.contains_key(crate_id))
+        .collect()
+}
+
+/// Fetch the newest index entries of the registry crates of `context` with `fetch`, which is
+/// given the index (`None` for crates.io) and the name of a crate. Git indexes are fetched
+/// as a whole, so only once, and local registries have nothing to fetch. Returns the crates
+/// which failed to be fetched.
+fn refresh_indexes(
+    context: &Context,
+    cargo_lockfile: &cargo_lock::Lockfile,
+    mut fetch: impl FnMut(Option<&str>, &str) -> Result<()>,
+) -> Vec<(CrateId, anyhow::Error)> {
+    let mut fetched_git_indexes = BTreeSet::new();
+    let mut failures = Vec::new();
+    for (crate_id, pkg) in registry_packages(context, cargo_lockfile) {
+        let source = pkg.source.as_ref().unwrap();
+        let url = source.url().to_string();
+        let index = match source.kind() {
+            _ if url == CRATES_IO_INDEX_URL => None,
+            SourceKind::SparseRegistry => Some(format!("sparse+{url}")),
+            SourceKind::Registry => {
+                if !fetched_git_indexes.insert(url.clone()) {
+                    continue;
+                }
+                Some(url)
+            }
+            _ => continue,
+        };
+        if let Err(err) = fetch(index.as_deref(), &crate_id.name) {
+            failures.push((crate_id, err));
+        }
+    }
+    failures
+}
+
+/// Have Cargo fetch the newest index entries of the registry crates of `context`, as it only
+/// fetches those it needs while resolving a lockfile. `cargo_config` is passed to Cargo for
+/// registry replacements.
+pub(crate) fn update_crate_indexes(
+    cargo: &Cargo,
+    context: &Context,
+    cargo_lockfile: &cargo_lock::Lockfile,
+    cargo_config: Option<&Path>,
+) -> Result<()> {
+    // Run outside of any workspace so its lockfile isn't considered.
+    let temp_dir = tempfile::tempdir().context("Failed to create a temporary directory")?;
+    let failures = refresh_indexes(context, cargo_lockfile, |index, name| {
+        let mut command = cargo.command()?;
+        command
+            .current_dir(temp_dir.path())
+            .arg("info")
+            .arg("--quiet");
+        if let Some(cargo_config) = cargo_config {
+            command.arg("--config").arg(cargo_config);
+        }
+        if let Some(index) = index {
+            command.arg("--index").arg(index);
+        }
+        let output = command
+            .arg(name)
+            .output()
+            .context("Failed to run `cargo info`")?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    });
+    for (crate_id, err) in failures {
+        tracing::warn!(
+            "Unable to fetch the newest versions of {crate_id}, its index may be outdated: {err:#}"
+        );
+    }
+    Ok(())
+}
+
+/// Compare every registry crate of `context` against the versions `versions` returns for it.
+fn find_outdated(
+    context: &Context,
+    cargo_lockfile: &cargo_lock::Lockfile,
+    mut versions: impl FnMut(&cargo_lock::Package) -> Result<Option<Vec<IndexVersion>>>,
+) -> Result<OutdatedReport> {
+    let registry_packages = registry_packages(context, cargo_lockfile);
+
+    let mut report = OutdatedReport::default();
+    for (crate_id, pkg) in &registry_packages {
+        let Some(versions) = versions(pkg)? else {
+            tracing::warn!(
+                "Unable to check {crate_id} for newer versions, it's missing from its index"
+            );
+            report.unchecked.push(crate_id.clone());
+            continue;
+        };
+        let Some(outdated) = newer_versions(crate_id, &versions) else {
+            continue;
+        };
+
+        if context.direct_deps.contains(crate_id) || context.direct_dev_deps.contains(crate_id) {
+            report.direct.push(outdated);
+        } else {
+            report.transitive.push(outdated);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Compare every registry crate of `context` against the newest versions in `crate_indexes`.
+///
+/// Registries are identified by the sources recorded in `cargo_lockfile`. The indexes should be
+/// updated with [update_crate_indexes] first. Crates missing from an index are reported as
+/// unchecked.
+pub(crate) fn find_outdated_crates(
+    context: &Context,
+    cargo_lockfile: &cargo_lock::Lockfile,
+    crate_indexes: &BTreeMap<String, CrateIndexLookup>,
+) -> Result<OutdatedReport> {
+    find_outdated(context, cargo_lockfile, |pkg| {
+        let source_id = pkg.source.as_ref().unwrap();
+        let lookup = crate_indexes
+            .get(&source_id.url().to_string())
+            .ok_or_else(|| anyhow!("Couldn't find crate_index data for SourceID {source_id:?}"))?;
+        lookup.versions(pkg.name.as_str())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "direct-cargo-bazel-deps"
+version = "0.0.1"
+dependencies = [
+ "log",
+ "tracing",
+]
+
+[[package]]
+name = "log"
+version = "0.4.20"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "b5e6163cb8c49088c2c36f57875e58ccd8c87c7427f7fbd50ea6710b2f3f2e8f"
+
+[[package]]
+name = "tracing"
+version = "0.1.40"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "c3523ab5a71916ccf420eebdf5521fcef02141234bbc0b8a49f2fdc4544364ef"
+dependencies = [
+ "log",
+]
+"#;
+
+    fn index_versions(versions: &[(&str, bool)]) -> Vec<IndexVersion> {
+        versions
+            .iter()
+            .map(|(version, yanked)| IndexVersion {
+                version: Version::parse(version).unwrap(),
+                yanked: *yanked,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn newer_compatible_and_latest() {
+        let versions = index_versions(&[
+            ("0.4.20", false),
+            ("0.4.21", false),
+            ("0.4.22", true),
+            ("0.5.0", false),
+            ("0.6.0-alpha.1", false),
+        ]);
+        let log = |version| CrateId::new("log".to_owned(), version);
+        assert_eq!(
+            newer_versions(&log(Version::new(0, 4, 20)), &versions),
+            Some(OutdatedCrate {
+                name: "log".to_owned(),
+                version: Version::new(0, 4, 20),
+                compatible: Some(Version::new(0, 4, 21)),
+                latest: Some(Version::new(0, 5, 0)),
+            })
+        );
+        assert_eq!(newer_versions(&log(Version::new(0, 5, 0)), &versions), None);
+        assert_eq!(
+            newer_versions(&log(Version::new(0, 4, 21)), &versions)
+                .unwrap()
+                .compatible,
+            None
+        );
+    }
+
+    #[test]
+    fn split_direct_and_transitive() {
+        let lockfile = cargo_lock::Lockfile::from_str(LOCKFILE).unwrap();
+        let log = CrateId::new("log".to_owned(), Version::new(0, 4, 20));
+        let tracing = CrateId::new("tracing".to_owned(), Version::new(0, 1, 40));
+
+        let mut context = Context::default();
+        for crate_id in [&log, &tracing] {
+            context.crates.insert(
+                crate_id.clone(),
+                serde_json::from_value(serde_json::json!({
+                    "name": crate_id.name,
+                    "version": crate_id.version,
+                    "repository": null,
+                    "license_ids": [],
+                }))
+                .unwrap(),
+            );
+        }
+        context.direct_deps = BTreeSet::from([tracing.clone()]);
+
+        let report = find_outdated(&context, &lockfile, |pkg| {
+            Ok(Some(match pkg.name.as_str() {
+                "log" => index_versions(&[("0.4.20", false), ("0.4.22", false)]),
+                _ => index_versions(&[("0.1.40", false), ("0.2.0", false)]),
+            }))
+        })
+        .unwrap();
+
+        assert_eq!(
+            report,
+            OutdatedReport {
+                direct: vec![OutdatedCrate {
+                    name: "tracing".to_owned(),
+                    version: Version::new(0, 1, 40),
+                    compatible: None,
+                    latest: Some(Version::new(0, 2, 0)),
+                }],
+                transitive: vec![OutdatedCrate {
+                    name: "log".to_owned(),
+                    version: Version::new(0, 4, 20),
+                    compatible: Some(Version::new(0, 4, 22)),
+                    latest: Some(Version::new(0, 4, 22)),
+                }],
+                unchecked: Vec::new(),
+            }
+        );
+
+        assert_eq!(
+            report.to_string(),
+            indoc::indoc! {"
+                Direct dependencies:
+                  name     current  compatible  latest
+                  tracing  0.1.40   -           0.2.0
+
+                Transitive dependencies:
+                  name  current  compatible  latest
+                  log   0.4.20   0.4.22      0.4.22
+            "}
+        );
+    }
+
+    fn mock_context(crate_ids: &[&CrateId]) -> Context {
+        let mut context = Context::default();
+        for crate_id in crate_ids {
+            context.crates.insert(
+                (*crate_id).clone(),
+                serde_json::from_value(serde_json::json!({
+                    "name": crate_id.name,
+                    "version": crate_id.version,
+                    "repository": null,
+                    "license_ids": [],
+                }))
+                .unwrap(),
+            );
+        }
+        context
+    }
+
+    #[test]
+    fn crates_missing_from_index_are_unchecked() {
+        let lockfile = cargo_lock::Lockfile::from_str(LOCKFILE).unwrap();
+        let log = CrateId::new("log".to_owned(), Version::new(0, 4, 20));
+        let tracing = CrateId::new("tracing".to_owned(), Version::new(0, 1, 40));
+        let context = mock_context(&[&log, &tracing]);
+
+        let report = find_outdated(&context, &lockfile, |pkg| {
+            Ok(match pkg.name.as_str() {
+                "log" => Some(index_versions(&[("0.4.20", false)])),
+                _ => None,
+            })
+        })
+        .unwrap();
+
+        assert!(report.is_empty());
+        assert_eq!(report.unchecked, vec![tracing]);
+        assert_eq!(
+            report.to_string(),
+            indoc::indoc! {"
+                All checked crates are up to date.
+
+                Not found in their index:
+                  tracing 0.1.40
+            "}
+        );
+    }
+
+    #[test]
+    fn refresh_each_registry_crate() {
+        let lockfile = cargo_lock::Lockfile::from_str(&format!(
+            "{LOCKFILE}{}",
+            indoc::indoc! {r#"
+
+                [[package]]
+                name = "serde"
+                version = "1.0.0"
+                source = "sparse+https://registry.example.com/index/"
+
+                [[package]]
+                name = "serde_json"
+                version = "1.0.0"
+                source = "registry+https://git.example.com/index"
+
+                [[package]]
+                name = "itoa"
+                version = "1.0.0"
+                source = "registry+https://git.example.com/index"
+            "#}
+        ))
+        .unwrap();
+        let crate_ids = lockfile
+            .packages
+            .iter()
+            .map(|pkg| CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone()))
+            .collect::<Vec<_>>();
+        let context = mock_context(&crate_ids.iter().collect::<Vec<_>>());
+
+        let mut fetched = Vec::new();
+        let failures = refresh_indexes(&context, &lockfile, |index, name| {
+            fetched.push((index.map(str::to_owned), name.to_owned()));
+            if name == "tracing" {
+                bail!("offline");
+            }
+            Ok(())
+        });
+
+        assert_eq!(
+            fetched,
+            vec![
+                (
+                    Some("https://git.example.com/index".to_owned()),
+                    "itoa".to_owned()
+                ),
+                (None, "log".to_owned()),
+                (
+                    Some("sparse+https://registry.example.com/index/".to_owned()),
+                    "serde".to_owned()
+                ),
+                (None, "tracing".to_owned()),
+            ]
+        );
+        assert_eq!(
+            failures
+                .into_iter()
+                .map(|(crate_id, _)| crate_id)
+                .collect::<Vec<_>>(),
+            vec![CrateId::new("tracing".to_owned(), Version::new(0, 1, 40))]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Translation of Cargo `[profile.*]` tables into `rustc` flags.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result};
+use cargo_toml::{LtoSetting, Profile, StripSetting};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::compilation_mode::CompilationMode;
+
+/// The package name Cargo uses for overrides which apply to every dependency.
+const ALL_PACKAGES: &str = "*";
+
+/// `rustc` flags for each Bazel `compilation_mode`.
+pub(crate) type CompilationModeFlags = BTreeMap<CompilationMode, Vec<String>>;
+
+/// The `rustc` flags equivalent to the `[profile.dev]` and `[profile.release]`
+/// tables of the root manifest. `dev` applies to `dbg` and `fastbuild` builds
+/// and `release` applies to `opt` builds.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CargoProfiles {
+    /// Flags which apply to every crate.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    common: CompilationModeFlags,
+
+    /// Flags from `[profile.*.package.<name>]` tables which apply after `common`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    packages: BTreeMap<String, CompilationModeFlags>,
+
+    /// Settings which affect an entire build and so can't be applied to individual crates.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) unsupported: BTreeSet<String>,
+}
+
+impl CargoProfiles {
+    /// Translate the raw `[profile]` table of a manifest. The table is read directly as
+    /// `cargo_toml` normalizes some settings (e.g. `debug = "limited"`) in ways which lose
+    /// the `rustc` flag Cargo would use.
+    pub(crate) fn new(profiles: &toml::Table) -> Result<Self> {
+        let mut cargo_profiles = Self::default();
+        for compilation_mode in CompilationMode::ALL {
+            let profile_name = compilation_mode.cargo_profile();
+            let Some(table) = profiles.get(profile_name) else {
+                continue;
+            };
+            let profile: Profile = table
+                .clone()
+                .try_into()
+                .with_context(|| format!("Failed to parse `[profile.{}]`", profile_name))?;
+
+            cargo_profiles
+                .unsupported
+                .extend(unsupported_settings(profile_name, &profile));
+
+            let flags = rustc_flags(&profile, table);
+            if !flags.is_empty() {
+                cargo_profiles.common.insert(compilation_mode, flags);
+            }
+
+            for (package, value) in profile.package.iter() {
+                let package_profile: Profile = value.clone().try_into().with_context(|| {
+                    format!(
+                        "Failed to parse `[profile.{}.package.{}]`",
+                        profile_name, package
+                    )
+                })?;
+
+                let flags = rustc_flags(&package_profile, value);
+                if !flags.is_empty() {
+                    cargo_profiles
+                        .packages
+                        .entry(package.clone())
+                        .or_default()
+                        .insert(compilation_mode, flags);
+                }
+            }
+        }
+
+        Ok(cargo_profiles)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.common.is_empty() && self.packages.is_empty() && self.unsupported.is_empty()
+    }
+
+    /// The flags which apply to the given package, in the order Cargo would apply them.
+    pub(crate) fn rustc_flags(&self, package: &str) -> CompilationModeFlags {
+        let mut flags = self.common.clone();
+        for overrides in [ALL_PACKAGES, package]
+            .iter()
+            .filter_map(|name| self.packages.get(*name))
+        {
+            for (compilation_mode, values) in overrides {
+                flags
+                    .entry(*compilation_mode)
+                    .or_default()
+                    .extend(values.iter().cloned());
+            }
+        }
+        flags
+    }
+}
+
+/// Later `-C` flags take precedence so these are able to override the defaults
+/// `rules_rust` derives from the compilation mode.
+fn rustc_flags(profile: &Profile, table: &toml::Value) -> Vec<String> {
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+
+    let mut flags = Vec::new();
+    if let Some(opt_level) = &profile.opt_level {
+        let opt_level = match opt_level {
+            toml::Value::String(level) => level.clone(),
+            level => level.to_string(),
+        };
+        flags.push(format!("-Copt-level={}", opt_level));
+    }
+    if let Some(debug) = profile.debug.as_ref().and(table.get("debug")) {
+        flags.push(format!("-Cdebuginfo={}", debuginfo(debug)));
+    }
+    if let Some(split_debuginfo) = &profile.split_debuginfo {
+        flags.push(format!("-Csplit-debuginfo={}", split_debuginfo));
+    }
+    if let Some(strip) = &profile.strip {
+        let strip = match strip {
+            StripSetting::None => "none",
+            StripSetting::Debuginfo => "debuginfo",
+            StripSetting::Symbols => "symbols",
+        };
+        flags.push(format!("-Cstrip={}", strip));
+    }
+    if let Some(debug_assertions) = profile.debug_assertions {
+        flags.push(format!("-Cdebug-assertions={}", on_off(debug_assertions)));
+    }
+    if let Some(overflow_checks) = profile.overflow_checks {
+        flags.push(format!("-Coverflow-checks={}", on_off(overflow_checks)));
+    }
+    if let Some(codegen_units) = profile.codegen_units {
+        flags.push(format!("-Ccodegen-units={}", codegen_units));
+    }
+    flags
+}
+
+/// The `-Cdebuginfo` level Cargo uses for a `debug` setting. `cargo_toml` parses
+/// `1` and `"limited"` the same as `"line-tables-only"` but they're distinct levels.
+fn debuginfo(debug: &toml::Value) -> String {
+    match debug {
+        toml::Value::Boolean(enabled) => if *enabled { "2" } else { "0" }.to_owned(),
+        toml::Value::String(level) => match level.as_str() {
+            "none" => "0".to_owned(),
+            "limited" => "1".to_owned(),
+            "full" => "2".to_owned(),
+            level => level.to_owned(),
+        },
+        level => level.to_string(),
+    }
+}
+
+/// LTO and the panic strategy must agree between every crate in a binary, including
+/// those outside of the Cargo workspace, so they're left to the Rust toolchain.
+fn unsupported_settings(profile_name: &str, profile: &Profile) -> Vec<String> {
+    let mut settings = Vec::new();
+    if profile
+        .lto
+        .as_ref()
+        .is_some_and(|lto| *lto != LtoSetting::ThinLocal)
+    {
+        settings.push(format!("profile.{}.lto", profile_name));
+    }
+    if profile
+        .panic
+        .as_ref()
+        .is_some_and(|panic| panic != "unwind")
+    {
+        settings.push(format!("profile.{}.panic", profile_name));
+    }
+    if profile.build_override.is_some() {
+        settings.push(format!("profile.{}.build-override", profile_name));
+    }
+    settings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profiles(manifest: &str) -> toml::Table {
+        let mut manifest: toml::Table = toml::from_str(manifest).unwrap();
+        match manifest.remove("profile") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            _ => toml::Table::new(),
+        }
+    }
+
+    #[test]
+    fn cargo_profiles_from_manifest() {
+        let profiles = CargoProfiles::new(&profiles(
+            r#"
+            [package]
+            name = "root"
+            version = "0.1.0"
+
+            [profile.dev]
+            debug = "line-tables-only"
+
+            [profile.dev.package.image]
+            opt-level = 3
+
+            [profile.dev.package."*"]
+            opt-level = "s"
+
+            [profile.release]
+            codegen-units = 1
+            lto = true
+            panic = "abort"
+            "#,
+        ))
+        .unwrap();
+
+        let dev = vec!["-Cdebuginfo=line-tables-only".to_owned()];
+        assert_eq!(
+            profiles.rustc_flags("image"),
+            BTreeMap::from([
+                (
+                    CompilationMode::Dbg,
+                    vec![
+                        "-Cdebuginfo=line-tables-only".to_owned(),
+                        "-Copt-level=s".to_owned(),
+                        "-Copt-level=3".to_owned(),
+                    ]
+                ),
+                (
+                    CompilationMode::Fastbuild,
+                    vec![
+                        "-Cdebuginfo=line-tables-only".to_owned(),
+                        "-Copt-level=s".to_owned(),
+                        "-Copt-level=3".to_owned(),
+                    ]
+                ),
+                (CompilationMode::Opt, vec!["-Ccodegen-units=1".to_owned()]),
+            ])
+        );
+        assert_eq!(
+            profiles.rustc_flags("png")[&CompilationMode::Dbg],
+            [dev, vec!["-Copt-level=s".to_owned()]].concat()
+        );
+        assert_eq!(
+            profiles.unsupported,
+            BTreeSet::from([
+                "profile.release.lto".to_owned(),
+                "profile.release.panic".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn cargo_profiles_debuginfo_levels() {
+        let debuginfo = |debug: &str| {
+            CargoProfiles::new(&profiles(&format!(
+                r#"
+                [package]
+                name = "root"
+                version = "0.1.0"
+
+                [profile.release]
+                debug = {}
+                "#,
+                debug
+            )))
+            .unwrap()
+            .rustc_flags("root")
+            .remove(&CompilationMode::Opt)
+            .unwrap()
+        };
+
+        assert_eq!(debuginfo("false"), ["-Cdebuginfo=0"]);
+        assert_eq!(debuginfo("\"none\""), ["-Cdebuginfo=0"]);
+        assert_eq!(debuginfo("1"), ["-Cdebuginfo=1"]);
+        assert_eq!(debuginfo("\"limited\""), ["-Cdebuginfo=1"]);
+        assert_eq!(
+            debuginfo("\"line-tables-only\""),
+            ["-Cdebuginfo=line-tables-only"]
+        );
+        assert_eq!(
+            debuginfo("\"line-directives-only\""),
+            ["-Cdebuginfo=line-directives-only"]
+        );
+        assert_eq!(debuginfo("2"), ["-Cdebuginfo=2"]);
+        assert_eq!(debuginfo("true"), ["-Cdebuginfo=2"]);
+    }
+
+    #[test]
+    fn cargo_profiles_workspace_metadata_round_trip() {
+        let profiles = CargoProfiles::new(&profiles(
+            r#"
+            [package]
+            name = "root"
+            version = "0.1.0"
+
+            [profile.release.package.image]
+            opt-level = 3
+            "#,
+        ))
+        .unwrap();
+
+        // Profiles are written to the spliced manifest and read back from `cargo metadata`.
+        let value = toml::Value::try_from(&profiles).unwrap();
+        let json = serde_json::to_value(value).unwrap();
+        assert_eq!(
+            serde_json::from_value::<CargoProfiles>(json).unwrap(),
+            profiles
+        );
+    }
+
+    #[test]
+    fn cargo_profiles_empty() {
+        let profiles = CargoProfiles::new(&profiles(
+            r#"
+            [package]
+            name = "root"
+            version = "0.1.0"
+            "#,
+        ))
+        .unwrap();
+
+        assert!(profiles.is_empty());
+        assert!(profiles.rustc_flags("image").is_empty());
+    }
+}
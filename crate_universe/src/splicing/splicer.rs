@@ -11,11 +11,11 @@ use tracing::debug;
 
 use crate::config::CrateId;
 use crate::metadata::discover_workspaces;
-use crate::splicing::{SplicedManifest, SplicingManifest};
+use crate::splicing::{LocalOverrides, SplicedManifest, SplicingManifest};
 use crate::utils::starlark::Label;
 use crate::utils::symlink::{remove_symlink, symlink};
 
-use super::{read_manifest, DirectPackageManifest, WorkspaceMetadata};
+use super::{read_manifest, read_manifest_profiles, DirectPackageManifest, WorkspaceMetadata};
 
 /// The core splicer implementation. Each style of Bazel workspace should be represented
 /// here and a splicing implementation defined.
@@ -116,22 +116,43 @@ impl<'a> SplicerKind<'a> {
 
     /// Performs splicing based on the current variant.
     #[tracing::instrument(skip_all)]
-    pub(crate) fn splice(&self, workspace_dir: &Utf8Path) -> Result<SplicedManifest> {
+    pub(crate) fn splice(
+        &self,
+        workspace_dir: &Utf8Path,
+        local_overrides: &LocalOverrides,
+    ) -> Result<SplicedManifest> {
         match self {
             SplicerKind::Workspace {
                 path,
                 manifest,
                 splicing_manifest,
-            } => Self::splice_workspace(workspace_dir, path, manifest, splicing_manifest),
+            } => Self::splice_workspace(
+                workspace_dir,
+                path,
+                manifest,
+                splicing_manifest,
+                local_overrides,
+            ),
             SplicerKind::Package {
                 path,
                 manifest,
                 splicing_manifest,
-            } => Self::splice_package(workspace_dir, path, manifest, splicing_manifest),
+            } => Self::splice_package(
+                workspace_dir,
+                path,
+                manifest,
+                splicing_manifest,
+                local_overrides,
+            ),
             SplicerKind::MultiPackage {
                 manifests,
                 splicing_manifest,
-            } => Self::splice_multi_package(workspace_dir, manifests, splicing_manifest),
+            } => Self::splice_multi_package(
+                workspace_dir,
+                manifests,
+                splicing_manifest,
+                local_overrides,
+            ),
         }
     }
 
@@ -142,6 +163,7 @@ impl<'a> SplicerKind<'a> {
         path: &&Utf8PathBuf,
         manifest: &&Manifest,
         splicing_manifest: &&SplicingManifest,
+        local_overrides: &LocalOverrides,
     ) -> Result<SplicedManifest> {
         let mut manifest = (*manifest).clone();
         let manifest_dir = path
@@ -167,9 +189,16 @@ impl<'a> SplicerKind<'a> {
         let member_manifests = BTreeMap::from([(*path, String::new())]);
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, member_manifests)?;
+        let workspace_metadata = WorkspaceMetadata::new(
+            splicing_manifest,
+            member_manifests,
+            &read_manifest_profiles(path)?,
+        )?;
         workspace_metadata.inject_into(&mut manifest)?;
 
+        // Redirect any locally overridden crates to their checkouts
+        Self::inject_local_overrides(&mut manifest, local_overrides)?;
+
         // Write the root manifest
         write_root_manifest(root_manifest_path.as_std_path(), manifest)?;
 
@@ -183,6 +212,7 @@ impl<'a> SplicerKind<'a> {
         path: &&Utf8PathBuf,
         manifest: &&Manifest,
         splicing_manifest: &&SplicingManifest,
+        local_overrides: &LocalOverrides,
     ) -> Result<SplicedManifest> {
         let manifest_dir = path
             .parent()
@@ -214,9 +244,16 @@ impl<'a> SplicerKind<'a> {
         let member_manifests = BTreeMap::from([(*path, String::new())]);
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, member_manifests)?;
+        let workspace_metadata = WorkspaceMetadata::new(
+            splicing_manifest,
+            member_manifests,
+            &read_manifest_profiles(path)?,
+        )?;
         workspace_metadata.inject_into(&mut manifest)?;
 
+        // Redirect any locally overridden crates to their checkouts
+        Self::inject_local_overrides(&mut manifest, local_overrides)?;
+
         // Write the root manifest
         write_root_manifest(root_manifest_path.as_std_path(), manifest)?;
 
@@ -229,6 +266,7 @@ impl<'a> SplicerKind<'a> {
         workspace_dir: &Utf8Path,
         manifests: &&BTreeMap<Utf8PathBuf, Manifest>,
         splicing_manifest: &&SplicingManifest,
+        local_overrides: &LocalOverrides,
     ) -> Result<SplicedManifest> {
         let mut manifest = default_cargo_workspace_manifest(&splicing_manifest.resolver_version);
 
@@ -252,7 +290,8 @@ impl<'a> SplicerKind<'a> {
         }
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, installations)?;
+        let workspace_metadata =
+            WorkspaceMetadata::new(splicing_manifest, installations, &toml::Table::new())?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Add any additional depeendencies to the root package
@@ -260,6 +299,9 @@ impl<'a> SplicerKind<'a> {
             Self::inject_direct_packages(&mut manifest, &splicing_manifest.direct_packages)?;
         }
 
+        // Redirect any locally overridden crates to their checkouts
+        Self::inject_local_overrides(&mut manifest, local_overrides)?;
+
         // Write the root manifest
         let root_manifest_path = workspace_dir.join("Cargo.toml");
         write_root_manifest(root_manifest_path.as_std_path(), manifest)?;
@@ -465,12 +507,26 @@ impl<'a> SplicerKind<'a> {
 
         Ok(())
     }
+
+    fn inject_local_overrides(
+        manifest: &mut Manifest,
+        local_overrides: &LocalOverrides,
+    ) -> Result<()> {
+        if local_overrides.is_empty() {
+            return Ok(());
+        }
+
+        local_overrides.validate()?;
+        Self::inject_patches(manifest, &local_overrides.patches())
+            .context("Local overrides conflict with existing `[patch]` entries")
+    }
 }
 
 pub(crate) struct Splicer {
     workspace_dir: Utf8PathBuf,
     manifests: BTreeMap<Utf8PathBuf, Manifest>,
     splicing_manifest: SplicingManifest,
+    local_overrides: LocalOverrides,
 }
 
 impl Splicer {
@@ -493,12 +549,21 @@ impl Splicer {
             workspace_dir,
             manifests,
             splicing_manifest,
+            local_overrides: LocalOverrides::default(),
         })
     }
 
+    /// Use local checkouts of crates in place of their crates.io releases. These are
+    /// only applied to the spliced workspace and never to the spliced-from manifests.
+    pub(crate) fn with_local_overrides(mut self, local_overrides: LocalOverrides) -> Self {
+        self.local_overrides = local_overrides;
+        self
+    }
+
     /// Build a new workspace root
     pub(crate) fn splice_workspace(&self) -> Result<SplicedManifest> {
-        SplicerKind::new(&self.manifests, &self.splicing_manifest)?.splice(&self.workspace_dir)
+        SplicerKind::new(&self.manifests, &self.splicing_manifest)?
+            .splice(&self.workspace_dir, &self.local_overrides)
     }
 }
 const DEFAULT_SPLICING_PACKAGE_NAME: &str = "direct-cargo-bazel-deps";
@@ -680,8 +745,11 @@ mod test {
 
     /// Get cargo and rustc binaries the Cargo way
     #[cfg(feature = "cargo")]
-    fn get_cargo_and_rustc_paths() -> (PathBuf, PathBuf) {
-        (PathBuf::from("cargo"), PathBuf::from("rustc"))
+    fn get_cargo_and_rustc_paths() -> (std::path::PathBuf, std::path::PathBuf) {
+        (
+            std::path::PathBuf::from("cargo"),
+            std::path::PathBuf::from("rustc"),
+        )
     }
 
     fn cargo() -> Cargo {
@@ -1251,6 +1319,41 @@ mod test {
         cargo_lock::Lockfile::load(workspace_root.as_ref().join("Cargo.lock")).unwrap();
     }
 
+    #[test]
+    fn splice_package_with_local_overrides() {
+        let (splicing_manifest, cache_dir) = mock_splicing_manifest_with_package();
+        let original_manifest =
+            fs::read_to_string(cache_dir.as_ref().join("root_pkg").join("Cargo.toml")).unwrap();
+
+        // Create a local checkout to redirect to
+        let checkout_dir = tempfile::tempdir().unwrap();
+        mock_cargo_toml(checkout_dir.as_ref().join("Cargo.toml"), "syn");
+        let local_overrides =
+            LocalOverrides::from_str(&format!("syn={}", checkout_dir.as_ref().display())).unwrap();
+
+        // Splice the workspace
+        let workspace_root = tempfile::tempdir().unwrap();
+        let workspace_manifest =
+            Splicer::new(tempdir_utf8pathbuf(&workspace_root), splicing_manifest)
+                .unwrap()
+                .with_local_overrides(local_overrides.clone())
+                .splice_workspace()
+                .unwrap();
+
+        // Ensure the spliced manifest is patched
+        let cargo_manifest = cargo_toml::Manifest::from_str(
+            &fs::read_to_string(workspace_manifest.as_path_buf()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(local_overrides.patches(), cargo_manifest.patch);
+
+        // Ensure the spliced-from manifest was not changed
+        assert_eq!(
+            original_manifest,
+            fs::read_to_string(cache_dir.as_ref().join("root_pkg").join("Cargo.toml")).unwrap()
+        );
+    }
+
     #[test]
     fn splice_multi_package() {
         let (splicing_manifest, _cache_dir) = mock_splicing_manifest_with_multi_package();
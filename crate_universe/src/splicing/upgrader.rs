@@ -0,0 +1,526 @@
+//! Rewriting the version requirements of direct dependencies for
+//! [CargoUpdateRequest::Upgrade](crate::metadata::CargoUpdateRequest::Upgrade).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use cargo_toml::{DepsSet, Manifest};
+use semver::{Version, VersionReq};
+use toml_edit::{DocumentMut, Value};
+use tracing::debug;
+
+use crate::config::VersionResolution;
+use crate::metadata::{Cargo, CargoUpdateRequest};
+use crate::splicing::crate_index_lookup::IndexVersion;
+use crate::splicing::{
+    generate_lockfile, load_crate_indexes, SplicedManifest, Splicer, SplicingManifest,
+};
+
+/// A dependency requirement which was rewritten in one of the spliced manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UpgradedDependency {
+    /// The manifest which was updated.
+    pub(crate) manifest: Utf8PathBuf,
+
+    /// The name of the package the dependency refers to.
+    pub(crate) package: String,
+
+    /// The requirement before the upgrade.
+    pub(crate) old_req: String,
+
+    /// The requirement after the upgrade.
+    pub(crate) new_req: String,
+}
+
+impl fmt::Display for UpgradedDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {} -> {}",
+            self.manifest, self.package, self.old_req, self.new_req
+        )
+    }
+}
+
+/// A dependency declared with a version requirement in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DeclaredDependency {
+    /// The path of the table the dependency is declared in (e.g. `["target", "cfg(unix)", "dependencies"]`).
+    table: Vec<String>,
+
+    /// The key the dependency is declared under.
+    key: String,
+
+    /// The name of the package the dependency refers to.
+    package: String,
+
+    /// The declared version requirement.
+    req: String,
+}
+
+/// Apply a [CargoUpdateRequest::Upgrade] to the manifests in `splicing_manifest`, rewriting
+/// them in place. Other requests are ignored.
+///
+/// This splices and resolves the workspace in a scratch directory to populate the crate indexes,
+/// so splicing is expected to be repeated afterwards to pick up the new requirements.
+pub(crate) fn apply_upgrade_request(
+    cargo: &Cargo,
+    splicing_manifest: &SplicingManifest,
+    existing_lock: &Option<PathBuf>,
+    update_request: &Option<CargoUpdateRequest>,
+) -> Result<Vec<UpgradedDependency>> {
+    let Some(CargoUpdateRequest::Upgrade {
+        packages,
+        incompatible,
+    }) = update_request
+    else {
+        return Ok(Vec::new());
+    };
+
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let temp_dir_path = Utf8PathBuf::from_path_buf(temp_dir.as_ref().to_path_buf())
+        .unwrap_or_else(|path| panic!("Temporary directory wasn't valid UTF-8: {:?}", path));
+
+    let spliced_manifest = Splicer::new(temp_dir_path, splicing_manifest.clone())?
+        .splice_workspace()
+        .context("Failed to splice workspace for upgrade")?;
+    let lockfile = generate_lockfile(
+        &spliced_manifest,
+        existing_lock,
+        cargo.clone(),
+        update_request,
+        VersionResolution::Maximal,
+    )?;
+
+    let upgrades = upgrade_manifests(
+        cargo,
+        splicing_manifest,
+        &spliced_manifest,
+        &lockfile,
+        packages,
+        *incompatible,
+    )?;
+    for upgrade in &upgrades {
+        tracing::info!("Upgraded {upgrade}");
+    }
+
+    Ok(upgrades)
+}
+
+/// Rewrite the version requirements of the direct dependencies in every manifest that was
+/// spliced together to the newest versions available in their registries.
+///
+/// `lockfile` is expected to be freshly generated for `spliced_manifest` so that the crate
+/// indexes of all direct dependencies are available locally. Requirements of dependencies that
+/// were provided directly to the repository rules (as opposed to in a `Cargo.toml`) can't be
+/// rewritten and are left untouched.
+pub(crate) fn upgrade_manifests(
+    cargo: &Cargo,
+    splicing_manifest: &SplicingManifest,
+    spliced_manifest: &SplicedManifest,
+    lockfile: &cargo_lock::Lockfile,
+    packages: &BTreeSet<String>,
+    incompatible: bool,
+) -> Result<Vec<UpgradedDependency>> {
+    let crate_indexes = load_crate_indexes(cargo, lockfile, spliced_manifest.as_path_buf())?;
+
+    // The registries each package in the lockfile is sourced from.
+    let mut package_sources: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for pkg in &lockfile.packages {
+        if let Some(source) = pkg.source.as_ref().filter(|source| source.is_registry()) {
+            package_sources
+                .entry(pkg.name.as_str())
+                .or_default()
+                .insert(source.url().to_string());
+        }
+    }
+
+    let mut upgrades = Vec::new();
+    for manifest_path in splicing_manifest.manifests.keys() {
+        let content = fs::read_to_string(manifest_path)
+            .with_context(|| format!("Failed to read manifest {manifest_path}"))?;
+        let manifest = Manifest::from_str(&content)
+            .with_context(|| format!("Failed to parse manifest {manifest_path}"))?;
+
+        let mut new_content = content.clone();
+        for dep in declared_dependencies(&manifest) {
+            if !packages.is_empty() && !packages.contains(&dep.package) {
+                continue;
+            }
+
+            let Some(sources) = package_sources.get(dep.package.as_str()) else {
+                debug!("Skipping {}: not sourced from a registry", dep.package);
+                continue;
+            };
+
+            let mut versions = Vec::new();
+            for source in sources {
+                if let Some(index) = crate_indexes.get(source) {
+                    versions.extend(index.versions(&dep.package)?.unwrap_or_default());
+                }
+            }
+
+            let Some(new_req) = select_upgrade(&dep.req, &versions, incompatible) else {
+                continue;
+            };
+
+            match rewrite_requirement(&new_content, &dep, &new_req) {
+                Some(rewritten) => new_content = rewritten,
+                None => {
+                    tracing::warn!(
+                        "Unable to locate the requirement for `{}` in {}, it will not be upgraded",
+                        dep.key,
+                        manifest_path
+                    );
+                    continue;
+                }
+            }
+
+            upgrades.push(UpgradedDependency {
+                manifest: manifest_path.clone(),
+                package: dep.package,
+                old_req: dep.req,
+                new_req,
+            });
+        }
+
+        if new_content != content {
+            fs::write(manifest_path, new_content)
+                .with_context(|| format!("Failed to write manifest {manifest_path}"))?;
+        }
+    }
+
+    Ok(upgrades)
+}
+
+/// Collect all registry dependencies with explicit version requirements from a manifest.
+fn declared_dependencies(manifest: &Manifest) -> Vec<DeclaredDependency> {
+    fn collect(table: Vec<String>, deps: &DepsSet, output: &mut Vec<DeclaredDependency>) {
+        for (key, dep) in deps {
+            if let Some(detail) = dep.detail() {
+                if detail.path.is_some() || detail.git.is_some() || detail.version.is_none() {
+                    continue;
+                }
+            }
+            let Ok(req) = dep.try_req() else {
+                // Inherited from the workspace
+                continue;
+            };
+            output.push(DeclaredDependency {
+                table: table.clone(),
+                key: key.clone(),
+                package: dep.package().unwrap_or(key).to_owned(),
+                req: req.to_owned(),
+            });
+        }
+    }
+
+    let mut output = Vec::new();
+    let tables = [
+        ("dependencies", &manifest.dependencies),
+        ("dev-dependencies", &manifest.dev_dependencies),
+        ("build-dependencies", &manifest.build_dependencies),
+    ];
+    for (name, deps) in tables {
+        collect(vec![name.to_owned()], deps, &mut output);
+    }
+    for (cfg, target) in &manifest.target {
+        let tables = [
+            ("dependencies", &target.dependencies),
+            ("dev-dependencies", &target.dev_dependencies),
+            ("build-dependencies", &target.build_dependencies),
+        ];
+        for (name, deps) in tables {
+            collect(
+                vec!["target".to_owned(), cfg.clone(), name.to_owned()],
+                deps,
+                &mut output,
+            );
+        }
+    }
+    if let Some(workspace) = &manifest.workspace {
+        collect(
+            vec!["workspace".to_owned(), "dependencies".to_owned()],
+            &workspace.dependencies,
+            &mut output,
+        );
+    }
+
+    output
+}
+
+/// Determine the new requirement for a dependency, if it should be upgraded.
+///
+/// Only caret (`1.2`, `^1.2`) and tilde (`~1.2`) requirements are upgraded. Exact, ranged
+/// and wildcard requirements are assumed to be intentional pins.
+fn select_upgrade(req: &str, versions: &[IndexVersion], incompatible: bool) -> Option<String> {
+    let trimmed = req.trim();
+    let (operator, base) = match trimmed.strip_prefix('^') {
+        Some(base) => ("^", base),
+        None => match trimmed.strip_prefix('~') {
+            Some(base) => ("~", base),
+            None => ("", trimmed),
+        },
+    };
+    if base.is_empty() || !base.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    let parsed_req = VersionReq::parse(trimmed).ok()?;
+    let minimum = minimum_version(base)?;
+
+    let newest = versions
+        .iter()
+        .filter(|v| !v.yanked && v.version.pre.is_empty())
+        .map(|v| &v.version)
+        .filter(|v| incompatible || parsed_req.matches(v))
+        .max()?;
+
+    if *newest <= minimum {
+        return None;
+    }
+
+    let new_req = format!(
+        "{operator}{}.{}.{}",
+        newest.major, newest.minor, newest.patch
+    );
+    (new_req != trimmed).then_some(new_req)
+}
+
+/// The lowest version matched by a partial version (e.g. `1.2` is `1.2.0`).
+fn minimum_version(base: &str) -> Option<Version> {
+    let mut parts = base.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Version::new(major, minor, patch))
+}
+
+/// Rewrite the requirement of `dep` in the text of a manifest, preserving all other formatting.
+///
+/// Supports the forms `key = "req"`, `key = { version = "req", ... }`, `key.version = "req"`
+/// and `[table.key]` sections containing `version = "req"`.
+fn rewrite_requirement(content: &str, dep: &DeclaredDependency, new_req: &str) -> Option<String> {
+    let mut document = content.parse::<DocumentMut>().ok()?;
+
+    let mut item = document.as_item_mut();
+    for segment in &dep.table {
+        item = item.get_mut(segment)?;
+    }
+    let item = item.get_mut(&dep.key)?;
+    let value = match item.as_value_mut() {
+        Some(value) if value.is_str() => value,
+        _ => item.get_mut("version")?.as_value_mut()?,
+    };
+    if value.as_str() != Some(dep.req.as_str()) {
+        return None;
+    }
+
+    // Keep the quoting style of the existing requirement.
+    let is_literal = match value {
+        Value::String(formatted) => formatted
+            .as_repr()
+            .and_then(|repr| repr.as_raw().as_str())
+            .is_some_and(|raw| raw.starts_with('\'')),
+        _ => false,
+    };
+    let raw = if is_literal {
+        format!("'{new_req}'")
+    } else {
+        format!("\"{new_req}\"")
+    };
+    let decor = value.decor().clone();
+    *value = raw.parse().ok()?;
+    *value.decor_mut() = decor;
+
+    Some(document.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn versions(versions: &[(&str, bool)]) -> Vec<IndexVersion> {
+        versions
+            .iter()
+            .map(|(version, yanked)| IndexVersion {
+                version: Version::parse(version).unwrap(),
+                yanked: *yanked,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_compatible_upgrade() {
+        let available = versions(&[
+            ("1.0.1", false),
+            ("1.2.0", false),
+            ("1.3.0", true),
+            ("1.4.0-rc.1", false),
+            ("2.0.0", false),
+        ]);
+
+        assert_eq!(
+            select_upgrade("1.0", &available, false),
+            Some("1.2.0".to_owned())
+        );
+        assert_eq!(
+            select_upgrade("^1", &available, false),
+            Some("^1.2.0".to_owned())
+        );
+        assert_eq!(
+            select_upgrade("~1.0", &available, false),
+            Some("~1.0.1".to_owned())
+        );
+        assert_eq!(select_upgrade("1.2.0", &available, false), None);
+    }
+
+    #[test]
+    fn select_incompatible_upgrade() {
+        let available = versions(&[("1.0.1", false), ("2.1.0", false)]);
+
+        assert_eq!(
+            select_upgrade("1.0", &available, true),
+            Some("2.1.0".to_owned())
+        );
+        assert_eq!(
+            select_upgrade("~1.0.1", &available, true),
+            Some("~2.1.0".to_owned())
+        );
+    }
+
+    #[test]
+    fn select_upgrade_skips_pins() {
+        let available = versions(&[("1.0.1", false), ("2.1.0", false)]);
+
+        for req in ["=1.0.1", ">=1, <2", "*", "1.*"] {
+            assert_eq!(select_upgrade(req, &available, true), None, "{req}");
+        }
+    }
+
+    #[test]
+    fn declared_dependencies_from_manifest() {
+        let manifest = Manifest::from_str(indoc::indoc! {r#"
+            [package]
+            name = "pkg"
+            version = "0.1.0"
+
+            [dependencies]
+            anyhow = "1.0"
+            local = { path = "../local" }
+            renamed = { package = "serde", version = "1.0.100" }
+            inherited = { workspace = true }
+
+            [target.'cfg(unix)'.dev-dependencies]
+            libc = "0.2"
+        "#})
+        .unwrap();
+
+        assert_eq!(
+            declared_dependencies(&manifest),
+            vec![
+                DeclaredDependency {
+                    table: vec!["dependencies".to_owned()],
+                    key: "anyhow".to_owned(),
+                    package: "anyhow".to_owned(),
+                    req: "1.0".to_owned(),
+                },
+                DeclaredDependency {
+                    table: vec!["dependencies".to_owned()],
+                    key: "renamed".to_owned(),
+                    package: "serde".to_owned(),
+                    req: "1.0.100".to_owned(),
+                },
+                DeclaredDependency {
+                    table: vec![
+                        "target".to_owned(),
+                        "cfg(unix)".to_owned(),
+                        "dev-dependencies".to_owned()
+                    ],
+                    key: "libc".to_owned(),
+                    package: "libc".to_owned(),
+                    req: "0.2".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrite_requirements_in_place() {
+        let content = indoc::indoc! {r#"
+            [package]
+            name = "pkg"
+            version = "1.0"
+
+            [dependencies]
+            # Keep this comment: anyhow = "1.0"
+            anyhow = "1.0"   # trailing comment
+            serde = { version = "1.0", features = ["derive"] }
+            tokio.version = "1.0"
+
+            [dependencies.log]
+            features = ["std"]
+            version = '0.4'
+
+            [target.'cfg(unix)'.dependencies]
+            libc = "0.2"
+        "#};
+
+        let dep = |table: &[&str], key: &str, req: &str| DeclaredDependency {
+            table: table.iter().map(|s| s.to_string()).collect(),
+            key: key.to_owned(),
+            package: key.to_owned(),
+            req: req.to_owned(),
+        };
+
+        let content =
+            rewrite_requirement(content, &dep(&["dependencies"], "anyhow", "1.0"), "1.0.86")
+                .unwrap();
+        let content =
+            rewrite_requirement(&content, &dep(&["dependencies"], "serde", "1.0"), "1.0.200")
+                .unwrap();
+        let content =
+            rewrite_requirement(&content, &dep(&["dependencies"], "tokio", "1.0"), "1.38.0")
+                .unwrap();
+        let content =
+            rewrite_requirement(&content, &dep(&["dependencies"], "log", "0.4"), "0.4.22").unwrap();
+        let content = rewrite_requirement(
+            &content,
+            &dep(&["target", "cfg(unix)", "dependencies"], "libc", "0.2"),
+            "0.2.155",
+        )
+        .unwrap();
+        assert!(
+            rewrite_requirement(&content, &dep(&["dependencies"], "missing", "1"), "2").is_none()
+        );
+
+        assert_eq!(
+            content,
+            indoc::indoc! {r#"
+                [package]
+                name = "pkg"
+                version = "1.0"
+
+                [dependencies]
+                # Keep this comment: anyhow = "1.0"
+                anyhow = "1.0.86"   # trailing comment
+                serde = { version = "1.0.200", features = ["derive"] }
+                tokio.version = "1.38.0"
+
+                [dependencies.log]
+                features = ["std"]
+                version = '0.4.22'
+
+                [target.'cfg(unix)'.dependencies]
+                libc = "0.2.155"
+            "#}
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Detection of resolved crates whose versions have been yanked from their registry.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::config::CrateId;
+
+use super::crate_index_lookup::CrateIndexLookup;
+
+/// Yanked crates keyed to the `{name} {version}` of every package which directly depends on them.
+pub(crate) type YankedCrates = BTreeMap<CrateId, BTreeSet<String>>;
+
+/// Find all registry packages in `lockfile` which `is_yanked` reports as yanked.
+fn find_yanked(
+    lockfile: &cargo_lock::Lockfile,
+    mut is_yanked: impl FnMut(&cargo_lock::Package) -> Result<bool>,
+) -> Result<YankedCrates> {
+    let mut yanked = YankedCrates::new();
+    for pkg in lockfile.packages.iter().filter(|pkg| {
+        pkg.source
+            .as_ref()
+            .is_some_and(|source| source.is_registry())
+    }) {
+        if is_yanked(pkg)? {
+            yanked.insert(
+                CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone()),
+                BTreeSet::new(),
+            );
+        }
+    }
+
+    if yanked.is_empty() {
+        return Ok(yanked);
+    }
+
+    for pkg in &lockfile.packages {
+        for dep in &pkg.dependencies {
+            let dep_id = CrateId::new(dep.name.as_str().to_owned(), dep.version.clone());
+            if let Some(dependents) = yanked.get_mut(&dep_id) {
+                dependents.insert(format!("{} {}", pkg.name, pkg.version));
+            }
+        }
+    }
+
+    Ok(yanked)
+}
+
+/// Find all registry packages in `lockfile` whose versions are yanked in `crate_indexes`.
+///
+/// Sparse indexes are only read from the local cache, which cargo populates while resolving the
+/// lockfile. Packages missing from an index are assumed not to be yanked.
+pub(crate) fn find_yanked_crates(
+    lockfile: &cargo_lock::Lockfile,
+    crate_indexes: &BTreeMap<String, CrateIndexLookup>,
+) -> Result<YankedCrates> {
+    find_yanked(lockfile, |pkg| {
+        let source_id = pkg.source.as_ref().unwrap();
+        let lookup = crate_indexes
+            .get(&source_id.url().to_string())
+            .ok_or_else(|| anyhow!("Couldn't find crate_index data for SourceID {source_id:?}"))?;
+        let versions = lookup.versions(pkg.name.as_str())?.unwrap_or_default();
+        Ok(versions
+            .iter()
+            .any(|version| version.version == pkg.version && version.yanked))
+    })
+}
+
+fn yanked_message(crate_id: &CrateId, dependents: &BTreeSet<String>) -> String {
+    let mut message = format!("`{crate_id}` has been yanked from its registry.");
+    if !dependents.is_empty() {
+        message.push_str(&format!(
+            " It is required by: {}",
+            dependents
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    message
+}
+
+/// Returns a warning for every yanked crate, or an error listing all of them if `deny` is set.
+pub(crate) fn check_yanked_crates(yanked: &YankedCrates, deny: bool) -> Result<Vec<String>> {
+    let messages: Vec<String> = yanked
+        .iter()
+        .map(|(crate_id, dependents)| yanked_message(crate_id, dependents))
+        .collect();
+
+    if deny && !messages.is_empty() {
+        bail!(
+            "The following crates have been yanked:\n{}",
+            messages
+                .iter()
+                .map(|message| format!("  {message}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use semver::Version;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "direct-cargo-bazel-deps"
+version = "0.0.1"
+dependencies = [
+ "log",
+ "tracing",
+]
+
+[[package]]
+name = "log"
+version = "0.4.21"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "90ed8c1e510134f979dbc4f070f87d4313098b704861a105fe34231c70a3901c"
+
+[[package]]
+name = "tracing"
+version = "0.1.40"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "c3523ab5a71916ccf420eebdf5521fcef02141234bbc0b8a49f2fdc4544364ef"
+dependencies = [
+ "log",
+]
+"#;
+
+    #[test]
+    fn find_yanked_with_dependents() {
+        let lockfile = cargo_lock::Lockfile::from_str(LOCKFILE).unwrap();
+
+        let mut queried = Vec::new();
+        let yanked = find_yanked(&lockfile, |pkg| {
+            queried.push(pkg.name.as_str().to_owned());
+            Ok(pkg.name.as_str() == "log")
+        })
+        .unwrap();
+
+        // Only registry packages are looked up.
+        assert_eq!(queried, vec!["log".to_owned(), "tracing".to_owned()]);
+        assert_eq!(
+            yanked,
+            YankedCrates::from([(
+                CrateId::new("log".to_owned(), Version::new(0, 4, 21)),
+                BTreeSet::from([
+                    "direct-cargo-bazel-deps 0.0.1".to_owned(),
+                    "tracing 0.1.40".to_owned()
+                ]),
+            )])
+        );
+
+        assert_eq!(
+            check_yanked_crates(&yanked, false).unwrap(),
+            vec!["`log 0.4.21` has been yanked from its registry. It is required by: direct-cargo-bazel-deps 0.0.1, tracing 0.1.40".to_owned()]
+        );
+        assert!(check_yanked_crates(&yanked, true).is_err());
+    }
+
+    #[test]
+    fn no_yanked_crates() {
+        let lockfile = cargo_lock::Lockfile::from_str(LOCKFILE).unwrap();
+        let yanked = find_yanked(&lockfile, |_| Ok(false)).unwrap();
+        assert!(yanked.is_empty());
+        assert!(check_yanked_crates(&yanked, true).unwrap().is_empty());
+    }
+}
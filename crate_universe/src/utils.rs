@@ -1,5 +1,7 @@
 //! Common utilities
 
+pub(crate) mod compilation_mode;
+pub(crate) mod diff;
 pub(crate) mod starlark;
 pub(crate) mod symlink;
 pub(crate) mod target_triple;
@@ -0,0 +1,42 @@
+use std::fmt::{Display, Formatter, Result};
+
+use serde::{Deserialize, Serialize};
+
+/// A value of Bazel's `--compilation_mode` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CompilationMode {
+    Dbg,
+    Fastbuild,
+    Opt,
+}
+
+impl CompilationMode {
+    pub(crate) const ALL: [Self; 3] = [Self::Dbg, Self::Fastbuild, Self::Opt];
+
+    /// The Cargo profile whose settings best match builds in this mode.
+    pub(crate) fn cargo_profile(&self) -> &'static str {
+        match self {
+            Self::Dbg | Self::Fastbuild => "dev",
+            Self::Opt => "release",
+        }
+    }
+
+    /// The `config_setting` matching builds in this mode.
+    pub(crate) fn config_setting(&self) -> String {
+        format!(
+            "@rules_rust//crate_universe/private:compilation_mode_{}",
+            self
+        )
+    }
+}
+
+impl Display for CompilationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Dbg => write!(f, "dbg"),
+            Self::Fastbuild => write!(f, "fastbuild"),
+            Self::Opt => write!(f, "opt"),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! A minimal line based diff for reporting changes to generated files.
+
+use std::fmt::Write;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Compare two texts and render the differences in the
+/// [unified format](https://www.gnu.org/software/diffutils/manual/html_node/Unified-Format.html).
+///
+/// Returns `None` if the texts are identical.
+pub(crate) fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let script = edit_script(&old_lines, &new_lines);
+
+    let mut output = format!("--- {old_name}\n+++ {new_name}\n");
+    for (start, end) in hunks(&script) {
+        // Count the lines of each side which precede the hunk and are within it.
+        let count = |range: &[Line], kind: Line| {
+            range
+                .iter()
+                .filter(|line| **line == Line::Equal || **line == kind)
+                .count()
+        };
+        let old_before = count(&script[..start], Line::Delete);
+        let new_before = count(&script[..start], Line::Insert);
+        let old_len = count(&script[start..end], Line::Delete);
+        let new_len = count(&script[start..end], Line::Insert);
+
+        // Empty ranges refer to the line preceding them.
+        let old_start = if old_len == 0 {
+            old_before
+        } else {
+            old_before + 1
+        };
+        let new_start = if new_len == 0 {
+            new_before
+        } else {
+            new_before + 1
+        };
+        writeln!(
+            output,
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@"
+        )
+        .unwrap();
+
+        let (mut old_idx, mut new_idx) = (old_before, new_before);
+        for line in &script[start..end] {
+            match line {
+                Line::Equal => {
+                    writeln!(output, " {}", old_lines[old_idx]).unwrap();
+                    old_idx += 1;
+                    new_idx += 1;
+                }
+                Line::Delete => {
+                    writeln!(output, "-{}", old_lines[old_idx]).unwrap();
+                    old_idx += 1;
+                }
+                Line::Insert => {
+                    writeln!(output, "+{}", new_lines[new_idx]).unwrap();
+                    new_idx += 1;
+                }
+            }
+        }
+    }
+
+    Some(output)
+}
+
+/// Compute the shortest sequence of edits that turns `old` into `new` using the longest common
+/// subsequence of the lines between any common prefix and suffix.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Line> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // `lcs[i][j]` is the length of the longest common subsequence of `old_mid[i..]` and `new_mid[j..]`.
+    let width = new_mid.len() + 1;
+    let mut lcs = vec![0_u32; (old_mid.len() + 1) * width];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut script = vec![Line::Equal; prefix];
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() && j < new_mid.len() {
+        if old_mid[i] == new_mid[j] {
+            script.push(Line::Equal);
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            script.push(Line::Delete);
+            i += 1;
+        } else {
+            script.push(Line::Insert);
+            j += 1;
+        }
+    }
+    script.extend(std::iter::repeat_n(Line::Delete, old_mid.len() - i));
+    script.extend(std::iter::repeat_n(Line::Insert, new_mid.len() - j));
+    script.extend(std::iter::repeat_n(Line::Equal, suffix));
+    script
+}
+
+/// Group the changes of an edit script into `(start, end)` ranges including their context.
+fn hunks(script: &[Line]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (idx, _) in script
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| **line != Line::Equal)
+    {
+        let start = idx.saturating_sub(CONTEXT_LINES);
+        let end = (idx + 1 + CONTEXT_LINES).min(script.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "a", "b"), None);
+    }
+
+    #[test]
+    fn changed_line() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified_diff(old, new, "a/BUILD.bazel", "b/BUILD.bazel").unwrap(),
+            indoc::indoc! {"
+                --- a/BUILD.bazel
+                +++ b/BUILD.bazel
+                @@ -2,7 +2,7 @@
+                 2
+                 3
+                 4
+                -5
+                +five
+                 6
+                 7
+                 8
+            "}
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = (1..=20).map(|i| format!("{i}\n")).collect::<String>();
+        let new = (1..=20)
+            .filter(|i| *i != 19)
+            .map(|i| match i {
+                2 => "two\n".to_owned(),
+                i => format!("{i}\n"),
+            })
+            .collect::<String>();
+        assert_eq!(
+            unified_diff(&old, &new, "a", "b").unwrap(),
+            indoc::indoc! {"
+                --- a
+                +++ b
+                @@ -1,5 +1,5 @@
+                 1
+                -2
+                +two
+                 3
+                 4
+                 5
+                @@ -16,5 +16,4 @@
+                 16
+                 17
+                 18
+                -19
+                 20
+            "}
+        );
+    }
+
+    #[test]
+    fn new_file() {
+        assert_eq!(
+            unified_diff("", "a\nb\n", "a", "b").unwrap(),
+            indoc::indoc! {"
+                --- a
+                +++ b
+                @@ -0,0 +1,2 @@
+                +a
+                +b
+            "}
+        );
+    }
+}
@@ -3,6 +3,7 @@
 mod glob;
 mod label;
 mod select;
+mod select_compilation_mode;
 mod select_dict;
 mod select_list;
 mod select_scalar;
@@ -10,7 +11,7 @@ mod select_set;
 mod serialize;
 mod target_compatible_with;
 
-use std::collections::BTreeSet as Set;
+use std::collections::{BTreeMap, BTreeSet as Set};
 
 use serde::{Serialize, Serializer};
 use serde_starlark::{Error as StarlarkError, FunctionCall};
@@ -18,6 +19,7 @@ use serde_starlark::{Error as StarlarkError, FunctionCall};
 pub(crate) use glob::*;
 pub(crate) use label::*;
 pub(crate) use select::*;
+pub(crate) use select_compilation_mode::*;
 pub(crate) use select_dict::*;
 pub(crate) use select_list::*;
 pub(crate) use select_scalar::*;
@@ -42,6 +44,8 @@ pub(crate) enum Starlark {
     RustLibrary(RustLibrary),
     #[serde(serialize_with = "serialize::rust_binary")]
     RustBinary(RustBinary),
+    RustDoc(RustDoc),
+    CratesDocIndex(CratesDocIndex),
 
     #[serde(skip_serializing)]
     Verbatim(String),
@@ -88,6 +92,7 @@ pub(crate) struct Alias {
     pub(crate) name: String,
     pub(crate) actual: Label,
     pub(crate) tags: Set<String>,
+    pub(crate) visibility: Set<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -189,6 +194,27 @@ pub(crate) struct RustBinary {
     pub(crate) common: CommonAttrs,
 }
 
+#[derive(Serialize)]
+#[serde(rename = "rust_doc")]
+pub(crate) struct RustDoc {
+    pub(crate) name: String,
+    #[serde(rename = "crate")]
+    pub(crate) krate: Label,
+    #[serde(skip_serializing_if = "Set::is_empty")]
+    pub(crate) rustdoc_flags: Set<String>,
+    pub(crate) tags: Set<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "crates_doc_index")]
+pub(crate) struct CratesDocIndex {
+    pub(crate) name: String,
+    pub(crate) docs: BTreeMap<Label, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) links: BTreeMap<String, Set<String>>,
+    pub(crate) tags: Set<String>,
+}
+
 #[derive(Serialize)]
 pub(crate) struct CommonAttrs {
     #[serde(skip_serializing_if = "Data::is_empty")]
@@ -206,8 +232,8 @@ pub(crate) struct CommonAttrs {
     pub(crate) rustc_env: SelectDict<String, String>,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) rustc_env_files: SelectSet<String>,
-    #[serde(skip_serializing_if = "SelectList::is_empty")]
-    pub(crate) rustc_flags: SelectList<String>,
+    #[serde(skip_serializing_if = "SelectCompilationMode::is_empty")]
+    pub(crate) rustc_flags: SelectCompilationMode<String>,
     pub(crate) srcs: Glob,
     #[serde(skip_serializing_if = "Set::is_empty")]
     pub(crate) tags: Set<String>,
@@ -247,6 +273,9 @@ impl Serialize for Alias {
         //            "tag1",
         //            "tag2",
         //         ],
+        //         visibility = [
+        //            "@//package:__pkg__",
+        //         ],
         //     )
 
         #[derive(Serialize)]
@@ -254,6 +283,8 @@ impl Serialize for Alias {
             pub(crate) name: &'a String,
             pub(crate) actual: &'a Label,
             pub(crate) tags: &'a Set<String>,
+            #[serde(skip_serializing_if = "Set::is_empty")]
+            pub(crate) visibility: &'a Set<String>,
         }
 
         FunctionCall::new(
@@ -262,6 +293,7 @@ impl Serialize for Alias {
                 name: &self.name,
                 actual: &self.actual,
                 tags: &self.tags,
+                visibility: &self.visibility,
             },
         )
         .serialize(serializer)
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use serde::ser::{SerializeMap, SerializeTupleStruct, Serializer};
+use serde::Serialize;
+use serde_starlark::{FunctionCall, MULTILINE};
+
+use crate::select::SelectableValue;
+use crate::utils::compilation_mode::CompilationMode;
+use crate::utils::starlark::serialize::MultilineArray;
+use crate::utils::starlark::SelectList;
+
+/// A [SelectList] preceded by values which depend on the Bazel `compilation_mode`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SelectCompilationMode<T>
+where
+    T: SelectableValue,
+{
+    compilation_modes: BTreeMap<CompilationMode, Vec<T>>,
+    select_list: SelectList<T>,
+}
+
+impl<T> SelectCompilationMode<T>
+where
+    T: SelectableValue,
+{
+    pub(crate) fn new(
+        compilation_modes: BTreeMap<CompilationMode, Vec<T>>,
+        select_list: SelectList<T>,
+    ) -> Self {
+        Self {
+            compilation_modes,
+            select_list,
+        }
+    }
+
+    /// Determine whether or not the select should be serialized
+    pub(crate) fn is_empty(&self) -> bool {
+        self.compilation_modes.is_empty() && self.select_list.is_empty()
+    }
+}
+
+impl<T> Serialize for SelectCompilationMode<T>
+where
+    T: SelectableValue,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Output looks like:
+        //
+        //     select({
+        //         "@rules_rust//crate_universe/private:compilation_mode_opt": [
+        //             "value...",
+        //         ],
+        //         "//conditions:default": [],
+        //     }) + [
+        //         "common...",
+        //     ] + select({
+        //         "configuration": [
+        //             "value...",  # cfg(whatever)
+        //         ],
+        //         "//conditions:default": [],
+        //     })
+        //
+        // Where everything after the first select is the serialized SelectList,
+        // which is omitted entirely if it is empty.
+
+        if self.compilation_modes.is_empty() {
+            return self.select_list.serialize(serializer);
+        }
+
+        struct SelectInner<'a, T>(&'a BTreeMap<CompilationMode, Vec<T>>);
+
+        impl<T> Serialize for SelectInner<'_, T>
+        where
+            T: SelectableValue,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(MULTILINE))?;
+                for (compilation_mode, values) in self.0.iter() {
+                    map.serialize_entry(
+                        &compilation_mode.config_setting(),
+                        &MultilineArray(values),
+                    )?;
+                }
+                map.serialize_entry("//conditions:default", &[] as &[T])?;
+                map.end()
+            }
+        }
+
+        let mut plus = serializer.serialize_tuple_struct("+", MULTILINE)?;
+        plus.serialize_field(&FunctionCall::new(
+            "select",
+            [SelectInner(&self.compilation_modes)],
+        ))?;
+        if !self.select_list.is_empty() {
+            plus.serialize_field(&self.select_list)?;
+        }
+        plus.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use indoc::indoc;
+
+    use super::*;
+
+    use crate::select::Select;
+
+    #[test]
+    fn empty_compilation_modes() {
+        let select_list = SelectList::new(
+            Select::from_value(vec!["--cap-lints=allow".to_owned()]),
+            &BTreeMap::new(),
+        );
+
+        let expected = indoc! {r#"
+            [
+                "--cap-lints=allow",
+            ]
+        "#};
+
+        assert_eq!(
+            SelectCompilationMode::new(BTreeMap::new(), select_list)
+                .serialize(serde_starlark::Serializer)
+                .unwrap(),
+            expected,
+        );
+    }
+
+    #[test]
+    fn compilation_modes_precede_select_list() {
+        let mut select: Select<Vec<String>> =
+            Select::from_value(vec!["--cap-lints=allow".to_owned()]);
+        select.insert("cfg(unix)".to_owned(), Some("cfg(unix)".to_owned()));
+        let platforms = BTreeMap::from([(
+            "cfg(unix)".to_owned(),
+            BTreeSet::from(["//platforms:linux".to_owned()]),
+        )]);
+
+        let compilation_modes =
+            BTreeMap::from([(CompilationMode::Opt, vec!["-Ccodegen-units=1".to_owned()])]);
+
+        let expected = indoc! {r#"
+            select({
+                "@rules_rust//crate_universe/private:compilation_mode_opt": [
+                    "-Ccodegen-units=1",
+                ],
+                "//conditions:default": [],
+            }) + [
+                "--cap-lints=allow",
+            ] + select({
+                "//platforms:linux": [
+                    "cfg(unix)",  # cfg(unix)
+                ],
+                "//conditions:default": [],
+            })
+        "#};
+
+        assert_eq!(
+            SelectCompilationMode::new(compilation_modes, SelectList::new(select, &platforms))
+                .serialize(serde_starlark::Serializer)
+                .unwrap(),
+            expected,
+        );
+    }
+}
@@ -106,7 +106,7 @@ fn run(repository_name: &str, manifests: HashMap<String, String>, lockfile: &str
     .unwrap();
 
     splice(SpliceOptions {
-        splicing_manifest,
+        splicing_manifest: Some(splicing_manifest),
         cargo_lockfile: Some(runfiles::rlocation!(runfiles, lockfile).unwrap()),
         repin: None,
         workspace_dir: None,
@@ -116,6 +116,7 @@ fn run(repository_name: &str, manifests: HashMap<String, String>, lockfile: &str
         config,
         cargo,
         rustc,
+        local_overrides: None,
     })
     .unwrap();
 
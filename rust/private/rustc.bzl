@@ -806,6 +806,15 @@ def _will_emit_object_file(emit):
 def _remove_codegen_units(flag):
     return None if flag.startswith("-Ccodegen-units") else flag
 
+def _is_incremental_compilation_enabled(ctx, toolchain, emit):
+    """Whether `Rustc` actions compile incrementally, see `@rules_rust//rust/settings:experimental_incremental_compilation`."""
+
+    # Like Cargo, optimized builds aren't incremental. Incremental compilation splits crates into
+    # many codegen units, which can't be emitted into a single object file.
+    return toolchain._experimental_incremental_compilation and \
+           ctx.var["COMPILATION_MODE"] != "opt" and \
+           not _will_emit_object_file(emit)
+
 def construct_arguments(
         *,
         ctx,
@@ -1357,13 +1366,64 @@ def rustc_compile_action(
             action_outputs.append(dsym_folder)
 
     if ctx.executable._process_wrapper:
+        execution_requirements = {}
+        if _is_incremental_compilation_enabled(ctx, toolchain, emit):
+            # The incremental compilation directory is kept by the process wrapper, outside of the
+            # sandbox, for each target and configuration.
+            args.process_wrapper_flags.add("--incremental-key", "{}@{}".format(ctx.label, ctx.bin_dir.path))
+            args.process_wrapper_flags.add("--incremental-base", toolchain._experimental_incremental_compilation_dir)
+
+            # Persistent workers receive the flagfiles of an action with each request. Any other
+            # argument is passed when the worker starts and is part of the key Bazel looks workers
+            # up by, which would give each target a worker of its own. The process wrapper expands
+            # the flagfiles itself when the action runs as a regular process.
+            for action_args in args.all:
+                action_args.set_param_file_format("multiline")
+                action_args.use_param_file("@%s", use_always = True)
+            execution_requirements = {
+                "supports-multiplex-workers": "1",
+                "supports-workers": "1",
+            }
+
+        rustc_inputs = compile_inputs
+        rustc_outputs = action_outputs
+        unused_inputs_list = None
+        if toolchain._experimental_use_unused_inputs_list and "dep-info" in emit:
+            # The process wrapper reports the candidates which aren't in the dep-info file rustc
+            # writes next to its outputs. Only dependency crates are candidates, which rustc lists
+            # with `-Zbinary-dep-depinfo`: sources and `compile_data` may be read by proc macros
+            # without being listed.
+            args.rustc_flags.add("-Zbinary-dep-depinfo")
+            unused_inputs_list = ctx.actions.declare_file(crate_info.output.basename + ".unused_inputs", sibling = crate_info.output)
+            unused_inputs_candidates = ctx.actions.declare_file(crate_info.output.basename + ".unused_inputs_candidates", sibling = crate_info.output)
+            candidates = ctx.actions.args()
+            candidates.set_param_file_format("multiline")
+            candidates.add_all(dep_info.transitive_metadata_outputs if _depend_on_metadata(crate_info, False) else dep_info.transitive_crate_outputs)
+            ctx.actions.write(
+                output = unused_inputs_candidates,
+                content = candidates,
+            )
+
+            dep_info_file = "{}/{}{}.d".format(
+                crate_info.output.dirname,
+                crate_info.name,
+                "-" + output_hash if output_hash else "",
+            )
+            args.process_wrapper_flags.add("--unused-inputs-list", unused_inputs_list)
+            args.process_wrapper_flags.add("--unused-inputs-candidates", unused_inputs_candidates)
+            args.process_wrapper_flags.add("--dep-info", dep_info_file)
+            rustc_inputs = depset([unused_inputs_candidates], transitive = [compile_inputs])
+            rustc_outputs = action_outputs + [unused_inputs_list]
+
         # Run as normal
         ctx.actions.run(
             executable = ctx.executable._process_wrapper,
-            inputs = compile_inputs,
-            outputs = action_outputs,
+            inputs = rustc_inputs,
+            outputs = rustc_outputs,
             env = env,
             arguments = args.all,
+            execution_requirements = execution_requirements,
+            unused_inputs_list = unused_inputs_list,
             mnemonic = "Rustc",
             progress_message = "Compiling Rust {} {}{} ({} files)".format(
                 crate_info.type,
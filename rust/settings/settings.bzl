@@ -159,6 +159,50 @@ def experimental_link_std_dylib():
         build_setting_default = False,
     )
 
+def experimental_incremental_compilation():
+    """A flag to enable incremental compilation of Rust targets built in the `fastbuild` and `dbg` compilation modes.
+
+    The process wrapper keeps a `-Cincremental` directory for each target and configuration outside of the
+    sandbox, so `Rustc` actions have to run without one. They support (multiplex) persistent workers for this,
+    e.g. with `--strategy=Rustc=worker`. The directories are kept in the directory set with
+    `--@rules_rust//rust/settings:experimental_incremental_compilation_dir`, which is required.
+
+    The directory of a target is discarded when the toolchain changes, and skipped when it's used by another
+    build. A build which fails in a way that may be caused by a corrupted cache is retried without it.
+    """
+    bool_flag(
+        name = "experimental_incremental_compilation",
+        build_setting_default = False,
+    )
+
+def experimental_incremental_compilation_dir():
+    """The absolute path of the directory containing incremental compilation directories when \
+    `--@rules_rust//rust/settings:experimental_incremental_compilation` is enabled.
+
+    It has to be a stable directory on the machine running the `Rustc` actions, outside of any sandbox, e.g. in
+    the output base of the workspace. There is no default, since the temporary directory of an action is private to
+    its sandbox, or to the machine it ran on with remote execution.
+    """
+    string_flag(
+        name = "experimental_incremental_compilation_dir",
+        build_setting_default = "",
+    )
+
+def experimental_use_unused_inputs_list():
+    """A flag to have `Rustc` actions report the dependency crates which rustc didn't read to Bazel with an `unused_inputs_list`.
+
+    The process wrapper compares the transitive `.rlib` and `.rmeta` files of a target with the crates listed in the
+    dep-info file rustc emits with `-Zbinary-dep-depinfo`. Bazel then excludes the unused ones from the cache key of the
+    action, so changing them no longer rebuilds the target.
+
+    `-Zbinary-dep-depinfo` is passed to rustc when this flag is enabled, so it requires a nightly toolchain. Sources and
+    `compile_data` are never reported, since files read by proc macros may be missing from the dep-info file.
+    """
+    bool_flag(
+        name = "experimental_use_unused_inputs_list",
+        build_setting_default = False,
+    )
+
 def experimental_use_sh_toolchain_for_bootstrap_process_wrapper():
     """A flag to control whether the shell path from a shell toolchain (`@bazel_tools//tools/sh:toolchain_type`) \
     is embedded into the bootstrap process wrapper for the `.sh` file.
@@ -3,6 +3,7 @@
 Toolchain rules for Rust.
 """
 
+load("@bazel_skylib//lib:paths.bzl", "paths")
 load("@bazel_skylib//rules:common_settings.bzl", "BuildSettingInfo")
 load("@rules_cc//cc/common:cc_common.bzl", "cc_common")
 load("@rules_cc//cc/common:cc_info.bzl", "CcInfo")
@@ -542,6 +543,16 @@ def _rust_toolchain_impl(ctx):
             "--@rules_rust//rust/settings:experimental_use_cc_common_link to be set",
         )
 
+    experimental_incremental_compilation = ctx.attr._experimental_incremental_compilation[BuildSettingInfo].value
+    experimental_incremental_compilation_dir = ctx.attr._experimental_incremental_compilation_dir[BuildSettingInfo].value
+    if experimental_incremental_compilation and not paths.is_absolute(experimental_incremental_compilation_dir):
+        fail(
+            "@rules_rust//rust/settings:experimental_incremental_compilation requires " +
+            "--@rules_rust//rust/settings:experimental_incremental_compilation_dir to be set to an absolute path, got '{}'".format(
+                experimental_incremental_compilation_dir,
+            ),
+        )
+
     rust_std = ctx.attr.rust_std
 
     sysroot = _generate_sysroot(
@@ -704,10 +715,13 @@ def _rust_toolchain_impl(ctx):
         _rename_first_party_crates = rename_first_party_crates,
         _third_party_dir = third_party_dir,
         _pipelined_compilation = pipelined_compilation,
+        _experimental_incremental_compilation = experimental_incremental_compilation,
+        _experimental_incremental_compilation_dir = experimental_incremental_compilation_dir,
         _experimental_link_std_dylib = _experimental_link_std_dylib(ctx),
         _experimental_use_cc_common_link = _experimental_use_cc_common_link(ctx),
         _experimental_use_global_allocator = experimental_use_global_allocator,
         _experimental_use_coverage_metadata_files = ctx.attr._experimental_use_coverage_metadata_files[BuildSettingInfo].value,
+        _experimental_use_unused_inputs_list = ctx.attr._experimental_use_unused_inputs_list[BuildSettingInfo].value,
         _incompatible_change_rust_test_compilation_output_directory = ctx.attr._incompatible_change_rust_test_compilation_output_directory[IncompatibleFlagInfo].enabled,
         _toolchain_generated_sysroot = ctx.attr._toolchain_generated_sysroot[BuildSettingInfo].value,
         _incompatible_do_not_include_data_in_compile_data = ctx.attr._incompatible_do_not_include_data_in_compile_data[IncompatibleFlagInfo].enabled,
@@ -891,6 +905,12 @@ rust_toolchain = rule(
         "_codegen_units": attr.label(
             default = Label("//rust/settings:codegen_units"),
         ),
+        "_experimental_incremental_compilation": attr.label(
+            default = Label("//rust/settings:experimental_incremental_compilation"),
+        ),
+        "_experimental_incremental_compilation_dir": attr.label(
+            default = Label("//rust/settings:experimental_incremental_compilation_dir"),
+        ),
         "_experimental_use_coverage_metadata_files": attr.label(
             default = Label("//rust/settings:experimental_use_coverage_metadata_files"),
         ),
@@ -901,6 +921,9 @@ rust_toolchain = rule(
                 "This flag is only relevant when used together with --@rules_rust//rust/settings:experimental_use_global_allocator."
             ),
         ),
+        "_experimental_use_unused_inputs_list": attr.label(
+            default = Label("//rust/settings:experimental_use_unused_inputs_list"),
+        ),
         "_incompatible_change_rust_test_compilation_output_directory": attr.label(
             default = Label("//rust/settings:incompatible_change_rust_test_compilation_output_directory"),
         ),
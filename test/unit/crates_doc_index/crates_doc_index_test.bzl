@@ -0,0 +1,151 @@
+"""Unittests for the documentation index of third-party crates"""
+
+load("@bazel_skylib//lib:unittest.bzl", "analysistest", "asserts")
+load("@bazel_skylib//rules:write_file.bzl", "write_file")
+load("//crate_universe/private:crates_doc_index.bzl", "crates_doc_index")
+load("//rust:defs.bzl", "rust_doc", "rust_library")
+load(
+    "//test/unit:common.bzl",
+    "assert_argv_contains",
+    "assert_argv_contains_not",
+    "assert_argv_contains_prefix_not",
+)
+
+def _rustdoc_actions(env, target):
+    actions = [action for action in target.actions if action.mnemonic == "RustdocIndex"]
+    asserts.equals(env, 2, len(actions))
+    return actions
+
+def _merged_crates_doc_index_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    alpha, beta = _rustdoc_actions(env, target)
+    assert_argv_contains(env, alpha, "-Zunstable-options")
+    assert_argv_contains(env, alpha, "--merge=none")
+    assert_argv_contains(env, beta, "-Zunstable-options")
+    assert_argv_contains(env, beta, "--merge=finalize")
+    assert_argv_contains(env, beta, "--extern-html-root-url=alpha=../")
+    asserts.equals(env, ["docs.rustdoc/0"], [
+        output.short_path.rpartition("/crates_doc_index/")[2]
+        for output in beta.outputs.to_list()
+    ])
+
+    return analysistest.end(env)
+
+merged_crates_doc_index_test = analysistest.make(
+    _merged_crates_doc_index_test_impl,
+    doc = "Test that crates are documented into one tree with a nightly toolchain",
+    config_settings = {
+        str(Label("//rust/toolchain/channel:channel")): "nightly",
+    },
+)
+
+def _separate_crates_doc_index_test_impl(ctx):
+    env = analysistest.begin(ctx)
+    target = analysistest.target_under_test(env)
+
+    outputs = []
+    for action in _rustdoc_actions(env, target):
+        assert_argv_contains_not(env, action, "-Zunstable-options")
+        assert_argv_contains_prefix_not(env, action, "--merge")
+        assert_argv_contains_prefix_not(env, action, "--extern-html-root-url")
+        outputs.extend([
+            output.short_path.rpartition("/crates_doc_index/")[2]
+            for output in action.outputs.to_list()
+        ])
+    asserts.equals(env, ["docs.rustdoc/alpha-0.1.0", "docs.rustdoc/beta-0.2.0"], outputs)
+
+    return analysistest.end(env)
+
+separate_crates_doc_index_test = analysistest.make(
+    _separate_crates_doc_index_test_impl,
+    doc = "Test that crates are documented into trees of their own with a stable toolchain",
+    config_settings = {
+        str(Label("//rust/toolchain/channel:channel")): "stable",
+    },
+)
+
+def crates_doc_index_test_suite(name):
+    """Entry-point macro called from the BUILD file.
+
+    Args:
+        name (str): Name of the macro.
+    """
+
+    write_file(
+        name = "alpha_rs",
+        out = "alpha.rs",
+        content = [
+            "pub fn alpha() {}",
+            "",
+        ],
+    )
+
+    write_file(
+        name = "beta_rs",
+        out = "beta.rs",
+        content = [
+            "pub fn beta() {",
+            "    alpha::alpha();",
+            "}",
+            "",
+        ],
+    )
+
+    rust_library(
+        name = "alpha",
+        srcs = ["alpha.rs"],
+        edition = "2021",
+    )
+
+    rust_library(
+        name = "beta",
+        srcs = ["beta.rs"],
+        edition = "2021",
+        deps = [":alpha"],
+    )
+
+    rust_doc(
+        name = "alpha_doc",
+        crate = ":alpha",
+    )
+
+    rust_doc(
+        name = "beta_doc",
+        crate = ":beta",
+    )
+
+    crates_doc_index(
+        name = "docs",
+        docs = {
+            ":alpha_doc": "0.1.0",
+            ":beta_doc": "0.2.0",
+        },
+        links = {
+            "beta-0.2.0": ["alpha-0.1.0"],
+        },
+    )
+
+    merged_crates_doc_index_test(
+        name = "merged_crates_doc_index_test",
+        target_under_test = ":docs",
+        # Merging the documentation of crates requires a shell.
+        target_compatible_with = select({
+            "@platforms//os:windows": ["@platforms//:incompatible"],
+            "//conditions:default": [],
+        }),
+    )
+
+    separate_crates_doc_index_test(
+        name = "separate_crates_doc_index_test",
+        target_under_test = ":docs",
+    )
+
+    native.test_suite(
+        name = name,
+        tests = [
+            ":merged_crates_doc_index_test",
+            ":separate_crates_doc_index_test",
+        ],
+    )
@@ -0,0 +1,231 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for Bazel's `unused_inputs_list`: the dependency crates of an action which rustc
+//! didn't read, according to the dep-info file it emitted, are excluded from the action's
+//! cache key in later builds.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::util::read_file_to_array;
+
+/// Extensions of the files rustc loads crates from.
+const CRATE_EXTENSIONS: [&str; 5] = ["rlib", "rmeta", "so", "dylib", "dll"];
+
+#[derive(Debug)]
+pub(crate) struct UnusedInputs {
+    // The dep-info file emitted by rustc.
+    pub(crate) dep_info: String,
+    // A file listing the inputs which may be unused, one per line.
+    pub(crate) candidates: String,
+    // The unused inputs list to write.
+    pub(crate) output: String,
+}
+
+/// Write the candidates which aren't dependencies in the dep-info file to the unused
+/// inputs list.
+pub(crate) fn write_unused_inputs(unused_inputs: &UnusedInputs) -> Result<(), String> {
+    let dep_info = fs::read_to_string(&unused_inputs.dep_info)
+        .map_err(|e| format!("failed to read {}: {}", unused_inputs.dep_info, e))?;
+    let candidates = read_file_to_array(&unused_inputs.candidates)?;
+    let current_dir =
+        std::env::current_dir().map_err(|e| format!("failed to get current directory: {}", e))?;
+
+    let mut content = String::new();
+    for input in find_unused_inputs(&dep_info, candidates, &current_dir) {
+        content.push_str(&input);
+        content.push('\n');
+    }
+    fs::write(&unused_inputs.output, content)
+        .map_err(|e| format!("failed to write {}: {}", unused_inputs.output, e))
+}
+
+fn find_unused_inputs(dep_info: &str, candidates: Vec<String>, current_dir: &Path) -> Vec<String> {
+    let dependencies: HashSet<PathBuf> = parse_dependencies(dep_info)
+        .iter()
+        .map(|dependency| normalize(dependency, current_dir))
+        .collect();
+    // Crates are only listed with `-Zbinary-dep-depinfo`, and then the standard library always
+    // is. Otherwise it's unknown whether a crate was used.
+    if !dependencies.iter().any(|dependency| is_crate(dependency)) {
+        return Vec::new();
+    }
+
+    // Other files may be read by proc macros without being listed, so only crates are reported.
+    candidates
+        .into_iter()
+        .filter(|candidate| {
+            let path = normalize(candidate, current_dir);
+            is_crate(&path) && !dependencies.contains(&path)
+        })
+        .collect()
+}
+
+/// Parse the dependencies of the rules of a dep-info file, which is a Makefile like:
+///
+/// ```text
+/// out/libfoo.rmeta: src/lib.rs src/with\ space.rs
+///
+/// src/lib.rs:
+/// src/with\ space.rs:
+///
+/// # env-dep:FOO=foo
+/// ```
+fn parse_dependencies(dep_info: &str) -> Vec<String> {
+    let mut dependencies = Vec::new();
+    for line in dep_info.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        // Windows paths contain colons, but not followed by a space.
+        let Some((_, prerequisites)) = line
+            .split_once(": ")
+            .or_else(|| line.strip_suffix(':').map(|target| (target, "")))
+        else {
+            continue;
+        };
+
+        // Spaces in paths are escaped with a backslash.
+        let mut dependency = String::new();
+        let mut chars = prerequisites.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&' ') => {
+                    dependency.push(' ');
+                    chars.next();
+                }
+                ' ' => {
+                    if !dependency.is_empty() {
+                        dependencies.push(std::mem::take(&mut dependency));
+                    }
+                }
+                c => dependency.push(c),
+            }
+        }
+        if !dependency.is_empty() {
+            dependencies.push(dependency);
+        }
+    }
+    dependencies
+}
+
+/// Make `path` relative to `current_dir` if it's inside of it, and remove `.` and `..`
+/// components, so paths written by rustc can be compared to those of the inputs.
+fn normalize(path: &str, current_dir: &Path) -> PathBuf {
+    let path = Path::new(path);
+    let path = path.strip_prefix(current_dir).unwrap_or(path);
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn is_crate(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| CRATE_EXTENSIONS.contains(&extension))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|&s| s.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_parse_dependencies() {
+        let dep_info = "\
+bazel-out/bin/pkg/libfoo-123.rmeta: pkg/src/lib.rs pkg/src/with\\ space.rs ./pkg/src/../data.txt
+
+pkg/src/lib.rs:
+pkg/src/with\\ space.rs:
+./pkg/src/../data.txt:
+
+# env-dep:CARGO_PKG_NAME=foo
+";
+        assert_eq!(
+            parse_dependencies(dep_info),
+            strings(&[
+                "pkg/src/lib.rs",
+                "pkg/src/with space.rs",
+                "./pkg/src/../data.txt"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_find_unused_inputs() {
+        let current_dir = Path::new("/execroot/_main");
+        let dep_info = "\
+bazel-out/bin/pkg/libfoo-123.rmeta: /execroot/_main/pkg/src/lib.rs
+
+/execroot/_main/pkg/src/lib.rs:
+";
+        let candidates = strings(&[
+            "bazel-out/bin/used/libused-456.rmeta",
+            "bazel-out/bin/unused/libunused-789.rmeta",
+        ]);
+
+        // Without `-Zbinary-dep-depinfo`, crates may have been used.
+        assert!(find_unused_inputs(dep_info, candidates.clone(), current_dir).is_empty());
+
+        let dep_info = "\
+bazel-out/bin/pkg/libfoo-123.rmeta: /execroot/_main/pkg/src/lib.rs ./bazel-out/bin/dep/../used/libused-456.rmeta /toolchain/lib/libcore-789.rlib
+
+/execroot/_main/pkg/src/lib.rs:
+./bazel-out/bin/dep/../used/libused-456.rmeta:
+/toolchain/lib/libcore-789.rlib:
+";
+        assert_eq!(
+            find_unused_inputs(dep_info, candidates, current_dir),
+            strings(&["bazel-out/bin/unused/libunused-789.rmeta"])
+        );
+    }
+
+    #[test]
+    fn test_files_read_by_proc_macros_are_used() {
+        // `pkg/template.txt` is `compile_data` which a proc macro read with `include_str!`,
+        // so rustc didn't list it.
+        let current_dir = Path::new("/execroot/_main");
+        let dep_info = "\
+bazel-out/bin/pkg/libfoo-123.rmeta: pkg/src/lib.rs bazel-out/bin/macro/libmacro-456.so /toolchain/lib/libcore-789.rlib
+
+pkg/src/lib.rs:
+bazel-out/bin/macro/libmacro-456.so:
+/toolchain/lib/libcore-789.rlib:
+";
+        let candidates = strings(&[
+            "pkg/src/lib.rs",
+            "pkg/template.txt",
+            "bazel-out/bin/macro/libmacro-456.so",
+        ]);
+        assert!(find_unused_inputs(dep_info, candidates, current_dir).is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental compilation directories managed by the process wrapper.
+//!
+//! The incremental compilation cache of rustc can't be an output of a Bazel action, so each
+//! target gets a directory outside of the sandbox instead, identified by the key passed with
+//! `--incremental-key` (the label and configuration of the target):
+//!
+//! ```text
+//! <base>/<hash of the key>/
+//!     lock         Held while the directory is in use, contains the id of the process.
+//!     toolchain    Describes the rustc which populated the cache.
+//!     incremental  The directory passed to rustc with `-Cincremental`.
+//! ```
+//!
+//! Incremental compilation only makes rustc faster, so the directory is skipped rather than
+//! waited for when it's in use, and a failure which may be caused by the cache is retried
+//! without it.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LOCK_FILE: &str = "lock";
+const TOOLCHAIN_FILE: &str = "toolchain";
+const CACHE_DIR: &str = "incremental";
+
+/// How long a directory may go unused before it's removed.
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How long a process may take to write its id into a lock file it created.
+const LOCK_WRITE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A locked incremental compilation directory, which is unlocked when dropped.
+#[derive(Debug)]
+pub(crate) struct IncrementalDir {
+    dir: PathBuf,
+}
+
+impl IncrementalDir {
+    /// Lock the directory for `key` in `base`, creating it if needed. Its cache is cleared if it
+    /// was populated by a different `rustc`. Returns `None` if another process uses it.
+    pub(crate) fn acquire(base: &Path, key: &str, rustc: &Path) -> io::Result<Option<Self>> {
+        let dir = base.join(format!("{:016x}", fnv1a(key.as_bytes())));
+        fs::create_dir_all(&dir)?;
+        if !lock(&dir)? {
+            return Ok(None);
+        }
+        let incremental_dir = Self { dir };
+
+        let toolchain = toolchain_fingerprint(rustc)?;
+        let toolchain_file = incremental_dir.dir.join(TOOLCHAIN_FILE);
+        if fs::read_to_string(&toolchain_file).ok().as_deref() != Some(toolchain.as_str()) {
+            incremental_dir.clear()?;
+            fs::write(&toolchain_file, toolchain)?;
+        }
+        // The modification time of the toolchain file records when the directory was last used.
+        OpenOptions::new()
+            .write(true)
+            .open(&toolchain_file)?
+            .set_modified(SystemTime::now())?;
+
+        remove_unused(base, &incremental_dir.dir);
+        Ok(Some(incremental_dir))
+    }
+
+    /// The flag enabling incremental compilation in this directory.
+    pub(crate) fn rustc_flag(&self) -> String {
+        format!("-Cincremental={}", self.dir.join(CACHE_DIR).display())
+    }
+
+    /// Remove the incremental compilation cache.
+    pub(crate) fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(self.dir.join(CACHE_DIR)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for IncrementalDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.dir.join(LOCK_FILE));
+    }
+}
+
+/// Whether rustc failing with `exit_code` and `output` may be caused by a corrupted
+/// incremental compilation cache rather than by the code it compiled.
+pub(crate) fn is_possible_cache_failure(exit_code: i32, output: &[u8]) -> bool {
+    // rustc exits with 101 when it panics, e.g. with an internal compiler error.
+    exit_code == 101 || String::from_utf8_lossy(output).contains("incremental compilation")
+}
+
+/// Try to lock `dir`, replacing a lock left behind by a process which no longer runs.
+fn lock(dir: &Path) -> io::Result<bool> {
+    let lock_file = dir.join(LOCK_FILE);
+    for _ in 0..2 {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_file)
+        {
+            Ok(mut file) => {
+                file.write_all(process::id().to_string().as_bytes())?;
+                return Ok(true);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let holder = fs::read_to_string(&lock_file)
+                    .ok()
+                    .and_then(|pid| pid.trim().parse::<u32>().ok());
+                let is_stale = match holder {
+                    Some(pid) => !is_running(pid),
+                    // The lock may have been created but not written yet.
+                    None => fs::metadata(&lock_file)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > LOCK_WRITE_TIMEOUT),
+                };
+                if !is_stale {
+                    return Ok(false);
+                }
+                match fs::remove_file(&lock_file) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(not(windows))]
+fn is_running(pid: u32) -> bool {
+    if pid == process::id() {
+        return true;
+    }
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_or(true, |status| status.success())
+}
+
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    if pid == process::id() {
+        return true;
+    }
+    Command::new("tasklist")
+        .args(["/NH", "/FO", "CSV", "/FI"])
+        .arg(format!("PID eq {}", pid))
+        .stderr(Stdio::null())
+        .output()
+        .map_or(true, |output| {
+            String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid))
+        })
+}
+
+/// Describe the rustc binary, so that a cache populated by another toolchain is discarded.
+fn toolchain_fingerprint(rustc: &Path) -> io::Result<String> {
+    let metadata = fs::metadata(rustc)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(format!(
+        "{}\n{}\n{}\n",
+        rustc.display(),
+        metadata.len(),
+        modified.as_nanos()
+    ))
+}
+
+/// Remove the directories in `base` other than `current` which haven't been used recently.
+fn remove_unused(base: &Path, current: &Path) {
+    let Ok(entries) = fs::read_dir(base) else {
+        return;
+    };
+    for dir in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if dir == current || !dir.is_dir() {
+            continue;
+        }
+        let age = fs::metadata(dir.join(TOOLCHAIN_FILE))
+            .or_else(|_| fs::metadata(&dir))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age > MAX_UNUSED_AGE) && lock(&dir).unwrap_or(false) {
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+}
+
+/// The 64 bit FNV-1a hash, which unlike `DefaultHasher` is stable across Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "process_wrapper_incremental_{}_{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_acquire_locks_and_invalidates() {
+        let base = temp_dir("acquire");
+        let rustc = base.join("rustc");
+        fs::write(&rustc, "rustc 1.0").unwrap();
+
+        let incremental_dir = IncrementalDir::acquire(&base, "//:lib@k8-fastbuild", &rustc)
+            .unwrap()
+            .unwrap();
+        let flag = incremental_dir.rustc_flag();
+        let cache = PathBuf::from(flag.strip_prefix("-Cincremental=").unwrap());
+        assert!(cache.starts_with(&base));
+
+        // The directory is in use.
+        assert!(
+            IncrementalDir::acquire(&base, "//:lib@k8-fastbuild", &rustc)
+                .unwrap()
+                .is_none()
+        );
+        fs::create_dir_all(cache.join("s-session")).unwrap();
+        drop(incremental_dir);
+
+        // The cache is kept for the same toolchain.
+        let incremental_dir = IncrementalDir::acquire(&base, "//:lib@k8-fastbuild", &rustc)
+            .unwrap()
+            .unwrap();
+        assert!(cache.join("s-session").exists());
+        drop(incremental_dir);
+
+        // And cleared for another.
+        fs::write(&rustc, "rustc 1.1.0").unwrap();
+        let incremental_dir = IncrementalDir::acquire(&base, "//:lib@k8-fastbuild", &rustc)
+            .unwrap()
+            .unwrap();
+        assert!(!cache.exists());
+        drop(incremental_dir);
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_stale_lock() {
+        let base = temp_dir("stale_lock");
+        let dir = base.join("dir");
+        fs::create_dir_all(&dir).unwrap();
+
+        // A process which exited.
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        fs::write(dir.join(LOCK_FILE), pid.to_string()).unwrap();
+        assert!(lock(&dir).unwrap());
+        assert_eq!(
+            fs::read_to_string(dir.join(LOCK_FILE)).unwrap(),
+            process::id().to_string()
+        );
+
+        // A lock which is still being written.
+        fs::write(dir.join(LOCK_FILE), "").unwrap();
+        assert!(!lock(&dir).unwrap());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_is_possible_cache_failure() {
+        assert!(is_possible_cache_failure(
+            101,
+            b"error: internal compiler error: unexpected panic"
+        ));
+        assert!(is_possible_cache_failure(
+            1,
+            b"error: could not create incremental compilation crate directory"
+        ));
+        assert!(!is_possible_cache_failure(
+            1,
+            b"error[E0308]: mismatched types"
+        ));
+    }
+}
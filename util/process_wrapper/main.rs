@@ -12,21 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod dep_info;
 mod flags;
+mod incremental;
 mod options;
 mod output;
 mod rustc;
 mod util;
+mod worker;
 
 use std::collections::HashMap;
+use std::env;
 use std::fmt;
 use std::fs::{copy, OpenOptions};
-use std::io;
+use std::io::{self, Read, Write};
+use std::path::Path;
 use std::process::{exit, Command, ExitStatus, Stdio};
+use std::thread;
 
 use tinyjson::JsonValue;
 
-use crate::options::options;
+use crate::incremental::IncrementalDir;
+use crate::options::{options, OptionError, Options};
 use crate::output::{process_output, LineOutput};
 use crate::rustc::ErrorFormat;
 
@@ -116,14 +123,101 @@ fn process_line(
 }
 
 fn main() -> Result<(), ProcessWrapperError> {
-    let opts = options().map_err(|e| ProcessWrapperError(e.to_string()))?;
+    let args: Vec<String> = env::args().collect();
+    if worker::is_persistent_worker(&args) {
+        return worker::run_worker(&args);
+    }
+
+    let opts = match options(args) {
+        Ok(opts) => opts,
+        Err(OptionError::Help(help)) => {
+            eprintln!("{help}");
+            exit(0);
+        }
+        Err(e) => return Err(ProcessWrapperError(e.to_string())),
+    };
+
+    exit(run(opts, None)?)
+}
+
+/// Run the child process described by `opts` and return its exit code.
+///
+/// Unless they're redirected to files, the child's stdout and stderr are forwarded to those
+/// of the process wrapper, or collected into `captured_output` if it's given.
+fn run(opts: Options, captured_output: Option<&mut Vec<u8>>) -> Result<i32, ProcessWrapperError> {
+    let incremental_dir = opts.incremental.as_ref().and_then(|(key, base)| {
+        match IncrementalDir::acquire(Path::new(base), key, Path::new(&opts.executable)) {
+            Ok(incremental_dir) => incremental_dir,
+            Err(e) => {
+                debug_log!("unable to use incremental compilation for {}: {}", key, e);
+                None
+            }
+        }
+    });
+
+    let code = match incremental_dir {
+        Some(incremental_dir) => {
+            // The output is held back until it's known whether the cache has to be discarded.
+            let mut output = Vec::new();
+            let code = run_child(&opts, &[incremental_dir.rustc_flag()], Some(&mut output))?;
+            if code != 0 && incremental::is_possible_cache_failure(code, &output) {
+                debug_log!("rebuilding without incremental compilation cache");
+                incremental_dir.clear().map_err(|e| {
+                    ProcessWrapperError(format!("failed to clear incremental cache: {}", e))
+                })?;
+                drop(incremental_dir);
+                run_child(&opts, &[], captured_output)?
+            } else {
+                match captured_output {
+                    Some(captured_output) => captured_output.extend(output),
+                    None => io::stderr().write_all(&output).map_err(|e| {
+                        ProcessWrapperError(format!("failed to write output: {}", e))
+                    })?,
+                }
+                code
+            }
+        }
+        None => run_child(&opts, &[], captured_output)?,
+    };
+
+    if code == 0 {
+        if let Some(unused_inputs) = &opts.unused_inputs {
+            dep_info::write_unused_inputs(unused_inputs).map_err(ProcessWrapperError)?;
+        }
+        if let Some(tf) = opts.touch_file {
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(tf)
+                .map_err(|e| ProcessWrapperError(format!("failed to create touch file: {}", e)))?;
+        }
+        if let Some((copy_source, copy_dest)) = opts.copy_output {
+            copy(&copy_source, &copy_dest).map_err(|e| {
+                ProcessWrapperError(format!(
+                    "failed to copy {} into {}: {}",
+                    copy_source, copy_dest, e
+                ))
+            })?;
+        }
+    }
+
+    Ok(code)
+}
 
-    let mut command = Command::new(opts.executable);
+/// Run the child process with `extra_arguments` and process its output.
+fn run_child(
+    opts: &Options,
+    extra_arguments: &[String],
+    mut captured_output: Option<&mut Vec<u8>>,
+) -> Result<i32, ProcessWrapperError> {
+    let mut command = Command::new(&opts.executable);
     command
-        .args(opts.child_arguments)
+        .args(&opts.child_arguments)
+        .args(extra_arguments)
         .env_clear()
-        .envs(opts.child_environment)
-        .stdout(if let Some(stdout_file) = opts.stdout_file {
+        .envs(&opts.child_environment)
+        .stdout(if let Some(stdout_file) = &opts.stdout_file {
             OpenOptions::new()
                 .create(true)
                 .truncate(true)
@@ -131,6 +225,8 @@ fn main() -> Result<(), ProcessWrapperError> {
                 .open(stdout_file)
                 .map_err(|e| ProcessWrapperError(format!("unable to open stdout file: {}", e)))?
                 .into()
+        } else if captured_output.is_some() {
+            Stdio::piped()
         } else {
             Stdio::inherit()
         })
@@ -140,7 +236,15 @@ fn main() -> Result<(), ProcessWrapperError> {
         .spawn()
         .map_err(|e| ProcessWrapperError(format!("failed to spawn child process: {}", e)))?;
 
-    let mut stderr: Box<dyn io::Write> = if let Some(stderr_file) = opts.stderr_file {
+    // Read stdout while stderr is processed so neither pipe can fill up and block the child.
+    let child_stdout = child.stdout.take().map(|mut child_stdout| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            child_stdout.read_to_end(&mut buffer).map(|_| buffer)
+        })
+    });
+
+    let mut stderr: Box<dyn io::Write + '_> = if let Some(stderr_file) = &opts.stderr_file {
         Box::new(
             OpenOptions::new()
                 .create(true)
@@ -149,6 +253,8 @@ fn main() -> Result<(), ProcessWrapperError> {
                 .open(stderr_file)
                 .map_err(|e| ProcessWrapperError(format!("unable to open stderr file: {}", e)))?,
         )
+    } else if let Some(captured_output) = captured_output.as_deref_mut() {
+        Box::new(captured_output)
     } else {
         Box::new(io::stderr())
     };
@@ -157,7 +263,7 @@ fn main() -> Result<(), ProcessWrapperError> {
         "unable to get child stderr".to_string(),
     ))?;
 
-    let mut output_file: Option<std::fs::File> = if let Some(output_file_name) = opts.output_file {
+    let mut output_file: Option<std::fs::File> = if let Some(output_file_name) = &opts.output_file {
         Some(
             OpenOptions::new()
                 .create(true)
@@ -200,33 +306,23 @@ fn main() -> Result<(), ProcessWrapperError> {
         )
     };
     result.map_err(|e| ProcessWrapperError(format!("failed to process stderr: {}", e)))?;
+    drop(stderr);
 
     let status = child
         .wait()
         .map_err(|e| ProcessWrapperError(format!("failed to wait for child process: {}", e)))?;
-    // If the child process is rustc and is killed after metadata generation, that's also a success.
-    let code = status_code(status, was_killed);
-    let success = code == 0;
-    if success {
-        if let Some(tf) = opts.touch_file {
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(tf)
-                .map_err(|e| ProcessWrapperError(format!("failed to create touch file: {}", e)))?;
-        }
-        if let Some((copy_source, copy_dest)) = opts.copy_output {
-            copy(&copy_source, &copy_dest).map_err(|e| {
-                ProcessWrapperError(format!(
-                    "failed to copy {} into {}: {}",
-                    copy_source, copy_dest, e
-                ))
-            })?;
+    if let Some(child_stdout) = child_stdout {
+        let stdout = child_stdout
+            .join()
+            .map_err(|_| ProcessWrapperError("failed to read child stdout".to_string()))?
+            .map_err(|e| ProcessWrapperError(format!("failed to read child stdout: {}", e)))?;
+        if let Some(captured_output) = captured_output {
+            captured_output.extend(stdout);
         }
     }
 
-    exit(code)
+    // If the child process is rustc and is killed after metadata generation, that's also a success.
+    Ok(status_code(status, was_killed))
 }
 
 #[cfg(test)]
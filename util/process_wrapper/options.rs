@@ -1,10 +1,10 @@
 use std::collections::HashMap;
-use std::env;
 use std::fmt;
 use std::fs::File;
 use std::io::{self, Write};
-use std::process::exit;
+use std::path::Path;
 
+use crate::dep_info::UnusedInputs;
 use crate::flags::{FlagParseError, Flags, ParseOutcome};
 use crate::rustc;
 use crate::util::*;
@@ -13,6 +13,8 @@ use crate::util::*;
 pub(crate) enum OptionError {
     FlagError(FlagParseError),
     Generic(String),
+    // `--help` was passed, with the help text.
+    Help(String),
 }
 
 impl fmt::Display for OptionError {
@@ -20,6 +22,7 @@ impl fmt::Display for OptionError {
         match self {
             Self::FlagError(e) => write!(f, "error parsing flags: {e}"),
             Self::Generic(s) => write!(f, "{s}"),
+            Self::Help(help) => write!(f, "{help}"),
         }
     }
 }
@@ -49,9 +52,16 @@ pub(crate) struct Options {
     pub(crate) rustc_quit_on_rmeta: bool,
     // This controls the output format of rustc messages.
     pub(crate) rustc_output_format: Option<rustc::ErrorFormat>,
+    // If set to (key, base), rustc compiles incrementally in the directory identified by the
+    // key in the base directory.
+    pub(crate) incremental: Option<(String, String)>,
+    // If set, writes the inputs which rustc didn't read to an unused inputs list.
+    pub(crate) unused_inputs: Option<UnusedInputs>,
 }
 
-pub(crate) fn options() -> Result<Options, OptionError> {
+/// Parse the options of the process wrapper from its command line, `args`, which starts
+/// with the program name.
+pub(crate) fn options(args: Vec<String>) -> Result<Options, OptionError> {
     // Process argument list until -- is encountered.
     // Everything after is sent to the child process.
     let mut subst_mapping_raw = None;
@@ -66,6 +76,12 @@ pub(crate) fn options() -> Result<Options, OptionError> {
     let mut output_file = None;
     let mut rustc_quit_on_rmeta_raw = None;
     let mut rustc_output_format_raw = None;
+    let mut worker_protocol = None;
+    let mut incremental_key = None;
+    let mut incremental_base = None;
+    let mut unused_inputs_list = None;
+    let mut unused_inputs_candidates = None;
+    let mut dep_info = None;
     let mut flags = Flags::new();
     flags.define_repeated_flag("--subst", "", &mut subst_mapping_raw);
     flags.define_flag("--stable-status-file", "", &mut stable_status_file_raw);
@@ -114,15 +130,48 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         Default: `rendered`",
         &mut rustc_output_format_raw,
     );
+    // Read by the worker itself (see `worker.rs`) rather than for each request, but still
+    // defined so it's accepted when the action runs as a regular process.
+    flags.define_flag(
+        "--worker-protocol",
+        "The protocol used by Bazel when running as a persistent worker.\n\
+        'json' or 'proto'. Default: `proto`",
+        &mut worker_protocol,
+    );
+    flags.define_flag(
+        "--incremental-key",
+        "Enables incremental compilation for a rustc child process, in a directory\n\
+        outside of the sandbox which is identified by this key.",
+        &mut incremental_key,
+    );
+    flags.define_flag(
+        "--incremental-base",
+        "The absolute path of the directory containing incremental compilation\n\
+        directories. Required by --incremental-key.",
+        &mut incremental_base,
+    );
+    flags.define_flag(
+        "--unused-inputs-list",
+        "Write the inputs listed in --unused-inputs-candidates which rustc didn't read,\n\
+        according to --dep-info, to this file after the child process runs successfully.",
+        &mut unused_inputs_list,
+    );
+    flags.define_flag(
+        "--unused-inputs-candidates",
+        "File containing the inputs which may be unused, one per line.",
+        &mut unused_inputs_candidates,
+    );
+    flags.define_flag(
+        "--dep-info",
+        "The dep-info file emitted by rustc.",
+        &mut dep_info,
+    );
 
     let mut child_args = match flags
-        .parse(env::args().collect())
+        .parse(expand_flagfiles(args)?)
         .map_err(OptionError::FlagError)?
     {
-        ParseOutcome::Help(help) => {
-            eprintln!("{help}");
-            exit(0);
-        }
+        ParseOutcome::Help(help) => return Err(OptionError::Help(help)),
         ParseOutcome::Parsed(p) => p,
     };
     let current_dir = std::env::current_dir()
@@ -146,10 +195,12 @@ pub(crate) fn options() -> Result<Options, OptionError> {
             Ok((key.to_owned(), v))
         })
         .collect::<Result<Vec<(String, String)>, OptionError>>()?;
-    let stable_stamp_mappings =
-        stable_status_file_raw.map_or_else(Vec::new, |s| read_stamp_status_to_array(s).unwrap());
-    let volatile_stamp_mappings =
-        volatile_status_file_raw.map_or_else(Vec::new, |s| read_stamp_status_to_array(s).unwrap());
+    let stable_stamp_mappings = stable_status_file_raw
+        .map_or_else(|| Ok(Vec::new()), read_stamp_status_to_array)
+        .map_err(OptionError::Generic)?;
+    let volatile_stamp_mappings = volatile_status_file_raw
+        .map_or_else(|| Ok(Vec::new()), read_stamp_status_to_array)
+        .map_err(OptionError::Generic)?;
     let environment_file_block = env_from_files(env_file_raw.unwrap_or_default())?;
     let mut file_arguments = args_from_file(arg_file_raw.unwrap_or_default())?;
     // Process --copy-output
@@ -172,6 +223,39 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         })
         .transpose()?;
 
+    let unused_inputs = match (unused_inputs_list, unused_inputs_candidates, dep_info) {
+        (Some(output), Some(candidates), Some(dep_info)) => Some(UnusedInputs {
+            dep_info,
+            candidates,
+            output,
+        }),
+        (None, None, None) => None,
+        _ => {
+            return Err(OptionError::Generic(
+                "\"--unused-inputs-list\", \"--unused-inputs-candidates\" and \"--dep-info\" \
+                must be used together"
+                    .to_owned(),
+            ))
+        }
+    };
+
+    // The directory has to outlive the sandbox of the action, and be the same for every action
+    // of a target, so it's never relative to the working directory.
+    let incremental = match (incremental_key, incremental_base) {
+        (Some(key), Some(base)) if Path::new(&base).is_absolute() => Some((key, base)),
+        (None, None) => None,
+        (Some(_), Some(base)) => {
+            return Err(OptionError::Generic(format!(
+                "\"--incremental-base\" must be an absolute path, got '{base}'"
+            )))
+        }
+        _ => {
+            return Err(OptionError::Generic(
+                "\"--incremental-key\" and \"--incremental-base\" must be used together".to_owned(),
+            ))
+        }
+    };
+
     let rustc_quit_on_rmeta = rustc_quit_on_rmeta_raw.is_some_and(|s| s == "true");
     let rustc_output_format = rustc_output_format_raw
         .map(|v| match v.as_str() {
@@ -212,9 +296,42 @@ pub(crate) fn options() -> Result<Options, OptionError> {
         output_file,
         rustc_quit_on_rmeta,
         rustc_output_format,
+        incremental,
+        unused_inputs,
     })
 }
 
+/// Expand the flagfiles among the options of the process wrapper, i.e. before `--`. Actions
+/// which may run in a persistent worker have to pass their arguments in a flagfile, which
+/// has to be expanded when they're run as a regular process instead. A flagfile may contain
+/// the `--` and the child process' arguments.
+fn expand_flagfiles(args: Vec<String>) -> Result<Vec<String>, OptionError> {
+    let mut expanded = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+    // The program name.
+    expanded.extend(args.next());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args.by_ref());
+            break;
+        }
+        match arg.strip_prefix('@') {
+            Some(path) => {
+                let mut contents = read_file_to_array(path).map_err(OptionError::Generic)?;
+                let has_child_arguments = contents.iter().any(|arg| arg == "--");
+                expanded.append(&mut contents);
+                if has_child_arguments {
+                    expanded.extend(args.by_ref());
+                    break;
+                }
+            }
+            None => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
+
 fn args_from_file(paths: Vec<String>) -> Result<Vec<String>, OptionError> {
     let mut args = vec![];
     for path in paths.iter() {
@@ -0,0 +1,662 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for running the process wrapper as a Bazel persistent worker, see
+//! https://bazel.build/remote/persistent.
+//!
+//! Bazel starts the worker with the action's arguments except its flagfiles, followed by
+//! `--persistent_worker`, and then sends the expanded flagfiles of each action as a
+//! `WorkRequest` on stdin. Each request is handled as if the process wrapper was run with
+//! those arguments, and answered by a `WorkResponse` on stdout with the exit code and the
+//! output of the child process. Requests with a non-zero id come from a multiplex worker and are handled
+//! concurrently.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Read, Write};
+use std::iter;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tinyjson::JsonValue;
+
+use crate::options::{options, OptionError};
+use crate::{run, ProcessWrapperError};
+
+/// Whether the process wrapper was started as a persistent worker.
+pub(crate) fn is_persistent_worker(args: &[String]) -> bool {
+    wrapper_args(args)
+        .iter()
+        .any(|arg| arg == "--persistent_worker")
+}
+
+/// The arguments of the process wrapper itself, before the `--` separating them from the
+/// command of the child process, which may use the same flags for its own purposes.
+fn wrapper_args(args: &[String]) -> &[String] {
+    match args.iter().position(|arg| arg == "--") {
+        Some(separator) => &args[..separator],
+        None => args,
+    }
+}
+
+/// The encoding of work requests and responses, set by the `requires-worker-protocol`
+/// execution requirement of the action and passed to the worker with `--worker-protocol`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Protocol {
+    Json,
+    Proto,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct WorkRequest {
+    arguments: Vec<String>,
+    request_id: i32,
+    cancel: bool,
+    sandbox_dir: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct WorkResponse {
+    exit_code: i32,
+    output: String,
+    request_id: i32,
+}
+
+pub(crate) fn run_worker(args: &[String]) -> Result<(), ProcessWrapperError> {
+    let protocol = worker_protocol(args)?;
+    let program_name = args.first().cloned().unwrap_or_default();
+
+    // Bazel starts a worker for each distinct set of startup arguments, so every argument of
+    // an action has to be in a flagfile for targets to share workers.
+    let startup_args = startup_args(args);
+    if !startup_args.is_empty() {
+        return Err(ProcessWrapperError(format!(
+            "persistent worker started with arguments which aren't in a flagfile: {:?}",
+            startup_args
+        )));
+    }
+
+    let stdin = io::stdin();
+    let mut requests = stdin.lock();
+    let responses = Arc::new(Mutex::new(io::stdout()));
+    let mut multiplex_requests: Vec<thread::JoinHandle<()>> = Vec::new();
+    loop {
+        let request = match read_request(&mut requests, protocol) {
+            Ok(Some(request)) => request,
+            // Bazel closes stdin to shut the worker down, requests which are still running
+            // are answered first.
+            Ok(None) => {
+                for handle in multiplex_requests {
+                    let _ = handle.join();
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(ProcessWrapperError(format!(
+                    "failed to read work request: {}",
+                    e
+                )))
+            }
+        };
+
+        // Bazel only cancels requests of workers with the `supports-worker-cancellation`
+        // execution requirement, which the process wrapper doesn't support.
+        if request.cancel {
+            continue;
+        }
+
+        if request.request_id == 0 {
+            let response = handle_request(&program_name, request);
+            write_response(&responses, protocol, &response)?;
+        } else {
+            let program_name = program_name.clone();
+            let responses = Arc::clone(&responses);
+            multiplex_requests.retain(|handle| !handle.is_finished());
+            multiplex_requests.push(thread::spawn(move || {
+                let response = handle_request(&program_name, request);
+                if let Err(e) = write_response(&responses, protocol, &response) {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }));
+        }
+    }
+}
+
+fn worker_protocol(args: &[String]) -> Result<Protocol, ProcessWrapperError> {
+    let protocol = wrapper_args(args)
+        .windows(2)
+        .find(|pair| pair[0] == "--worker-protocol")
+        .map(|pair| pair[1].as_str());
+    match protocol {
+        None | Some("proto") => Ok(Protocol::Proto),
+        Some("json") => Ok(Protocol::Json),
+        Some(protocol) => Err(ProcessWrapperError(format!(
+            "invalid --worker-protocol '{}'",
+            protocol
+        ))),
+    }
+}
+
+/// The arguments the worker was started with other than those controlling the worker
+/// itself, which are the arguments of the action which aren't in a flagfile.
+fn startup_args(args: &[String]) -> Vec<String> {
+    let mut startup_args = Vec::new();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--persistent_worker" => {}
+            "--worker-protocol" => {
+                args.next();
+            }
+            _ => startup_args.push(arg.clone()),
+        }
+    }
+    startup_args
+}
+
+fn handle_request(program_name: &str, request: WorkRequest) -> WorkResponse {
+    let mut output = Vec::new();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        execute_request(program_name, &request, &mut output)
+    }));
+    let exit_code = match result {
+        Ok(Ok(exit_code)) => exit_code,
+        Ok(Err(e)) => {
+            output.extend(format!("{}\n", e).into_bytes());
+            1
+        }
+        Err(_) => {
+            output.extend(b"process wrapper error: panicked while handling work request\n");
+            1
+        }
+    };
+
+    WorkResponse {
+        exit_code,
+        output: String::from_utf8_lossy(&output).into_owned(),
+        request_id: request.request_id,
+    }
+}
+
+fn execute_request(
+    program_name: &str,
+    request: &WorkRequest,
+    output: &mut Vec<u8>,
+) -> Result<i32, ProcessWrapperError> {
+    if !request.sandbox_dir.is_empty() {
+        return Err(ProcessWrapperError(
+            "multiplex sandboxing is not supported".to_owned(),
+        ));
+    }
+
+    let args = iter::once(program_name.to_owned())
+        .chain(request.arguments.iter().cloned())
+        .collect();
+    let opts = match options(args) {
+        Ok(opts) => opts,
+        Err(OptionError::Help(help)) => {
+            output.extend(help.into_bytes());
+            return Ok(0);
+        }
+        Err(e) => return Err(ProcessWrapperError(e.to_string())),
+    };
+
+    run(opts, Some(output))
+}
+
+fn read_request(reader: &mut impl BufRead, protocol: Protocol) -> io::Result<Option<WorkRequest>> {
+    match protocol {
+        Protocol::Json => read_json_request(reader),
+        Protocol::Proto => read_proto_request(reader),
+    }
+}
+
+fn write_response(
+    responses: &Mutex<io::Stdout>,
+    protocol: Protocol,
+    response: &WorkResponse,
+) -> Result<(), ProcessWrapperError> {
+    let encoded = match protocol {
+        Protocol::Json => encode_json_response(response)?,
+        Protocol::Proto => encode_proto_response(response),
+    };
+    let mut stdout = responses
+        .lock()
+        .map_err(|_| ProcessWrapperError("failed to lock stdout".to_owned()))?;
+    stdout
+        .write_all(&encoded)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| ProcessWrapperError(format!("failed to write work response: {}", e)))
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Read the next JSON encoded `WorkRequest`. Requests aren't necessarily on a single line,
+/// so this reads up to the end of the first complete object.
+fn read_json_request(reader: &mut impl BufRead) -> io::Result<Option<WorkRequest>> {
+    let mut message = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in reader.bytes() {
+        let byte = byte?;
+        if message.is_empty() {
+            if byte.is_ascii_whitespace() {
+                continue;
+            }
+            if byte != b'{' {
+                return Err(invalid_data("work request is not a JSON object"));
+            }
+        }
+        message.push(byte);
+
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return decode_json_request(&message).map(Some);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if message.is_empty() {
+        Ok(None)
+    } else {
+        Err(io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+fn decode_json_request(message: &[u8]) -> io::Result<WorkRequest> {
+    let message = std::str::from_utf8(message).map_err(|e| invalid_data(e.to_string()))?;
+    let value = message
+        .parse::<JsonValue>()
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let JsonValue::Object(fields) = value else {
+        return Err(invalid_data("work request is not a JSON object"));
+    };
+
+    let mut request = WorkRequest::default();
+    for (name, value) in fields {
+        match (name.as_str(), value) {
+            ("arguments", JsonValue::Array(arguments)) => {
+                request.arguments = arguments
+                    .into_iter()
+                    .map(|argument| match argument {
+                        JsonValue::String(argument) => Ok(argument),
+                        _ => Err(invalid_data("work request argument is not a string")),
+                    })
+                    .collect::<io::Result<_>>()?;
+            }
+            ("requestId", JsonValue::Number(request_id)) => request.request_id = request_id as i32,
+            ("cancel", JsonValue::Boolean(cancel)) => request.cancel = cancel,
+            ("sandboxDir", JsonValue::String(sandbox_dir)) => request.sandbox_dir = sandbox_dir,
+            // Other fields, like the inputs and verbosity, aren't used.
+            _ => {}
+        }
+    }
+    Ok(request)
+}
+
+fn encode_json_response(response: &WorkResponse) -> Result<Vec<u8>, ProcessWrapperError> {
+    let value = JsonValue::Object(HashMap::from([
+        (
+            "exitCode".to_owned(),
+            JsonValue::Number(response.exit_code as f64),
+        ),
+        (
+            "output".to_owned(),
+            JsonValue::String(response.output.clone()),
+        ),
+        (
+            "requestId".to_owned(),
+            JsonValue::Number(response.request_id as f64),
+        ),
+    ]));
+    let mut encoded = value
+        .stringify()
+        .map_err(|e| ProcessWrapperError(format!("failed to encode work response: {}", e)))?;
+    encoded.push('\n');
+    Ok(encoded.into_bytes())
+}
+
+/// Read a varint, or `None` if the reader is already at its end.
+fn read_varint(reader: &mut impl BufRead) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    for (index, byte) in reader.bytes().enumerate() {
+        let byte = byte?;
+        if index >= 10 {
+            return Err(invalid_data("varint is too long"));
+        }
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+const WIRE_TYPE_VARINT: u64 = 0;
+const WIRE_TYPE_FIXED64: u64 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u64 = 2;
+const WIRE_TYPE_FIXED32: u64 = 5;
+
+/// Read the next length delimited, protobuf encoded `WorkRequest`.
+fn read_proto_request(reader: &mut impl BufRead) -> io::Result<Option<WorkRequest>> {
+    let Some(length) = read_varint(reader)? else {
+        return Ok(None);
+    };
+    let length = usize::try_from(length).map_err(|e| invalid_data(e.to_string()))?;
+    let mut message = vec![0; length];
+    reader.read_exact(&mut message)?;
+    decode_proto_request(&message).map(Some)
+}
+
+fn decode_proto_request(mut message: &[u8]) -> io::Result<WorkRequest> {
+    let truncated = || invalid_data("work request is truncated");
+    let mut request = WorkRequest::default();
+    while let Some(key) = read_varint(&mut message)? {
+        let (field, wire_type) = (key >> 3, key & 0x7);
+        match wire_type {
+            WIRE_TYPE_VARINT => {
+                let value = read_varint(&mut message)?.ok_or_else(truncated)?;
+                match field {
+                    3 => request.request_id = value as i32,
+                    4 => request.cancel = value != 0,
+                    // The verbosity isn't used.
+                    _ => {}
+                }
+            }
+            WIRE_TYPE_LENGTH_DELIMITED => {
+                let length = read_varint(&mut message)?.ok_or_else(truncated)?;
+                let length = usize::try_from(length).map_err(|e| invalid_data(e.to_string()))?;
+                if length > message.len() {
+                    return Err(truncated());
+                }
+                let (value, rest) = message.split_at(length);
+                message = rest;
+                let string =
+                    || String::from_utf8(value.to_vec()).map_err(|e| invalid_data(e.to_string()));
+                match field {
+                    1 => request.arguments.push(string()?),
+                    6 => request.sandbox_dir = string()?,
+                    // The inputs aren't used.
+                    _ => {}
+                }
+            }
+            WIRE_TYPE_FIXED64 | WIRE_TYPE_FIXED32 => {
+                let length = if wire_type == WIRE_TYPE_FIXED64 { 8 } else { 4 };
+                if length > message.len() {
+                    return Err(truncated());
+                }
+                message = &message[length..];
+            }
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported wire type {} in work request",
+                    wire_type
+                )))
+            }
+        }
+    }
+    Ok(request)
+}
+
+/// Encode a `WorkResponse`, prefixed with its length. Fields with default values are omitted.
+fn encode_proto_response(response: &WorkResponse) -> Vec<u8> {
+    let mut message = Vec::new();
+    if response.exit_code != 0 {
+        write_varint(&mut message, 1 << 3 | WIRE_TYPE_VARINT);
+        // Negative numbers are sign extended to 64 bits.
+        write_varint(&mut message, i64::from(response.exit_code) as u64);
+    }
+    if !response.output.is_empty() {
+        write_varint(&mut message, 2 << 3 | WIRE_TYPE_LENGTH_DELIMITED);
+        write_varint(&mut message, response.output.len() as u64);
+        message.extend(response.output.as_bytes());
+    }
+    if response.request_id != 0 {
+        write_varint(&mut message, 3 << 3 | WIRE_TYPE_VARINT);
+        write_varint(&mut message, i64::from(response.request_id) as u64);
+    }
+
+    let mut encoded = Vec::with_capacity(message.len() + 5);
+    write_varint(&mut encoded, message.len() as u64);
+    encoded.extend(message);
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_worker_protocol() {
+        assert!(is_persistent_worker(&args(&[
+            "process_wrapper",
+            "--persistent_worker"
+        ])));
+        assert!(!is_persistent_worker(&args(&[
+            "process_wrapper",
+            "@flagfile"
+        ])));
+
+        assert_eq!(
+            worker_protocol(&args(&["process_wrapper", "--persistent_worker"])).unwrap(),
+            Protocol::Proto
+        );
+        assert_eq!(
+            worker_protocol(&args(&[
+                "process_wrapper",
+                "--worker-protocol",
+                "json",
+                "--persistent_worker"
+            ]))
+            .unwrap(),
+            Protocol::Json
+        );
+        assert!(worker_protocol(&args(&["process_wrapper", "--worker-protocol", "xml"])).is_err());
+    }
+
+    #[test]
+    fn test_worker_flags_of_child_are_ignored() {
+        let child_args = args(&[
+            "process_wrapper",
+            "--",
+            "tool",
+            "--worker-protocol",
+            "xml",
+            "--persistent_worker",
+        ]);
+        assert!(!is_persistent_worker(&child_args));
+        assert_eq!(worker_protocol(&child_args).unwrap(), Protocol::Proto);
+    }
+
+    #[test]
+    fn test_startup_args() {
+        // Arguments of an action which aren't in a flagfile are passed when the worker starts,
+        // and would give each target a worker of its own.
+        assert_eq!(
+            startup_args(&args(&[
+                "process_wrapper",
+                "--edition=2021",
+                "lib.rs",
+                "--worker-protocol",
+                "json",
+                "--persistent_worker",
+            ])),
+            args(&["--edition=2021", "lib.rs"])
+        );
+        assert!(run_worker(&args(&[
+            "process_wrapper",
+            "--edition=2021",
+            "--persistent_worker"
+        ]))
+        .is_err());
+        assert!(startup_args(&args(&[
+            "process_wrapper",
+            "--worker-protocol",
+            "json",
+            "--persistent_worker"
+        ]))
+        .is_empty());
+    }
+
+    #[test]
+    fn test_read_json_requests() {
+        let mut input = io::Cursor::new(
+            r#"{"arguments": ["--touch-file", "{}\"", "--", "rustc"], "requestId": 3}
+            {
+              "arguments": [],
+              "inputs": [{"path": "lib.rs", "digest": "AAAA"}],
+              "cancel": true
+            }"#,
+        );
+
+        assert_eq!(
+            read_json_request(&mut input).unwrap(),
+            Some(WorkRequest {
+                arguments: args(&["--touch-file", "{}\"", "--", "rustc"]),
+                request_id: 3,
+                ..WorkRequest::default()
+            })
+        );
+        assert_eq!(
+            read_json_request(&mut input).unwrap(),
+            Some(WorkRequest {
+                cancel: true,
+                ..WorkRequest::default()
+            })
+        );
+        assert_eq!(read_json_request(&mut input).unwrap(), None);
+
+        let mut truncated = io::Cursor::new(r#"{"arguments": ["#);
+        assert!(read_json_request(&mut truncated).is_err());
+    }
+
+    #[test]
+    fn test_encode_json_response() {
+        let encoded = encode_json_response(&WorkResponse {
+            exit_code: 1,
+            output: "error: \"oops\"\n".to_owned(),
+            request_id: 7,
+        })
+        .unwrap();
+        let encoded = String::from_utf8(encoded).unwrap();
+        assert!(encoded.ends_with('\n'));
+        assert_eq!(
+            encoded.trim_end().parse::<JsonValue>().unwrap(),
+            r#"{"exitCode": 1, "output": "error: \"oops\"\n", "requestId": 7}"#
+                .parse::<JsonValue>()
+                .unwrap()
+        );
+    }
+
+    fn encode_proto_request(request: &WorkRequest, verbosity: u64) -> Vec<u8> {
+        let mut message = Vec::new();
+        for argument in &request.arguments {
+            write_varint(&mut message, 1 << 3 | WIRE_TYPE_LENGTH_DELIMITED);
+            write_varint(&mut message, argument.len() as u64);
+            message.extend(argument.as_bytes());
+        }
+        // An input, with a path and a digest.
+        write_varint(&mut message, 2 << 3 | WIRE_TYPE_LENGTH_DELIMITED);
+        write_varint(&mut message, 11);
+        message.extend(b"\x0a\x06lib.rs\x12\x01\xff");
+        write_varint(&mut message, 3 << 3 | WIRE_TYPE_VARINT);
+        write_varint(&mut message, request.request_id as u64);
+        write_varint(&mut message, 5 << 3 | WIRE_TYPE_VARINT);
+        write_varint(&mut message, verbosity);
+
+        let mut encoded = Vec::new();
+        write_varint(&mut encoded, message.len() as u64);
+        encoded.extend(message);
+        encoded
+    }
+
+    #[test]
+    fn test_read_proto_requests() {
+        let first = WorkRequest {
+            arguments: args(&["--", "rustc", &"a".repeat(200)]),
+            request_id: 1,
+            ..WorkRequest::default()
+        };
+        let second = WorkRequest {
+            arguments: args(&["--", "rustc"]),
+            request_id: 300,
+            ..WorkRequest::default()
+        };
+        let mut input = encode_proto_request(&first, 10);
+        input.extend(encode_proto_request(&second, 0));
+        let mut input = io::Cursor::new(input);
+
+        assert_eq!(read_proto_request(&mut input).unwrap(), Some(first));
+        assert_eq!(read_proto_request(&mut input).unwrap(), Some(second));
+        assert_eq!(read_proto_request(&mut input).unwrap(), None);
+
+        let mut truncated = io::Cursor::new(vec![5, 0x0a, 10]);
+        assert!(read_proto_request(&mut truncated).is_err());
+    }
+
+    #[test]
+    fn test_encode_proto_response() {
+        assert_eq!(encode_proto_response(&WorkResponse::default()), vec![0]);
+        assert_eq!(
+            encode_proto_response(&WorkResponse {
+                exit_code: 1,
+                output: "out".to_owned(),
+                request_id: 150,
+            }),
+            vec![10, 0x08, 1, 0x12, 3, b'o', b'u', b't', 0x18, 0x96, 0x01]
+        );
+
+        let encoded = encode_proto_response(&WorkResponse {
+            exit_code: -1,
+            ..WorkResponse::default()
+        });
+        assert_eq!(encoded[0], 11);
+        assert_eq!(&encoded[1..3], &[0x08, 0xff]);
+        assert_eq!(encoded[11], 0x01);
+    }
+}